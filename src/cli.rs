@@ -0,0 +1,271 @@
+use crate::config::{check_config, validate_config, ConfigFormat, DEFAULT_CONFIG_PATH};
+use crate::formatter::roa::{format_roa, RoaFormat};
+use crate::parser::route::DiagnosticAction;
+use crate::scheduler::check_task_settings;
+use crate::task::dns::GenerateDNSAuthoritativeZonesTask;
+use crate::task::roa::GenerateRoaTask;
+use crate::task::{default_tasks, Task};
+use crate::{AppConfig, AppState, ConfigHandle};
+use anyhow::{anyhow, bail, Context};
+use clap::{Parser, Subcommand};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use tracing::info;
+
+#[derive(Parser, Debug)]
+#[command(version, about = "ROA and DNS zone generator for the dn42 registry")]
+pub struct Cli {
+    #[arg(short, long, global = true, env = "CONFIG_PATH", default_value = DEFAULT_CONFIG_PATH)]
+    pub config: PathBuf,
+
+    // Defaults to `serve` when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the HTTP server and the background task scheduler
+    Serve,
+    /// Generate output once from a local registry checkout and exit
+    Generate {
+        #[command(subcommand)]
+        target: GenerateTarget,
+    },
+    /// Check the configuration and parse the registry, reporting every problem found
+    #[command(alias = "check-config")]
+    Validate {
+        /// Configuration file to check, overrides --config
+        config_file: Option<PathBuf>,
+        /// Registry checkout to parse instead of git_repo_local_path
+        #[arg(long)]
+        registry: Option<PathBuf>,
+    },
+    /// Print the default configuration
+    PrintDefaultConfig {
+        #[arg(short, long, default_value = "json", value_parser = ConfigFormat::from_str)]
+        format: ConfigFormat,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GenerateTarget {
+    /// Generate the ROA set
+    Roa {
+        #[arg(short, long, default_value = "json", value_parser = RoaFormat::from_str)]
+        format: RoaFormat,
+        /// Registry checkout to read instead of git_repo_local_path
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        /// Git revision (commit, tag or branch) to generate from instead of the working tree
+        #[arg(long)]
+        revision: Option<String>,
+        /// Output file, standard output when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Generate forward and reverse DNS zone files
+    Zones {
+        /// Registry checkout to read instead of git_repo_local_path
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        /// Git revision (commit, tag or branch) to generate from instead of the working tree
+        #[arg(long)]
+        revision: Option<String>,
+        /// Directory receiving one <zone>.zone file per zone
+        #[arg(long)]
+        out_dir: PathBuf,
+    },
+}
+
+fn load_config(config_path: &Path, registry: Option<&Path>) -> Result<AppConfig, Vec<String>> {
+    let mut config = check_config(config_path)?;
+
+    // One-shot commands never touch the remote repository
+    config.do_git_pull = false;
+
+    if let Some(registry) = registry {
+        config.git_repo_local_path = registry.display().to_string();
+        validate_config(&config)?;
+    }
+
+    Ok(config)
+}
+
+fn one_shot_state(config_path: &Path, registry: Option<&Path>, revision: Option<&str>) -> anyhow::Result<AppState> {
+    let mut config = load_config(config_path, registry)
+        .map_err(|problems| anyhow!("Invalid configuration {:?}:\n  - {}", config_path, problems.join("\n  - ")))?;
+
+    if let Some(revision) = revision {
+        config = config.at_revision(revision);
+    }
+
+    if !Path::new(&config.git_repo_local_path).is_dir() {
+        bail!("Registry {:?} does not exist or is not a directory", config.git_repo_local_path);
+    }
+
+    Ok(AppState {
+        config: ConfigHandle::new(config),
+        ..Default::default()
+    })
+}
+
+pub fn generate_roa(config_path: &Path, format: RoaFormat, registry: Option<&Path>, revision: Option<&str>, output: Option<&Path>) -> anyhow::Result<()> {
+    let state = one_shot_state(config_path, registry, revision)?;
+
+    GenerateRoaTask::new(state.clone()).run()?;
+
+    let data = state.roa_data.read().unwrap();
+    let content = format_roa(&data.output, format, state.config.get().roa_json_style)?;
+
+    match output {
+        Some(output) => {
+            std::fs::write(output, content).with_context(|| format!("Failed to write {:?}", output))?;
+            info!("Wrote {} ROAs to {:?}.", data.output.roas.len(), output);
+        }
+        None => std::io::stdout().write_all(content.as_bytes())?,
+    }
+
+    Ok(())
+}
+
+pub fn generate_zones(config_path: &Path, registry: Option<&Path>, revision: Option<&str>, out_dir: &Path) -> anyhow::Result<()> {
+    let state = one_shot_state(config_path, registry, revision)?;
+
+    GenerateDNSAuthoritativeZonesTask::new(state.clone()).run()?;
+
+    std::fs::create_dir_all(out_dir).with_context(|| format!("Failed to create {:?}", out_dir))?;
+
+    let data = state.dns_data.read().unwrap();
+
+    for (zone_name, content) in &data.content {
+        let path = out_dir.join(format!("{}.zone", zone_name.trim_end_matches('.')));
+        std::fs::write(&path, content.content()).with_context(|| format!("Failed to write {:?}", path))?;
+    }
+
+    info!("Wrote {} zones to {:?}.", data.content.len(), out_dir);
+
+    Ok(())
+}
+
+// Returns the route objects that were rejected, corrections are only printed
+fn validate_registry(state: &AppState) -> anyhow::Result<Vec<String>> {
+    GenerateRoaTask::new(state.clone()).run()?;
+    GenerateDNSAuthoritativeZonesTask::new(state.clone()).run()?;
+
+    println!("Registry {:?}: {} ROAs, {} DNS zones",
+        state.config.get().git_repo_local_path,
+        state.roa_data.read().unwrap().output.roas.len(),
+        state.dns_data.read().unwrap().zones.len());
+
+    let mut rejected = Vec::new();
+
+    for diagnostic in &state.roa_data.read().unwrap().diagnostics {
+        match diagnostic.action {
+            DiagnosticAction::Rejected => rejected.push(format!("{}: {}", diagnostic.file, diagnostic.message)),
+            DiagnosticAction::Corrected | DiagnosticAction::Flagged => println!("{}: {}", diagnostic.file, diagnostic.message),
+        }
+    }
+
+    // Delegation problems do not stop zone generation, so they are only printed
+    for problem in &state.dns_data.read().unwrap().lint {
+        println!("{}: {}", problem.file, problem.message);
+    }
+
+    Ok(rejected)
+}
+
+// Prints every problem at once instead of stopping at the first one
+pub fn validate(config_path: &Path, registry: Option<&Path>) -> ExitCode {
+    let mut problems = match load_config(config_path, registry) {
+        Ok(config) => {
+            let state = AppState {
+                config: ConfigHandle::new(config.clone()),
+                ..Default::default()
+            };
+
+            let mut problems = Vec::new();
+
+            if let Err(e) = check_task_settings(&default_tasks(&state), &config) {
+                problems.push(e.to_string());
+            }
+
+            // Without a checkout only the configuration itself can be checked
+            if Path::new(&config.git_repo_local_path).is_dir() {
+                match validate_registry(&state) {
+                    Ok(rejected) => problems.extend(rejected),
+                    Err(e) => problems.push(format!("{:#}", e)),
+                }
+            } else if registry.is_some() {
+                problems.push(format!("Registry {:?} does not exist or is not a directory", config.git_repo_local_path));
+            }
+
+            problems
+        }
+        Err(problems) => problems,
+    };
+
+    if problems.is_empty() {
+        println!("Configuration {:?} OK", config_path);
+        return ExitCode::SUCCESS;
+    }
+
+    problems.sort();
+    problems.dedup();
+
+    eprintln!("Configuration {:?} has {} problem(s):", config_path, problems.len());
+
+    for problem in problems {
+        eprintln!("  - {}", problem);
+    }
+
+    ExitCode::FAILURE
+}
+
+pub fn print_default_config(format: ConfigFormat) -> anyhow::Result<()> {
+    print!("{}", format.serialize(&AppConfig::default())?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_generate_roa() {
+        let cli = Cli::try_parse_from([
+            "dn42-roa-generator", "generate", "roa", "--format", "bird2", "--registry", "./registry", "--revision", "v1", "-o", "roa.conf",
+        ]).unwrap();
+
+        match cli.command {
+            Some(Command::Generate { target: GenerateTarget::Roa { format, registry, revision, output } }) => {
+                assert_eq!(format, RoaFormat::Bird2);
+                assert_eq!(registry, Some(PathBuf::from("./registry")));
+                assert_eq!(revision.as_deref(), Some("v1"));
+                assert_eq!(output, Some(PathBuf::from("roa.conf")));
+            }
+            command => panic!("Unexpected command {:?}", command),
+        }
+
+        assert!(Cli::try_parse_from(["dn42-roa-generator", "generate", "roa", "--format", "bird1"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_default_and_aliases() {
+        assert!(Cli::try_parse_from(["dn42-roa-generator"]).unwrap().command.is_none());
+
+        let cli = Cli::try_parse_from(["dn42-roa-generator", "check-config", "config.toml"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Validate { config_file: Some(_), .. })));
+
+        assert!(Cli::try_parse_from(["dn42-roa-generator", "generate", "zones"]).is_err());
+    }
+}
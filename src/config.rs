@@ -0,0 +1,654 @@
+use crate::model::dns::FQDNName;
+use crate::scheduler::check_task_settings;
+use crate::server::{build_router, routes};
+use crate::task::Task;
+use crate::{AppConfig, AppState};
+use anyhow::anyhow;
+use axum::Router;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use strum::{Display, EnumString};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info, warn};
+
+pub const DEFAULT_CONFIG_PATH: &str = "config.json";
+
+// Every AppConfig field can be overridden with DN42_ROA_<FIELD_NAME_IN_UPPERCASE>,
+// e.g. DN42_ROA_LISTEN_ADDRESS=127.0.0.1:8080 or DN42_ROA_TASKS='{"generate_dns":{"enabled":false}}'
+pub const ENV_PREFIX: &str = "DN42_ROA_";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum ConfigFormat {
+    #[strum(serialize = "json")]
+    Json,
+    #[strum(serialize = "toml")]
+    Toml,
+    #[strum(serialize = "yaml", serialize = "yml")]
+    Yaml,
+}
+
+impl ConfigFormat {
+    // Unknown or missing extensions are treated as JSON, the historical format
+    pub fn from_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| ConfigFormat::from_str(e).ok())
+            .unwrap_or(ConfigFormat::Json)
+    }
+
+    fn parse(&self, content: &str) -> Result<Value, String> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn serialize(&self, config: &AppConfig) -> anyhow::Result<String> {
+        Ok(match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(config)?,
+            ConfigFormat::Toml => toml::to_string_pretty(config)?,
+            ConfigFormat::Yaml => serde_yaml::to_string(config)?,
+        })
+    }
+}
+
+fn default_config_value() -> Map<String, Value> {
+    match serde_json::to_value(AppConfig::default()) {
+        Ok(Value::Object(map)) => map,
+        _ => unreachable!("AppConfig always serializes to an object"),
+    }
+}
+
+fn check_unknown_keys(value: &Map<String, Value>, problems: &mut Vec<String>) {
+    let known = default_config_value();
+
+    for key in value.keys() {
+        if !known.contains_key(key) {
+            problems.push(format!("Unknown configuration key '{}'", key));
+        }
+    }
+}
+
+fn apply_env_overrides(value: &mut Map<String, Value>, lookup: impl Fn(&str) -> Option<String>, problems: &mut Vec<String>) {
+    for (key, default_value) in default_config_value() {
+        let variable = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+
+        let raw = match lookup(&variable) {
+            Some(raw) => raw,
+            None => continue,
+        };
+
+        // Strings are taken verbatim, everything else has to be valid JSON
+        let parsed = match default_value {
+            Value::String(_) => Ok(Value::String(raw)),
+            _ => serde_json::from_str::<Value>(&raw).map_err(|e| e.to_string()),
+        };
+
+        match parsed {
+            Ok(parsed) => {
+                value.insert(key, parsed);
+            }
+            Err(e) => problems.push(format!("Environment variable {}: {}", variable, e)),
+        }
+    }
+}
+
+fn parse_config_file(config_path: &Path, problems: &mut Vec<String>) -> Map<String, Value> {
+    if !config_path.exists() {
+        info!("Configuration file {:?} does not exist. Using default configuration.", config_path);
+        return Map::new();
+    }
+
+    let content = match std::fs::read_to_string(config_path) {
+        Ok(content) => content,
+        Err(e) => {
+            problems.push(format!("Failed to read configuration file {:?}: {}", config_path, e));
+            return Map::new();
+        }
+    };
+
+    let format = ConfigFormat::from_path(config_path);
+
+    match format.parse(&content) {
+        Ok(Value::Object(map)) => map,
+        Ok(_) => {
+            problems.push(format!("Configuration file {:?} must contain a {} table/object at the top level", config_path, format));
+            Map::new()
+        }
+        Err(e) => {
+            problems.push(format!("Failed to parse {:?} as {}: {}", config_path, format, e));
+            Map::new()
+        }
+    }
+}
+
+// Loads the configuration file, applies environment overrides and validates the result.
+// All problems are collected so they can be reported at once.
+pub fn check_config(config_path: &Path) -> Result<AppConfig, Vec<String>> {
+    check_config_with_env(config_path, |name| std::env::var(name).ok())
+}
+
+fn check_config_with_env(config_path: &Path, lookup: impl Fn(&str) -> Option<String>) -> Result<AppConfig, Vec<String>> {
+    let mut problems = Vec::new();
+
+    let mut value = parse_config_file(config_path, &mut problems);
+
+    check_unknown_keys(&value, &mut problems);
+    apply_env_overrides(&mut value, lookup, &mut problems);
+
+    // Drop unknown keys so that deserialization reports type errors only
+    let known = default_config_value();
+    value.retain(|key, _| known.contains_key(key));
+
+    let config: AppConfig = match serde_path_to_error::deserialize(Value::Object(value)) {
+        Ok(config) => config,
+        Err(e) => {
+            problems.push(format!("Invalid value for '{}': {}", e.path(), e.inner()));
+            return Err(problems);
+        }
+    };
+
+    if let Err(validation_problems) = validate_config(&config) {
+        problems.extend(validation_problems);
+    }
+
+    if problems.is_empty() { Ok(config) } else { Err(problems) }
+}
+
+pub fn load_config(config_path: &Path) -> anyhow::Result<AppConfig> {
+    check_config(config_path).map_err(|problems| {
+        anyhow!("Invalid configuration {:?}:\n  - {}", config_path, problems.join("\n  - "))
+    })
+}
+
+fn validate_endpoint(name: &str, endpoint: &str, problems: &mut Vec<String>) {
+    if !endpoint.starts_with('/') {
+        problems.push(format!("{} '{}' must start with '/'", name, endpoint));
+    }
+
+    if endpoint.len() > 1 && endpoint.ends_with('/') {
+        problems.push(format!("{} '{}' must not end with '/'", name, endpoint));
+    }
+
+    if let Some(c) = endpoint.chars().find(|c| c.is_whitespace() || matches!(c, '{' | '}' | '*' | '?' | '#')) {
+        problems.push(format!("{} '{}' contains invalid character {:?}", name, endpoint, c));
+    }
+}
+
+fn validate_relative_path(name: &str, repo_path: &Path, relative_path: &str, problems: &mut Vec<String>) {
+    let path = Path::new(relative_path);
+
+    if path.is_absolute() {
+        problems.push(format!("{} '{}' must be relative to git_repo_local_path", name, relative_path));
+        return;
+    }
+
+    if path.components().any(|c| c == std::path::Component::ParentDir) {
+        problems.push(format!("{} '{}' must not leave the repository with '..'", name, relative_path));
+        return;
+    }
+
+    // The repository may not have been cloned yet, in that case there is nothing to check
+    if repo_path.is_dir() && !repo_path.join(path).is_dir() {
+        problems.push(format!("{} '{}' does not exist in {:?}", name, relative_path, repo_path));
+    }
+}
+
+// Whether some request path matches both route patterns, {param} matches one segment
+// and {*rest} the remaining ones
+fn routes_overlap(a: &[&str], b: &[&str]) -> bool {
+    let is_param = |segment: &str| segment.starts_with('{');
+    let is_wildcard = |segment: &str| segment.starts_with("{*");
+
+    match (a.split_first(), b.split_first()) {
+        (None, None) => true,
+        (Some((first, _)), _) if is_wildcard(first) => !b.is_empty(),
+        (_, Some((first, _))) if is_wildcard(first) => !a.is_empty(),
+        (Some((first_a, rest_a)), Some((first_b, rest_b))) => {
+            (first_a == first_b || is_param(first_a) || is_param(first_b)) && routes_overlap(rest_a, rest_b)
+        }
+        _ => false,
+    }
+}
+
+fn validate_routes(config: &AppConfig, problems: &mut Vec<String>) {
+    let routes = routes(config);
+    let segments = routes.iter().map(|(_, path, _)| path.split('/').skip(1).collect::<Vec<_>>()).collect::<Vec<_>>();
+    let mut reported = HashSet::new();
+
+    for (i, (name_a, path_a, _)) in routes.iter().enumerate() {
+        for (j, (name_b, path_b, _)) in routes.iter().enumerate().skip(i + 1) {
+            // Routes of one endpoint are laid out to fit together, e.g. the RDAP not found fallback
+            if name_a == name_b || !routes_overlap(&segments[i], &segments[j]) {
+                continue;
+            }
+
+            if reported.insert((*name_a, *name_b)) {
+                problems.push(format!("{} route '{}' overlaps {} route '{}'", name_a, path_a, name_b, path_b));
+            }
+        }
+    }
+}
+
+// Returns every problem found instead of stopping at the first one.
+pub fn validate_config(config: &AppConfig) -> Result<(), Vec<String>> {
+    let mut problems = Vec::new();
+
+    if config.listen_address.parse::<SocketAddr>().is_err() {
+        problems.push(format!("listen_address '{}' is not a valid socket address (expected e.g. 0.0.0.0:8080)", config.listen_address));
+    }
+
+    if !config.whois_listen_address.is_empty() && config.whois_listen_address.parse::<SocketAddr>().is_err() {
+        problems.push(format!("whois_listen_address '{}' is not a valid socket address (expected e.g. 0.0.0.0:43)", config.whois_listen_address));
+    }
+
+    if !config.dns_health_resolver.is_empty() {
+        if config.dns_health_resolver.parse::<SocketAddr>().is_err() {
+            problems.push(format!("dns_health_resolver '{}' is not a valid socket address (expected e.g. 172.20.0.53:53)", config.dns_health_resolver));
+        }
+
+        if config.dns_health_timeout_seconds == 0 {
+            problems.push("dns_health_timeout_seconds must be greater than 0".to_string());
+        }
+
+        if config.dns_health_concurrency == 0 {
+            problems.push("dns_health_concurrency must be greater than 0".to_string());
+        }
+    }
+
+    let endpoints = [
+        ("roa_endpoint", &config.roa_endpoint),
+        ("dns_config_endpoint", &config.dns_config_endpoint),
+        ("dns_content_endpoint_directory", &config.dns_content_endpoint_directory),
+        ("status_endpoint", &config.status_endpoint),
+        ("rdap_endpoint_directory", &config.rdap_endpoint_directory),
+        ("lookup_endpoint_directory", &config.lookup_endpoint_directory),
+        ("asn_endpoint_directory", &config.asn_endpoint_directory),
+        ("mntner_endpoint_directory", &config.mntner_endpoint_directory),
+        ("roa_diagnostics_endpoint", &config.roa_diagnostics_endpoint),
+        ("dns_lint_endpoint", &config.dns_lint_endpoint),
+        ("dns_health_endpoint_directory", &config.dns_health_endpoint_directory),
+        ("filter_endpoint_directory", &config.filter_endpoint_directory),
+        ("history_endpoint_directory", &config.history_endpoint_directory),
+        ("events_endpoint", &config.events_endpoint),
+        ("events_websocket_endpoint", &config.events_websocket_endpoint),
+        ("admin_refresh_endpoint", &config.admin_refresh_endpoint),
+        ("webhook_endpoint", &config.webhook_endpoint),
+    ];
+
+    let endpoint_problems = problems.len();
+    let mut seen = HashSet::new();
+
+    for (name, endpoint) in endpoints {
+        validate_endpoint(name, endpoint, &mut problems);
+
+        if !seen.insert(endpoint.as_str()) {
+            problems.push(format!("{} '{}' is used by more than one endpoint", name, endpoint));
+        }
+    }
+
+    // Overlaps are only meaningful between well-formed, distinct endpoints
+    if problems.len() == endpoint_problems {
+        validate_routes(config, &mut problems);
+    }
+
+    if config.update_interval_seconds == 0 {
+        problems.push("update_interval_seconds must be greater than 0".to_string());
+    }
+
+    for (name, value) in [
+        ("dns_primary_master", &config.dns_primary_master),
+        ("dns_responsible_party", &config.dns_responsible_party),
+    ] {
+        if let Err(e) = FQDNName::new(value) {
+            problems.push(format!("{} '{}' is not a valid domain name: {}", name, value, e));
+        }
+    }
+
+    if !config.rdap_base_url.is_empty() && !config.rdap_base_url.starts_with("http://") && !config.rdap_base_url.starts_with("https://") {
+        problems.push(format!("rdap_base_url '{}' must be an absolute http(s) URL", config.rdap_base_url));
+    }
+
+    if config.git_repo_url.trim().is_empty() {
+        problems.push("git_repo_url must not be empty".to_string());
+    }
+
+    if config.git_repo_local_path.trim().is_empty() {
+        problems.push("git_repo_local_path must not be empty".to_string());
+    }
+
+    if config.roa_trust_anchor.trim().is_empty() || config.roa_trust_anchor.contains(',') {
+        problems.push(format!("roa_trust_anchor '{}' must be a non-empty name without commas", config.roa_trust_anchor));
+    }
+
+    let mut source_names = HashSet::new();
+
+    for (i, source) in config.roa_sources.iter().enumerate() {
+        if source.name.trim().is_empty() {
+            problems.push(format!("roa_sources[{}] must have a name", i));
+        } else if !source_names.insert(&source.name) {
+            problems.push(format!("roa_sources[{}] name '{}' is used more than once", i, source.name));
+        }
+
+        if source.location.trim().is_empty() {
+            problems.push(format!("roa_sources[{}] must have a location", i));
+        }
+
+        if source.trust_anchor.contains(',') {
+            problems.push(format!("roa_sources[{}] trust_anchor '{}' must not contain commas", i, source.trust_anchor));
+        }
+    }
+
+    let repo_path = Path::new(&config.git_repo_local_path);
+
+    for (name, relative_path) in [
+        ("git_repo_ipv4_route_relative_path", &config.git_repo_ipv4_route_relative_path),
+        ("git_repo_ipv6_route_relative_path", &config.git_repo_ipv6_route_relative_path),
+        ("git_repo_dns_relative_path", &config.git_repo_dns_relative_path),
+        ("git_repo_inetnum_relative_path", &config.git_repo_inetnum_relative_path),
+        ("git_repo_inet6num_relative_path", &config.git_repo_inet6num_relative_path),
+        ("git_repo_data_relative_path", &config.git_repo_data_relative_path),
+        ("git_repo_aut_num_relative_path", &config.git_repo_aut_num_relative_path),
+    ] {
+        validate_relative_path(name, repo_path, relative_path, &mut problems);
+    }
+
+    if problems.is_empty() { Ok(()) } else { Err(problems) }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ReloadStatus {
+    pub config_path: String,
+    // Incremented on every successfully applied configuration
+    pub generation: u64,
+    pub last_attempt: Option<String>,
+    pub last_success: Option<String>,
+    pub last_error: Option<String>,
+}
+
+pub struct ConfigReloader {
+    config_path: PathBuf,
+    state: AppState,
+    router: Arc<RwLock<Router>>,
+    tasks: Vec<Arc<dyn Task>>,
+    last_modified: Option<SystemTime>,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl ConfigReloader {
+    pub fn new(config_path: PathBuf, state: AppState, router: Arc<RwLock<Router>>, tasks: Vec<Arc<dyn Task>>) -> Self {
+        let last_modified = modified_time(&config_path);
+
+        state.reload_status.write().unwrap().config_path = config_path.display().to_string();
+
+        ConfigReloader {
+            config_path,
+            state,
+            router,
+            tasks,
+            last_modified,
+        }
+    }
+
+    fn try_reload(&self) -> anyhow::Result<()> {
+        let new_config = load_config(&self.config_path)?;
+
+        check_task_settings(&self.tasks, &new_config)?;
+
+        let current_config = self.state.config.get();
+
+        if new_config.listen_address != current_config.listen_address {
+            warn!("listen_address changed from {} to {}, this only takes effect after a restart.", current_config.listen_address, new_config.listen_address);
+        }
+
+        if new_config.whois_listen_address != current_config.whois_listen_address {
+            warn!("whois_listen_address changed from '{}' to '{}', this only takes effect after a restart.", current_config.whois_listen_address, new_config.whois_listen_address);
+        }
+
+        let new_router = build_router(self.state.clone(), &new_config);
+
+        // Swap routes and configuration together so requests never see a mix of both
+        let mut router_lock = self.router.write().unwrap();
+        *router_lock = new_router;
+        self.state.config.replace(new_config);
+        drop(router_lock);
+
+        // Regenerate outputs that depend on the configuration
+        self.state.refresh_trigger.notify_one();
+
+        Ok(())
+    }
+
+    pub fn reload(&self, reason: &str) {
+        info!("Reloading configuration from {:?} ({}).", self.config_path, reason);
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let result = self.try_reload();
+
+        let mut status = self.state.reload_status.write().unwrap();
+        status.last_attempt = Some(now.clone());
+
+        match result {
+            Ok(()) => {
+                status.generation += 1;
+                status.last_success = Some(now);
+                status.last_error = None;
+
+                info!("Configuration reloaded (generation {}).", status.generation);
+            }
+            Err(e) => {
+                status.last_error = Some(format!("{:#}", e));
+
+                error!("Failed to reload configuration, keeping the current one: {:#}", e);
+            }
+        }
+    }
+
+    pub async fn run(mut self) {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => Some(hangup),
+            Err(e) => {
+                warn!("Failed to listen for SIGHUP, reload on signal is disabled: {:?}", e);
+                None
+            }
+        };
+
+        loop {
+            let watch_interval = self.state.config.get().config_watch_interval_seconds;
+
+            let poll = async {
+                match watch_interval {
+                    0 => std::future::pending().await,
+                    seconds => tokio::time::sleep(Duration::from_secs(seconds)).await,
+                }
+            };
+
+            let hangup_received = async {
+                match hangup.as_mut() {
+                    Some(hangup) => hangup.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = hangup_received => {
+                    self.last_modified = modified_time(&self.config_path);
+                    self.reload("SIGHUP");
+                }
+                _ = poll => {
+                    let modified = modified_time(&self.config_path);
+
+                    if modified.is_some() && modified != self.last_modified {
+                        self.last_modified = modified;
+                        self.reload("file changed");
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(validate_config(&AppConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_reports_all_problems() {
+        let config = AppConfig {
+            listen_address: "not-an-address".to_string(),
+            roa_endpoint: "roa.json".to_string(),
+            status_endpoint: "/dns/config.json".to_string(),
+            update_interval_seconds: 0,
+            ..Default::default()
+        };
+
+        let problems = validate_config(&config).unwrap_err();
+
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+    }
+
+    #[test]
+    fn test_validate_roa_sources() {
+        let source = |name: &str, location: &str| crate::RoaSourceConfig {
+            name: name.to_string(),
+            location: location.to_string(),
+            ..Default::default()
+        };
+
+        let config = AppConfig {
+            roa_sources: vec![source("peer", "/etc/peer.json"), source("peer", "http://127.0.0.1:8323/json"), source("", "")],
+            ..Default::default()
+        };
+
+        assert_eq!(validate_config(&config).unwrap_err(), vec![
+            "roa_sources[1] name 'peer' is used more than once",
+            "roa_sources[2] must have a name",
+            "roa_sources[2] must have a location",
+        ]);
+    }
+
+    #[test]
+    fn test_validate_overlapping_endpoints() {
+        let config = AppConfig {
+            asn_endpoint_directory: format!("{}/autnum", AppConfig::default().rdap_endpoint_directory),
+            ..Default::default()
+        };
+
+        assert_eq!(validate_config(&config).unwrap_err(), vec![
+            "asn_endpoint_directory route '/rdap/autnum/{asn}' overlaps rdap_endpoint_directory route '/rdap/autnum/{query}'",
+        ]);
+
+        // An exact endpoint inside another endpoint's directory is shadowed by its parameter
+        let config = AppConfig { status_endpoint: "/mntner/status".to_string(), ..Default::default() };
+
+        assert_eq!(validate_config(&config).unwrap_err(), vec![
+            "mntner_endpoint_directory route '/mntner/{handle}' overlaps status_endpoint route '/mntner/status'",
+        ]);
+
+        let config = AppConfig { events_endpoint: "/lookup/events".to_string(), ..Default::default() };
+
+        assert_eq!(validate_config(&config).unwrap_err(), vec![
+            "events_endpoint route '/lookup/events' overlaps lookup_endpoint_directory route '/lookup/{*query}'",
+        ]);
+    }
+
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dn42-roa-config-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_config_formats() {
+        let json = write_config("config.json", r#"{"listen_address": "127.0.0.1:1234", "update_interval_seconds": 60}"#);
+        let toml = write_config("config.toml", "listen_address = \"127.0.0.1:1234\"\nupdate_interval_seconds = 60\n");
+        let yaml = write_config("config.yml", "listen_address: 127.0.0.1:1234\nupdate_interval_seconds: 60\n");
+
+        for path in [json, toml, yaml] {
+            let config = check_config_with_env(&path, |_| None).unwrap();
+
+            assert_eq!(config.listen_address, "127.0.0.1:1234", "{:?}", path);
+            assert_eq!(config.update_interval_seconds, 60, "{:?}", path);
+            assert_eq!(config.roa_endpoint, "/roa.json", "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn test_missing_file_uses_defaults_without_writing() {
+        let path = std::env::temp_dir().join("dn42-roa-config-test-missing.json");
+
+        let config = check_config_with_env(&path, |_| None).unwrap();
+
+        assert_eq!(config.listen_address, AppConfig::default().listen_address);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let path = write_config("env.json", r#"{"listen_address": "127.0.0.1:1234"}"#);
+
+        let config = check_config_with_env(&path, |name| match name {
+            "DN42_ROA_LISTEN_ADDRESS" => Some("127.0.0.1:9999".to_string()),
+            "DN42_ROA_DO_GIT_PULL" => Some("false".to_string()),
+            "DN42_ROA_UPDATE_INTERVAL_SECONDS" => Some("42".to_string()),
+            "DN42_ROA_TASKS" => Some(r#"{"generate_dns": {"enabled": false}}"#.to_string()),
+            _ => None,
+        })
+        .unwrap();
+
+        assert_eq!(config.listen_address, "127.0.0.1:9999");
+        assert!(!config.do_git_pull);
+        assert_eq!(config.update_interval_seconds, 42);
+        assert_eq!(config.tasks["generate_dns"].enabled, Some(false));
+    }
+
+    #[test]
+    fn test_all_problems_reported() {
+        let path = write_config(
+            "problems.json",
+            r#"{"listen_adress": "0.0.0.0:8080", "roa_endpoint": "roa.json", "dns_primary_master": "ns1..dn42", "git_repo_dns_relative_path": "/data/dns"}"#,
+        );
+
+        let problems = check_config_with_env(&path, |name| match name {
+            "DN42_ROA_UPDATE_INTERVAL_SECONDS" => Some("often".to_string()),
+            _ => None,
+        })
+        .unwrap_err();
+
+        assert_eq!(problems.len(), 5, "{:?}", problems);
+        assert!(problems.iter().any(|p| p.contains("listen_adress")));
+        assert!(problems.iter().any(|p| p.contains("DN42_ROA_UPDATE_INTERVAL_SECONDS")));
+        assert!(problems.iter().any(|p| p.contains("dns_primary_master")));
+        assert!(problems.iter().any(|p| p.contains("git_repo_dns_relative_path")));
+        assert!(problems.iter().any(|p| p.contains("roa_endpoint")));
+    }
+
+    #[test]
+    fn test_type_errors_include_field_path() {
+        let path = write_config("types.yaml", "tasks:\n  generate_roa:\n    interval_seconds: soon\n");
+
+        let problems = check_config_with_env(&path, |_| None).unwrap_err();
+
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].contains("tasks.generate_roa.interval_seconds"), "{:?}", problems);
+    }
+}
@@ -112,6 +112,14 @@ fn generate_record_data(buffer: &mut String, data: &DNSRecordData) {
     }
 }
 
+// A single record on one line with its absolute name, e.g. "www.example.dn42. 3600 IN A 172.20.0.1"
+pub fn format_dns_record(record: &DNSRecord) -> String {
+    let mut buffer = format!("{} {} IN {} ", ensure_fqdn(record.name.as_str()), record.ttl, record.data.type_str());
+    generate_record_data(&mut buffer, &record.data);
+
+    buffer
+}
+
 fn generate_record_lines(buffer: &mut String, records: &HashSet<DNSRecord>, current_origin: FQDNName, default_ttl: u32) {
     // [Name] [TTL] [Class] [Type] [RDATA]
 
@@ -0,0 +1,253 @@
+use crate::parser::filter::{FilterPrefix, SetExpansion};
+use strum::{Display, EnumIter, EnumString};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Display, EnumString, EnumIter)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum FilterFormat {
+    Bird2,
+    // ip/ipv6 prefix-list syntax shared by FRR and Cisco IOS
+    #[strum(to_string = "frr", serialize = "cisco")]
+    Frr,
+    Junos,
+    Openbgpd,
+}
+
+// Filter names are derived from the set name, e.g. AS-EXAMPLE -> AS_EXAMPLE for BIRD
+fn identifier(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect()
+}
+
+fn list_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c.to_ascii_uppercase() } else { '_' }).collect()
+}
+
+fn split_families(expansion: &SetExpansion) -> (Vec<&FilterPrefix>, Vec<&FilterPrefix>) {
+    expansion.prefixes.iter().partition(|p| p.prefix.is_ipv4())
+}
+
+fn header(expansion: &SetExpansion, comment: &str) -> String {
+    let mut buffer = format!("{} {}: {} ASNs, {} prefixes\n", comment, expansion.name, expansion.asns.len(), expansion.prefixes.len());
+
+    for warning in &expansion.warnings {
+        buffer.push_str(&format!("{} warning: {}\n", comment, warning));
+    }
+
+    buffer
+}
+
+pub fn format_filter(expansion: &SetExpansion, format: FilterFormat) -> String {
+    match format {
+        FilterFormat::Bird2 => format_filter_bird2(expansion),
+        FilterFormat::Frr => format_filter_frr(expansion),
+        FilterFormat::Junos => format_filter_junos(expansion),
+        FilterFormat::Openbgpd => format_filter_openbgpd(expansion),
+    }
+}
+
+fn format_filter_bird2(expansion: &SetExpansion) -> String {
+    /*
+    define AS_EXAMPLE_ASNS = [ 4242420000, 4242420001 ];
+    define AS_EXAMPLE_V4 = [ 172.20.0.0/24{24,28} ];
+    define AS_EXAMPLE_V6 = [ fd00::/48 ];
+    */
+    let name = identifier(&expansion.name);
+    let (ipv4, ipv6) = split_families(expansion);
+
+    let prefix_set = |prefixes: &[&FilterPrefix]| {
+        prefixes
+            .iter()
+            .map(|p| match p.is_exact() {
+                true => format!("\n    {}", p.prefix),
+                false => format!("\n    {}{{{},{}}}", p.prefix, p.min_length, p.max_length),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    let asns = expansion.asns.iter().map(|asn| asn.to_string()).collect::<Vec<_>>().join(", ");
+
+    let mut buffer = header(expansion, "#");
+    buffer.push_str(&format!("define {}_ASNS = [ {} ];\n", name, asns));
+    buffer.push_str(&format!("define {}_V4 = [{}\n];\n", name, prefix_set(&ipv4)));
+    buffer.push_str(&format!("define {}_V6 = [{}\n];\n", name, prefix_set(&ipv6)));
+
+    buffer
+}
+
+fn format_filter_frr(expansion: &SetExpansion) -> String {
+    /*
+    no ip prefix-list AS-EXAMPLE
+    ip prefix-list AS-EXAMPLE seq 5 permit 172.20.0.0/24 le 28
+    bgp as-path access-list AS-EXAMPLE permit _4242420000$
+    */
+    let name = list_name(&expansion.name);
+    let (ipv4, ipv6) = split_families(expansion);
+
+    let mut buffer = header(expansion, "!");
+
+    for (command, prefixes, any) in [("ip", ipv4, "0.0.0.0/0"), ("ipv6", ipv6, "::/0")] {
+        buffer.push_str(&format!("no {} prefix-list {}\n", command, name));
+
+        // An empty list would match everything, deny explicitly instead
+        if prefixes.is_empty() {
+            buffer.push_str(&format!("{} prefix-list {} deny {} le {}\n", command, name, any, if command == "ip" { 32 } else { 128 }));
+        }
+
+        for (i, p) in prefixes.iter().enumerate() {
+            let mut line = format!("{} prefix-list {} seq {} permit {}", command, name, (i + 1) * 5, p.prefix);
+
+            if p.min_length > p.prefix.prefix_len() {
+                line.push_str(&format!(" ge {}", p.min_length));
+            }
+
+            if p.max_length > p.prefix.prefix_len() {
+                line.push_str(&format!(" le {}", p.max_length));
+            }
+
+            buffer.push_str(&line);
+            buffer.push('\n');
+        }
+    }
+
+    buffer.push_str(&format!("no bgp as-path access-list {}\n", name));
+
+    for asn in &expansion.asns {
+        buffer.push_str(&format!("bgp as-path access-list {} permit _{}$\n", name, asn));
+    }
+
+    buffer
+}
+
+fn format_filter_junos(expansion: &SetExpansion) -> String {
+    /*
+    policy-options {
+    replace:
+        route-filter-list AS-EXAMPLE-V4 {
+            172.20.0.0/24 upto /28;
+        }
+    }
+    */
+    let name = list_name(&expansion.name);
+    let (ipv4, ipv6) = split_families(expansion);
+
+    let mut buffer = header(expansion, "#");
+    buffer.push_str("policy-options {\n");
+
+    for (suffix, prefixes) in [("V4", ipv4), ("V6", ipv6)] {
+        buffer.push_str(&format!("replace:\n    route-filter-list {}-{} {{\n", name, suffix));
+
+        for p in prefixes {
+            let len = p.prefix.prefix_len();
+
+            let match_type = if p.is_exact() {
+                "exact".to_string()
+            } else if p.min_length == len {
+                format!("upto /{}", p.max_length)
+            } else {
+                format!("prefix-length-range /{}-/{}", p.min_length, p.max_length)
+            };
+
+            buffer.push_str(&format!("        {} {};\n", p.prefix, match_type));
+        }
+
+        buffer.push_str("    }\n");
+    }
+
+    buffer.push_str(&format!("replace:\n    as-path-group {} {{\n", name));
+
+    for (i, asn) in expansion.asns.iter().enumerate() {
+        buffer.push_str(&format!("        as-path a{} \".* {}\";\n", i, asn));
+    }
+
+    buffer.push_str("    }\n}\n");
+
+    buffer
+}
+
+fn format_filter_openbgpd(expansion: &SetExpansion) -> String {
+    /*
+    as-set "AS-EXAMPLE" { 4242420000 4242420001 }
+    prefix-set "AS-EXAMPLE" {
+        172.20.0.0/24 prefixlen 24 - 28
+    }
+    */
+    let name = list_name(&expansion.name);
+
+    let asns = expansion.asns.iter().map(|asn| asn.to_string()).collect::<Vec<_>>().join(" ");
+
+    let mut buffer = header(expansion, "#");
+    buffer.push_str(&format!("as-set \"{}\" {{ {} }}\n", name, asns));
+    buffer.push_str(&format!("prefix-set \"{}\" {{\n", name));
+
+    for p in &expansion.prefixes {
+        match p.is_exact() {
+            true => buffer.push_str(&format!("    {}\n", p.prefix)),
+            false => buffer.push_str(&format!("    {} prefixlen {} - {}\n", p.prefix, p.min_length, p.max_length)),
+        }
+    }
+
+    buffer.push_str("}\n");
+
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::filter::expand_set;
+    use crate::parser::filter::tests::filter_registry;
+    use std::str::FromStr;
+
+    fn expansion() -> SetExpansion {
+        expand_set(&filter_registry(), "AS-DEEP", 10).unwrap()
+    }
+
+    #[test]
+    fn test_format_from_str() {
+        assert_eq!(FilterFormat::from_str("BIRD2").unwrap(), FilterFormat::Bird2);
+        assert_eq!(FilterFormat::from_str("cisco").unwrap(), FilterFormat::Frr);
+        assert_eq!(FilterFormat::Frr.to_string(), "frr");
+        assert!(FilterFormat::from_str("bgpq3").is_err());
+    }
+
+    #[test]
+    fn test_format_filter_bird2() {
+        let output = format_filter(&expand_set(&filter_registry(), "AS4242420000", 10).unwrap(), FilterFormat::Bird2);
+
+        assert_eq!(output, "# AS4242420000: 1 ASNs, 2 prefixes\n\
+                            define AS4242420000_ASNS = [ 4242420000 ];\n\
+                            define AS4242420000_V4 = [\n    172.20.0.0/24{24,28}\n];\n\
+                            define AS4242420000_V6 = [\n    fd00::/48\n];\n");
+    }
+
+    #[test]
+    fn test_format_filter_frr() {
+        let output = format_filter(&expansion(), FilterFormat::Frr);
+
+        assert_eq!(output, "! AS-DEEP: 1 ASNs, 1 prefixes\n\
+                            no ip prefix-list AS-DEEP\n\
+                            ip prefix-list AS-DEEP seq 5 permit 172.22.0.0/24\n\
+                            no ipv6 prefix-list AS-DEEP\n\
+                            ipv6 prefix-list AS-DEEP deny ::/0 le 128\n\
+                            no bgp as-path access-list AS-DEEP\n\
+                            bgp as-path access-list AS-DEEP permit _4242420002$\n");
+    }
+
+    #[test]
+    fn test_format_filter_junos() {
+        let output = format_filter(&expand_set(&filter_registry(), "RS-EXAMPLE", 10).unwrap(), FilterFormat::Junos);
+
+        assert!(output.contains("route-filter-list RS-EXAMPLE-V4 {\n        172.20.0.0/16 prefix-length-range /24-/28;\n        172.21.0.0/24 exact;\n"));
+        assert!(output.contains("route-filter-list RS-EXAMPLE-V6 {\n        fd42::/32 upto /128;\n"));
+        assert!(output.contains("as-path a0 \".* 4242420001\";"));
+    }
+
+    #[test]
+    fn test_format_filter_openbgpd_with_warnings() {
+        let output = format_filter(&expand_set(&filter_registry(), "AS-EXAMPLE", 10).unwrap(), FilterFormat::Openbgpd);
+
+        assert!(output.contains("# warning: Unknown set AS-MISSING (member of AS-EXAMPLE)\n"));
+        assert!(output.contains("as-set \"AS-EXAMPLE\" { 4242420000 4242420001 4242420002 }\n"));
+        assert!(output.contains("    172.20.0.0/24 prefixlen 24 - 28\n    172.21.0.0/24\n"));
+    }
+}
@@ -1 +1,3 @@
-pub mod dns_zone;
\ No newline at end of file
+pub mod dns_zone;
+pub mod filter;
+pub mod roa;
@@ -0,0 +1,316 @@
+use crate::model::output::RpkiClientOutput;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use strum::{Display, EnumIter, EnumString};
+
+// Field naming of the JSON format. rpki-client's is also read by StayRTR and gortr.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoaJsonStyle {
+    #[default]
+    RpkiClient,
+    // Routinator's json output: AS-prefixed ASNs and generatedTime instead of buildtime
+    Routinator,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Display, EnumString, EnumIter)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum RoaFormat {
+    // rpki-client compatible JSON, as served on roa_endpoint
+    Json,
+    Bird2,
+    // roa-set block for bgpd.conf
+    Openbgpd,
+    // gortr/StayRTR JSON, GoBGP has no static ROA configuration and reads ROAs over RTR from these
+    Gobgp,
+    // IOS XR "rpki route" statements for the router bgp section
+    Cisco,
+    // Junos static validation records
+    Juniper,
+    // Routinator's csv output
+    Csv,
+}
+
+impl RoaFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            RoaFormat::Json | RoaFormat::Gobgp => "application/json",
+            RoaFormat::Csv => "text/csv",
+            RoaFormat::Bird2 | RoaFormat::Openbgpd | RoaFormat::Cisco | RoaFormat::Juniper => "text/plain",
+        }
+    }
+}
+
+pub fn format_roa(output: &RpkiClientOutput, format: RoaFormat, json_style: RoaJsonStyle) -> anyhow::Result<String> {
+    Ok(match format {
+        RoaFormat::Json => format_roa_json(output, json_style)?,
+        RoaFormat::Bird2 => format_roa_bird2(output),
+        RoaFormat::Openbgpd => format_roa_openbgpd(output),
+        RoaFormat::Gobgp => format_roa_gobgp(output)?,
+        RoaFormat::Cisco => format_roa_cisco(output),
+        RoaFormat::Juniper => format_roa_juniper(output),
+        RoaFormat::Csv => format_roa_csv(output),
+    })
+}
+
+pub fn format_roa_json(output: &RpkiClientOutput, style: RoaJsonStyle) -> anyhow::Result<String> {
+    if style == RoaJsonStyle::RpkiClient {
+        return Ok(serde_json::to_string_pretty(output)?);
+    }
+
+    let mut value = serde_json::to_value(output)?;
+
+    /*
+    {"metadata":{"generated":1735689600,"generatedTime":"2025-01-01T00:00:00+00:00"},
+     "roas":[{"asn":"AS4242420000","prefix":"172.20.0.0/24","maxLength":28,"ta":"dn42"}],
+     "aspas":[{"customer":"AS4242420000","providers":["AS4242420001"]}]}
+    */
+    let as_string = |asn: &Value| json!(format!("AS{}", asn));
+
+    if let Some(metadata) = value["metadata"].as_object_mut()
+        && let Some(build_time) = metadata.remove("buildtime")
+    {
+        metadata.insert("generatedTime".to_string(), build_time);
+    }
+
+    for roa in value.get_mut("roas").and_then(Value::as_array_mut).into_iter().flatten() {
+        roa["asn"] = as_string(&roa["asn"]);
+    }
+
+    for aspa in value.get_mut("aspas").and_then(Value::as_array_mut).into_iter().flatten() {
+        if let Some(aspa) = aspa.as_object_mut()
+            && let Some(customer) = aspa.remove("customer_asid")
+        {
+            aspa.insert("customer".to_string(), as_string(&customer));
+        }
+
+        for provider in aspa["providers"].as_array_mut().into_iter().flatten() {
+            *provider = as_string(provider);
+        }
+    }
+
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+pub fn format_roa_bird2(output: &RpkiClientOutput) -> String {
+    /*
+    # Generated at 2025-01-01T00:00:00Z, 2 ROAs
+    route 172.20.0.0/24 max 28 as 4242420000;
+    route fd00::/48 max 64 as 4242420000;
+    */
+    let mut buffer = String::new();
+
+    buffer.push_str(format!("# Generated at {}, {} ROAs\n", output.metadata.build_time, output.roas.len()).as_str());
+
+    for roa in &output.roas {
+        buffer.push_str(format!("route {} max {} as {};\n", roa.prefix, roa.max_length, roa.asn).as_str());
+    }
+
+    buffer
+}
+
+pub fn format_roa_openbgpd(output: &RpkiClientOutput) -> String {
+    /*
+    # Generated at 2025-01-01T00:00:00Z, 2 ROAs
+    roa-set {
+        172.20.0.0/24 maxlen 28 source-as 4242420000
+        fd00::/48 maxlen 64 source-as 4242420000
+    }
+    */
+    let mut buffer = String::new();
+
+    buffer.push_str(format!("# Generated at {}, {} ROAs\n", output.metadata.build_time, output.roas.len()).as_str());
+    buffer.push_str("roa-set {\n");
+
+    for roa in &output.roas {
+        buffer.push_str(format!("\t{} maxlen {} source-as {}\n", roa.prefix, roa.max_length, roa.asn).as_str());
+    }
+
+    buffer.push_str("}\n");
+
+    buffer
+}
+
+pub fn format_roa_gobgp(output: &RpkiClientOutput) -> anyhow::Result<String> {
+    /*
+    {"metadata":{"counts":1,"generated":1735689600},"roas":[{"prefix":"172.20.0.0/24","maxLength":28,"asn":"AS4242420000"}]}
+    */
+    let roas = output
+        .roas
+        .iter()
+        .map(|roa| serde_json::json!({
+            "prefix": roa.prefix,
+            "maxLength": roa.max_length,
+            "asn": format!("AS{}", roa.asn),
+        }))
+        .collect::<Vec<_>>();
+
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "metadata": {
+            "counts": output.roas.len(),
+            "generated": output.metadata.generated,
+        },
+        "roas": roas,
+    }))?)
+}
+
+pub fn format_roa_cisco(output: &RpkiClientOutput) -> String {
+    /*
+    ! Generated at 2025-01-01T00:00:00Z, 2 ROAs
+    rpki route 172.20.0.0/24 max 28 origin 4242420000
+    */
+    let mut buffer = String::new();
+
+    buffer.push_str(format!("! Generated at {}, {} ROAs\n", output.metadata.build_time, output.roas.len()).as_str());
+
+    for roa in &output.roas {
+        buffer.push_str(format!("rpki route {} max {} origin {}\n", roa.prefix, roa.max_length, roa.asn).as_str());
+    }
+
+    buffer
+}
+
+pub fn format_roa_juniper(output: &RpkiClientOutput) -> String {
+    /*
+    # Generated at 2025-01-01T00:00:00Z, 2 ROAs
+    set routing-options validation static record 172.20.0.0/24 maximum-length 28 origin-autonomous-system 4242420000 validation-state valid
+    */
+    let mut buffer = String::new();
+
+    buffer.push_str(format!("# Generated at {}, {} ROAs\n", output.metadata.build_time, output.roas.len()).as_str());
+
+    for roa in &output.roas {
+        buffer.push_str(format!(
+            "set routing-options validation static record {} maximum-length {} origin-autonomous-system {} validation-state valid\n",
+            roa.prefix, roa.max_length, roa.asn
+        ).as_str());
+    }
+
+    buffer
+}
+
+pub fn format_roa_csv(output: &RpkiClientOutput) -> String {
+    /*
+    ASN,IP Prefix,Max Length,Trust Anchor
+    AS4242420000,172.20.0.0/24,28,dn42
+    */
+    let mut buffer = String::from("ASN,IP Prefix,Max Length,Trust Anchor\n");
+
+    for roa in &output.roas {
+        buffer.push_str(format!("AS{},{},{},{}\n", roa.asn, roa.prefix, roa.max_length, roa.ta).as_str());
+    }
+
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::output::{Aspa, ROA};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_format_roa_bird2() {
+        let output = RpkiClientOutput {
+            roas: vec![
+                ROA { asn: 4242420000, prefix: "172.20.0.0/24".to_string(), max_length: 28, ..Default::default() },
+                ROA { asn: 4242420001, prefix: "fd00::/48".to_string(), max_length: 64, ..Default::default() },
+            ],
+            ..Default::default()
+        };
+
+        let content = format_roa_bird2(&output);
+        let lines = content.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with('#'));
+        assert_eq!(lines[1], "route 172.20.0.0/24 max 28 as 4242420000;");
+        assert_eq!(lines[2], "route fd00::/48 max 64 as 4242420001;");
+    }
+
+    #[test]
+    fn test_roa_format_from_str() {
+        assert_eq!(RoaFormat::from_str("bird2").unwrap(), RoaFormat::Bird2);
+        assert_eq!(RoaFormat::from_str("JSON").unwrap(), RoaFormat::Json);
+        assert!(RoaFormat::from_str("bird1").is_err());
+    }
+
+    fn sample() -> RpkiClientOutput {
+        let mut output = RpkiClientOutput {
+            roas: vec![
+                ROA { asn: 4242420000, prefix: "172.20.0.0/24".to_string(), max_length: 28, ..Default::default() },
+                ROA { asn: 4242420001, prefix: "fd00::/48".to_string(), max_length: 64, ..Default::default() },
+            ],
+            ..Default::default()
+        };
+        output.metadata.build_time = "2025-01-01T00:00:00+00:00".to_string();
+        output.metadata.generated = 1735689600;
+        output.aspas = Some(vec![Aspa { customer_asid: 4242420000, providers: vec![4242420001], ..Default::default() }]);
+        output.update_counts();
+        output.stamp("dn42", 1735776000);
+        output
+    }
+
+    #[test]
+    fn test_format_roa_json_styles() {
+        let value: Value = serde_json::from_str(&format_roa_json(&sample(), RoaJsonStyle::RpkiClient).unwrap()).unwrap();
+
+        assert_eq!(value["metadata"]["buildtime"], "2025-01-01T00:00:00+00:00");
+        assert_eq!(value["metadata"]["ipv4roas"], 1);
+        assert_eq!(value["metadata"]["ipv6roas"], 1);
+        assert_eq!(value["metadata"]["aspas"], 1);
+        assert!(value["metadata"]["generatedby"].as_str().unwrap().starts_with("dn42-roa-generator "));
+        assert_eq!(value["roas"][0], json!({"asn": 4242420000u32, "prefix": "172.20.0.0/24", "maxLength": 28, "ta": "dn42", "expires": 1735776000}));
+        assert_eq!(value["aspas"][0], json!({"customer_asid": 4242420000u32, "expires": 1735776000, "providers": [4242420001u32]}));
+
+        let value: Value = serde_json::from_str(&format_roa_json(&sample(), RoaJsonStyle::Routinator).unwrap()).unwrap();
+
+        assert_eq!(value["metadata"]["generatedTime"], "2025-01-01T00:00:00+00:00");
+        assert_eq!(value["metadata"]["generated"], 1735689600);
+        assert!(value["metadata"].get("buildtime").is_none());
+        assert_eq!(value["roas"][1]["asn"], "AS4242420001");
+        assert_eq!(value["aspas"][0], json!({"customer": "AS4242420000", "expires": 1735776000, "providers": ["AS4242420001"]}));
+
+        let mut output = sample();
+        output.aspas = None;
+
+        let value: Value = serde_json::from_str(&format_roa_json(&output, RoaJsonStyle::Routinator).unwrap()).unwrap();
+        assert!(value.get("aspas").is_none());
+    }
+
+    #[test]
+    fn test_format_roa_openbgpd() {
+        assert_eq!(format_roa_openbgpd(&sample()), "# Generated at 2025-01-01T00:00:00+00:00, 2 ROAs\n\
+                                                     roa-set {\n\
+                                                     \t172.20.0.0/24 maxlen 28 source-as 4242420000\n\
+                                                     \tfd00::/48 maxlen 64 source-as 4242420001\n\
+                                                     }\n");
+    }
+
+    #[test]
+    fn test_format_roa_gobgp() {
+        let value: serde_json::Value = serde_json::from_str(&format_roa_gobgp(&sample()).unwrap()).unwrap();
+
+        assert_eq!(value["metadata"]["generated"], 1735689600);
+        assert_eq!(value["roas"][0], serde_json::json!({"prefix": "172.20.0.0/24", "maxLength": 28, "asn": "AS4242420000"}));
+    }
+
+    #[test]
+    fn test_format_roa_router_tables() {
+        let cisco = format_roa_cisco(&sample());
+        assert_eq!(cisco.lines().nth(2), Some("rpki route fd00::/48 max 64 origin 4242420001"));
+
+        let juniper = format_roa_juniper(&sample());
+        assert_eq!(
+            juniper.lines().nth(1),
+            Some("set routing-options validation static record 172.20.0.0/24 maximum-length 28 origin-autonomous-system 4242420000 validation-state valid")
+        );
+    }
+
+    #[test]
+    fn test_format_roa_csv() {
+        assert_eq!(format_roa_csv(&sample()), "ASN,IP Prefix,Max Length,Trust Anchor\n\
+                                                AS4242420000,172.20.0.0/24,28,dn42\n\
+                                                AS4242420001,fd00::/48,64,dn42\n");
+    }
+}
@@ -1,15 +1,15 @@
 use crate::model::record::RecordFile;
-use crate::task::dns::GenerateDNSAuthoritativeZonesTask;
-use crate::task::roa::GenerateRoaTask;
-use crate::task::Task;
-use crate::AppState;
-use anyhow::Context;
+use crate::AppConfig;
+use crate::parser::dns_wire::{decode_response, encode_query, DnsResponse};
+use anyhow::{anyhow, bail, Context};
 use std::fmt::Debug;
 use std::fs;
+use std::io::{Read, Write};
+use std::net::{Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::time::{Duration, Instant};
 use std::path::{Path, PathBuf};
-use std::time::Instant;
 use tokio::io::AsyncBufReadExt;
-use tracing::{error, info};
+use tracing::info;
 
 fn discover_record(route_directories: impl Iterator<Item=impl AsRef<Path> + Debug>) -> anyhow::Result<Vec<PathBuf>> {
     let mut record_files = Vec::new();
@@ -25,6 +25,9 @@ fn discover_record(route_directories: impl Iterator<Item=impl AsRef<Path> + Debu
         }
     }
 
+    // Keep output ordering independent of the directory iteration order
+    record_files.sort();
+
     Ok(record_files)
 }
 
@@ -51,46 +54,6 @@ pub fn get_records_from_dirs(record_type: &str, directories: impl Iterator<Item=
     Ok(records)
 }
 
-pub async fn background_updater(state: AppState) {
-    let do_git_pull = state.config.do_git_pull;
-    let repo_url = state.config.git_repo_url.clone();
-    let repo_local_path = Path::new(&state.config.git_repo_local_path);
-    let update_interval = std::time::Duration::from_secs(state.config.update_interval_seconds);
-
-    let tasks: Vec<Box<dyn Task>> = vec![
-        Box::new(GenerateRoaTask::new(state.clone())),
-        Box::new(GenerateDNSAuthoritativeZonesTask::new(state.clone()))
-    ];
-
-    loop {
-        info!("Starting background update of git repository.");
-
-        if let Err(e) = sync_git_repository(&repo_url, repo_local_path, do_git_pull).await {
-            error!("Error updating git repository: {:?}", e);
-        } else {
-            info!("Successfully updated git repository.");
-        }
-
-        for task in &tasks {
-            info!("Running task: {}", task.name());
-            let begin = Instant::now();
-
-            if let Err(e) = task.run() {
-                error!("Error running task '{}': {:?}", task.name(), e);
-            } else {
-                info!("Successfully completed task: {}", task.name());
-            }
-
-            let elapsed = begin.elapsed();
-            info!("Task '{}' completed in {:.2?}", task.name(), elapsed);
-        }
-
-        info!("Waiting for {:?} before next update.", update_interval);
-
-        tokio::time::sleep(update_interval).await;
-    }
-}
-
 pub async fn run_command_echo_output(command: &mut tokio::process::Command) -> anyhow::Result<()> {
     info!("Running command '{:?}'", command);
 
@@ -120,6 +83,310 @@ pub async fn run_command_echo_output(command: &mut tokio::process::Command) -> a
     Ok(())
 }
 
+const SOURCE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+// Reads a file path or fetches an http(s) URL
+pub fn read_location(location: &str) -> anyhow::Result<String> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(SOURCE_TIMEOUT)).build().into();
+
+        return agent
+            .get(location)
+            .call()
+            .and_then(|mut response| response.body_mut().read_to_string())
+            .with_context(|| format!("Failed to fetch {}", location));
+    }
+
+    fs::read_to_string(location).with_context(|| format!("Failed to read {}", location))
+}
+
+fn query_id() -> u16 {
+    use std::hash::{BuildHasher, Hasher};
+
+    std::collections::hash_map::RandomState::new().build_hasher().finish() as u16
+}
+
+fn dns_exchange_tcp(server: SocketAddr, query: &[u8], timeout: Duration) -> anyhow::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect_timeout(&server, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    stream.write_all(&[(query.len() >> 8) as u8, query.len() as u8])?;
+    stream.write_all(query)?;
+
+    let mut length = [0; 2];
+    stream.read_exact(&mut length)?;
+
+    let mut response = vec![0; u16::from_be_bytes(length) as usize];
+    stream.read_exact(&mut response)?;
+
+    Ok(response)
+}
+
+// Sends one query over UDP and retries over TCP when the answer is truncated
+pub fn dns_query(server: SocketAddr, name: &str, record_type: u16, recursion_desired: bool, timeout: Duration) -> anyhow::Result<DnsResponse> {
+    let id = query_id();
+    let query = encode_query(id, name, record_type, recursion_desired).map_err(|e| anyhow!(e))?;
+
+    let local: SocketAddr = if server.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { (Ipv6Addr::UNSPECIFIED, 0).into() };
+    let socket = UdpSocket::bind(local)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.send_to(&query, server)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buffer = [0; 65535];
+
+    // Answers from other addresses or to other queries are ignored until the timeout
+    let response = loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        if remaining.is_zero() {
+            bail!("No answer from {} within {:?}", server, timeout);
+        }
+
+        socket.set_read_timeout(Some(remaining))?;
+
+        let (length, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                bail!("No answer from {} within {:?}", server, timeout)
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to query {}", server)),
+        };
+
+        if from != server {
+            continue;
+        }
+
+        match decode_response(&buffer[..length]) {
+            Ok(response) if response.id == id => break response,
+            _ => continue,
+        }
+    };
+
+    if !response.truncated {
+        return Ok(response);
+    }
+
+    let message = dns_exchange_tcp(server, &query, timeout).with_context(|| format!("Failed to query {} over TCP", server))?;
+
+    let response = decode_response(&message).map_err(|e| anyhow!("Invalid answer from {}: {}", server, e))?;
+
+    if response.id != id {
+        bail!("Answer from {} over TCP does not match the query id", server);
+    }
+
+    Ok(response)
+}
+
+fn git_output(repo: &Path, args: &[&str]) -> anyhow::Result<Vec<u8>> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run git {:?}", args))?;
+
+    if !output.status.success() {
+        bail!("git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(output.stdout)
+}
+
+// Reads blob contents in one git process, answers come back in request order
+fn read_blobs(repo: &Path, hashes: &[String]) -> anyhow::Result<Vec<String>> {
+    let mut child = std::process::Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["cat-file", "--batch"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .context("Failed to run git cat-file")?;
+
+    let mut stdin = child.stdin.take().context("git cat-file has no stdin")?;
+    let request = hashes.iter().map(|hash| format!("{}\n", hash)).collect::<String>();
+
+    // Written from another thread so a full stdout pipe cannot block the request
+    let writer = std::thread::spawn(move || stdin.write_all(request.as_bytes()));
+
+    let mut output = Vec::new();
+    child.stdout.take().context("git cat-file has no stdout")?.read_to_end(&mut output)?;
+    child.wait()?;
+    writer.join().map_err(|_| anyhow::anyhow!("git cat-file writer panicked"))??;
+
+    // <hash> blob <size>\n<content>\n
+    let mut blobs = Vec::with_capacity(hashes.len());
+    let mut rest = output.as_slice();
+
+    for hash in hashes {
+        let header_end = rest.iter().position(|&b| b == b'\n').with_context(|| format!("Truncated git cat-file output at {}", hash))?;
+        let header = String::from_utf8_lossy(&rest[..header_end]).to_string();
+
+        let size = match header.split(' ').collect::<Vec<_>>().as_slice() {
+            [_, "blob", size] => size.parse::<usize>()?,
+            _ => bail!("Unexpected git cat-file answer for {}: {}", hash, header),
+        };
+
+        let content = rest.get(header_end + 1..header_end + 1 + size).with_context(|| format!("Truncated blob {}", hash))?;
+        blobs.push(String::from_utf8_lossy(content).to_string());
+        rest = rest.get(header_end + 2 + size..).unwrap_or_default();
+    }
+
+    Ok(blobs)
+}
+
+// (type, hash, path) of the direct children of a directory at a commit, like a working tree listing
+fn ls_tree(repo: &Path, commit: &str, relative_path: &str) -> anyhow::Result<Vec<(String, String, String)>> {
+    // <mode> <type> <hash>\t<path>, NUL separated
+    let listing = git_output(repo, &["ls-tree", "-z", commit, "--", &format!("{}/", relative_path.trim_end_matches('/'))])?;
+
+    Ok(listing
+        .split(|&b| b == 0)
+        .filter_map(|entry| {
+            let entry = String::from_utf8_lossy(entry);
+            let (meta, path) = entry.split_once('\t')?;
+
+            match meta.split(' ').collect::<Vec<_>>().as_slice() {
+                [_, object_type, hash] => Some((object_type.to_string(), hash.to_string(), path.to_string())),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+// Where registry objects are read from: the checked out files, or a commit in the
+// repository's object store when git_repo_revision is set
+#[derive(Debug, Clone)]
+pub enum RegistrySource {
+    WorkTree(PathBuf),
+    Revision { repo: PathBuf, commit: String },
+}
+
+impl RegistrySource {
+    pub fn from_config(config: &AppConfig) -> anyhow::Result<RegistrySource> {
+        let repo = PathBuf::from(&config.git_repo_local_path);
+
+        if config.git_repo_revision.is_empty() {
+            return Ok(RegistrySource::WorkTree(repo));
+        }
+
+        let revision = format!("{}^{{commit}}", config.git_repo_revision);
+        let commit = git_output(&repo, &["rev-parse", "--verify", "--quiet", &revision])
+            .with_context(|| format!("Unknown revision {} in {:?}", config.git_repo_revision, repo))?;
+
+        Ok(RegistrySource::Revision { repo, commit: String::from_utf8_lossy(&commit).trim().to_string() })
+    }
+
+    pub fn repo(&self) -> &Path {
+        match self {
+            RegistrySource::WorkTree(repo) | RegistrySource::Revision { repo, .. } => repo,
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        match self {
+            RegistrySource::WorkTree(repo) => repo.exists(),
+            // The revision was resolved, so the repository exists
+            RegistrySource::Revision { .. } => true,
+        }
+    }
+
+    pub fn has_directory(&self, relative_path: &str) -> bool {
+        match self {
+            RegistrySource::WorkTree(repo) => repo.join(relative_path).is_dir(),
+            RegistrySource::Revision { repo, commit } => git_output(repo, &["cat-file", "-t", &format!("{}:{}", commit, relative_path)])
+                .is_ok_and(|object_type| object_type.trim_ascii() == b"tree"),
+        }
+    }
+
+    pub fn commit(&self) -> Option<String> {
+        match self {
+            RegistrySource::WorkTree(repo) => git_head_commit(repo),
+            RegistrySource::Revision { commit, .. } => Some(commit.clone()),
+        }
+    }
+
+    // Subdirectories of a directory relative to the repository, ordered by path
+    pub fn directories(&self, relative_path: &str) -> anyhow::Result<Vec<String>> {
+        let mut directories = match self {
+            RegistrySource::WorkTree(repo) => {
+                let path = repo.join(relative_path);
+                let mut directories = Vec::new();
+
+                for entry in fs::read_dir(&path).with_context(|| format!("Failed to read directory {:?}", path))? {
+                    let entry = entry.with_context(|| format!("Failed to read directory entry {:?}", path))?;
+
+                    if entry.path().is_dir() {
+                        directories.push(format!("{}/{}", relative_path.trim_end_matches('/'), entry.file_name().to_string_lossy()));
+                    }
+                }
+
+                directories
+            }
+            RegistrySource::Revision { repo, commit } => ls_tree(repo, commit, relative_path)?
+                .into_iter()
+                .filter(|(object_type, _, _)| object_type == "tree")
+                .map(|(_, _, path)| path)
+                .collect(),
+        };
+
+        directories.sort();
+
+        Ok(directories)
+    }
+
+    // Records in the given directories relative to the repository, ordered by path
+    pub fn records(&self, record_type: &str, relative_paths: &[&str]) -> anyhow::Result<Vec<RecordFile>> {
+        let (repo, commit) = match self {
+            RegistrySource::WorkTree(repo) => {
+                return get_records_from_dirs(record_type, relative_paths.iter().map(|path| repo.join(path)));
+            }
+            RegistrySource::Revision { repo, commit } => (repo, commit),
+        };
+
+        let mut paths = Vec::new();
+        let mut hashes = Vec::new();
+
+        // git does not track empty directories, a missing tree simply has no records
+        for relative_path in relative_paths {
+            for (object_type, hash, path) in ls_tree(repo, commit, relative_path)? {
+                if object_type == "blob" {
+                    paths.push(repo.join(path));
+                    hashes.push(hash);
+                }
+            }
+        }
+
+        let mut records = paths
+            .into_iter()
+            .zip(read_blobs(repo, &hashes)?)
+            .map(|(path, content)| RecordFile::from_content(path, content))
+            .collect::<Vec<_>>();
+
+        records.sort_by(|a, b| a.get_file_path().cmp(b.get_file_path()));
+
+        info!("[{}] Read {} record files at {}", record_type, records.len(), commit);
+
+        Ok(records)
+    }
+}
+
+// Commit checked out in the registry, None when it is not a git repository
+pub fn git_head_commit(repo_local_path: &Path) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["-C", repo_local_path.to_str()?, "rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 #[allow(clippy::collapsible_else_if)]
 pub async fn sync_git_repository(repo_url: &str, repo_local_path: &Path, do_git_pull: bool) -> anyhow::Result<()> {
     if !repo_local_path.exists() {
@@ -141,4 +408,66 @@ pub async fn sync_git_repository(repo_url: &str, repo_local_path: &Path, do_git_
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(repo: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[test]
+    fn test_registry_source_at_revision() {
+        let repo = std::env::temp_dir().join(format!("dn42-roa-io-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&repo);
+        fs::create_dir_all(repo.join("data/route/nested")).unwrap();
+
+        git(&repo, &["init", "-q"]);
+        fs::write(repo.join("data/route/172.20.0.0_24"), "route: 172.20.0.0/24\norigin: AS4242420000\n").unwrap();
+        fs::write(repo.join("data/route/nested/ignored"), "route: 10.0.0.0/8\n").unwrap();
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-qm", "first"]);
+        git(&repo, &["tag", "first"]);
+
+        fs::write(repo.join("data/route/172.20.0.0_24"), "route: 172.20.0.0/24\norigin: AS4242420001\n").unwrap();
+        fs::write(repo.join("data/route/172.21.0.0_24"), "route: 172.21.0.0/24\norigin: AS4242420002\n").unwrap();
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-qm", "second"]);
+
+        let config = AppConfig {
+            git_repo_local_path: repo.display().to_string(),
+            ..Default::default()
+        };
+
+        let source = RegistrySource::from_config(&config.at_revision("first")).unwrap();
+        let records = source.records("ROA", &["data/route"]).unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].get_attribute("origin"), Some("AS4242420000"));
+        assert_eq!(records[0].get_file_path(), repo.join("data/route/172.20.0.0_24"));
+        assert!(source.has_directory("data/route"));
+        assert!(!source.has_directory("data/aut-num"));
+        assert!(source.records("ROA", &["data/aut-num"]).unwrap().is_empty());
+        assert_eq!(source.directories("data").unwrap(), vec!["data/route"]);
+        assert_eq!(source.directories("data/route").unwrap(), vec!["data/route/nested"]);
+
+        let worktree = RegistrySource::from_config(&config).unwrap();
+        assert_eq!(worktree.records("ROA", &["data/route"]).unwrap().len(), 2);
+        assert_eq!(worktree.directories("data").unwrap(), vec!["data/route"]);
+        assert_ne!(worktree.commit(), source.commit());
+
+        assert!(RegistrySource::from_config(&config.at_revision("no-such-revision")).is_err());
+
+        fs::remove_dir_all(&repo).unwrap();
+    }
+}
@@ -1,19 +1,84 @@
 pub mod model;
+pub mod config;
 pub mod io;
 pub mod parser;
 pub mod task;
 
 pub mod formatter;
+pub mod scheduler;
+pub mod server;
+pub mod cli;
 
+use crate::config::ReloadStatus;
+use crate::formatter::roa::{RoaFormat, RoaJsonStyle};
+use crate::model::dns::DNSZone;
+use crate::model::health::DomainHealth;
+use crate::model::history::{DnsHistory, RoaHistory};
+use crate::model::output::{RpkiClientOutput, ROA};
+use crate::model::registry::Registry;
+use crate::model::trie::PrefixTrie;
+use crate::parser::dns_lint::DnsLintProblem;
+use crate::parser::route::RouteDiagnostic;
+use crate::parser::source::{ParsedRoaSource, RoaSourceFormat};
+use crate::scheduler::TaskStatus;
+use crate::server::content::CachedContent;
+use crate::server::events::UpdateBroadcaster;
+use crate::server::revision::RevisionCache;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use strum::IntoEnumIterator;
+use tokio::sync::{watch, Notify};
 
 #[derive(Clone, Default)]
 pub struct AppState {
-    pub config: Arc<AppConfig>,
+    pub config: ConfigHandle,
     pub roa_data: Arc<RwLock<ROACache>>,
     pub dns_data: Arc<RwLock<DNSCache>>,
+    // Past versions of the ROA set and zone records, for the history endpoints
+    pub roa_history: Arc<RwLock<RoaHistory>>,
+    pub dns_history: Arc<RwLock<DnsHistory>>,
+    pub dns_health: Arc<RwLock<DnsHealthCache>>,
+    // States generated for ?revision= requests
+    pub revision_cache: Arc<Mutex<RevisionCache>>,
+    // Notifies /events subscribers whenever roa_data or dns_data is replaced
+    pub updates: UpdateBroadcaster,
+    pub registry: Arc<RwLock<Registry>>,
+    // Wakes the background updater before its interval elapses.
+    // Notify keeps at most one pending permit, so bursts of triggers coalesce into a single run.
+    pub refresh_trigger: Arc<Notify>,
+    // task id -> scheduler bookkeeping, exposed through the status endpoint
+    pub task_status: Arc<RwLock<HashMap<String, TaskStatus>>>,
+    pub reload_status: Arc<RwLock<ReloadStatus>>,
+}
+
+// The current configuration, replaced as a whole on reload.
+// Readers should call get() once per operation so they see a consistent snapshot.
+#[derive(Clone)]
+pub struct ConfigHandle(Arc<watch::Sender<Arc<AppConfig>>>);
+
+impl ConfigHandle {
+    pub fn new(config: AppConfig) -> Self {
+        ConfigHandle(Arc::new(watch::Sender::new(Arc::new(config))))
+    }
+
+    pub fn get(&self) -> Arc<AppConfig> {
+        self.0.borrow().clone()
+    }
+
+    pub fn replace(&self, config: AppConfig) {
+        self.0.send_replace(Arc::new(config));
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<AppConfig>> {
+        self.0.subscribe()
+    }
+}
+
+impl Default for ConfigHandle {
+    fn default() -> Self {
+        ConfigHandle::new(AppConfig::default())
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -23,22 +88,145 @@ pub struct AppConfig {
     pub roa_endpoint: String,
     pub dns_config_endpoint: String,
     pub dns_content_endpoint_directory: String,
+    pub status_endpoint: String,
+    // Base path of the RDAP (RFC 9083) API, e.g. /rdap/ip/{addr}
+    pub rdap_endpoint_directory: String,
+    // Absolute URL of rdap_endpoint_directory used in links, e.g. https://roa.example.dn42/rdap.
+    // Empty: built from the request's Host header.
+    pub rdap_base_url: String,
+    // Address lookups, e.g. /lookup/172.20.0.1 or /lookup/fd00::/48
+    pub lookup_endpoint_directory: String,
+    // Routes, ROAs, domains, inetnums and problems of an ASN or maintainer, e.g. /asn/AS4242420000 or /mntner/EXAMPLE-MNT
+    pub asn_endpoint_directory: String,
+    pub mntner_endpoint_directory: String,
+    // Route objects rejected or corrected while generating ROAs
+    pub roa_diagnostics_endpoint: String,
+    // Nameserver and glue problems of forward and reverse delegations
+    pub dns_lint_endpoint: String,
+    // Live delegation health check results, e.g. /dns/health/example.dn42
+    pub dns_health_endpoint_directory: String,
+    // Expanded as-set/route-set filters, e.g. /filters/AS-EXAMPLE?format=bird2
+    pub filter_endpoint_directory: String,
+    // Maximum nesting of as-set/route-set members followed when expanding filters
+    pub filter_max_depth: usize,
+    // Versions and diffs of the ROA set and DNS zones, e.g. /history/roa/diff?since=2025-01-01T00:00:00Z
+    pub history_endpoint_directory: String,
+    // Number of ROA and DNS versions kept for the history endpoints
+    pub history_size: usize,
+    // Server-Sent Events stream of ROA and DNS updates
+    pub events_endpoint: String,
+    // The same events over WebSocket
+    pub events_websocket_endpoint: String,
+
+    pub admin_refresh_endpoint: String,
+    // Bearer token for the admin endpoints, empty disables them
+    pub admin_token: String,
+    pub webhook_endpoint: String,
+    // Shared secret used to verify push webhook signatures, empty disables the webhook
+    pub webhook_secret: String,
 
     pub do_git_pull: bool,
 
     pub git_repo_url: String,
     pub git_repo_local_path: String,
+    // Commit, tag or branch to generate from instead of the working tree, read from the object store. Empty reads the working tree.
+    pub git_repo_revision: String,
     pub git_repo_ipv4_route_relative_path: String,
     pub git_repo_ipv6_route_relative_path: String,
 
     pub git_repo_dns_relative_path: String,
     pub git_repo_inetnum_relative_path: String,
     pub git_repo_inet6num_relative_path: String,
+    // Directory holding one sub-directory per object type, indexed for whois lookups
+    pub git_repo_data_relative_path: String,
+    // aut-num objects, ROA origins without one are reported
+    pub git_repo_aut_num_relative_path: String,
+
+    // Address of the whois (RFC 3912) server, e.g. 0.0.0.0:43. Empty disables it.
+    pub whois_listen_address: String,
+
+    // Merge ROAs whose announcements are already authorised by another ROA of the same origin
+    pub roa_compression: bool,
+    // Publish route objects with host bits set under their network address instead of rejecting them
+    pub roa_normalise_prefixes: bool,
+    // Leave out ROAs whose origin has no aut-num object instead of only reporting them
+    pub roa_exclude_unregistered_origins: bool,
+    // aut-num attribute listing upstream ASNs to publish as ASPA records, e.g. "aspa-provider". Empty disables ASPA output.
+    pub aspa_provider_attribute: String,
+    // Trust anchor name ("ta") reported for every ROA
+    pub roa_trust_anchor: String,
+    // Validity of generated ROAs and ASPAs ("expires"), renewed once half of it has passed. 0 omits expiry.
+    pub roa_expiry_seconds: u64,
+    // Field naming of the JSON output, "rpki-client" (also read by StayRTR) or "routinator"
+    pub roa_json_style: RoaJsonStyle,
+    // Additional ROA sources merged with the registry, see RoaSourceConfig
+    pub roa_sources: Vec<RoaSourceConfig>,
 
     pub update_interval_seconds: u64,
+    // How often the configuration file is checked for changes, 0 disables watching (SIGHUP still works)
+    pub config_watch_interval_seconds: u64,
+    // task id -> schedule overrides, see TaskConfig
+    pub tasks: HashMap<String, TaskConfig>,
 
     pub dns_primary_master: String,
     pub dns_responsible_party: String,
+
+    // Resolver used by the delegation health check to look up nameservers without glue,
+    // e.g. 172.20.0.53:53. Empty disables the check.
+    pub dns_health_resolver: String,
+    // Port the delegated nameservers are queried on
+    pub dns_health_nameserver_port: u16,
+    pub dns_health_timeout_seconds: u64,
+    // Delegations checked in parallel
+    pub dns_health_concurrency: usize,
+    // Failed checks kept per domain
+    pub dns_health_history_size: usize,
+}
+
+// Every field is optional, unset fields fall back to the task's own defaults.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct TaskConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    // 0 means the task only runs after its dependencies.
+    // Tasks without dependencies default to update_interval_seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_seconds: Option<u64>,
+    // Ids of tasks that trigger this one when they complete successfully
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jitter_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_backoff_seconds: Option<u64>,
+}
+
+impl AppConfig {
+    // Configuration for generating the registry as it was at a past revision.
+    // External ROA sources are left out, only their current content could be read.
+    pub fn at_revision(&self, revision: &str) -> AppConfig {
+        AppConfig {
+            git_repo_revision: revision.to_string(),
+            do_git_pull: false,
+            roa_sources: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+// A file or http(s) URL with ROAs to publish next to the registry's.
+// A source that cannot be read keeps its last successfully read ROAs and does not affect the others.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct RoaSourceConfig {
+    pub name: String,
+    pub location: String,
+    pub format: RoaSourceFormat,
+    // Trust anchor reported for the source's ROAs, defaults to the source name
+    pub trust_anchor: String,
 }
 
 impl Default for AppConfig {
@@ -48,49 +236,144 @@ impl Default for AppConfig {
             roa_endpoint: "/roa.json".to_string(),
             dns_config_endpoint: "/dns/config.json".to_string(),
             dns_content_endpoint_directory: "/dns/content".to_string(),
+            status_endpoint: "/status".to_string(),
+            rdap_endpoint_directory: "/rdap".to_string(),
+            rdap_base_url: String::new(),
+            lookup_endpoint_directory: "/lookup".to_string(),
+            asn_endpoint_directory: "/asn".to_string(),
+            mntner_endpoint_directory: "/mntner".to_string(),
+            roa_diagnostics_endpoint: "/roa/diagnostics".to_string(),
+            dns_lint_endpoint: "/dns/lint".to_string(),
+            dns_health_endpoint_directory: "/dns/health".to_string(),
+            filter_endpoint_directory: "/filters".to_string(),
+            filter_max_depth: 20,
+            history_endpoint_directory: "/history".to_string(),
+            history_size: 100,
+            events_endpoint: "/events".to_string(),
+            events_websocket_endpoint: "/events/ws".to_string(),
+            admin_refresh_endpoint: "/admin/refresh".to_string(),
+            admin_token: String::new(),
+            webhook_endpoint: "/webhook/push".to_string(),
+            webhook_secret: String::new(),
             do_git_pull: true,
             git_repo_url: "git@git.dn42.dev:dn42/registry.git".to_string(),
             git_repo_local_path: "./registry".to_string(),
+            git_repo_revision: String::new(),
             git_repo_ipv4_route_relative_path: "data/route".to_string(),
             git_repo_ipv6_route_relative_path: "data/route6".to_string(),
 
             git_repo_dns_relative_path: "data/dns".to_string(),
             git_repo_inetnum_relative_path: "data/inetnum".to_string(),
             git_repo_inet6num_relative_path: "data/inet6num".to_string(),
+            git_repo_data_relative_path: "data".to_string(),
+            git_repo_aut_num_relative_path: "data/aut-num".to_string(),
+
+            whois_listen_address: String::new(),
+
+            roa_compression: false,
+            roa_normalise_prefixes: false,
+            roa_exclude_unregistered_origins: false,
+            aspa_provider_attribute: String::new(),
+            roa_trust_anchor: "dn42".to_string(),
+            roa_expiry_seconds: 86400,
+            roa_json_style: RoaJsonStyle::RpkiClient,
+            roa_sources: Vec::new(),
 
             update_interval_seconds: 300,
+            config_watch_interval_seconds: 5,
+            tasks: HashMap::new(),
 
             dns_primary_master: "default-not-set".to_string(),
             dns_responsible_party: "default-not-set".to_string(),
+
+            dns_health_resolver: String::new(),
+            dns_health_nameserver_port: 53,
+            dns_health_timeout_seconds: 3,
+            dns_health_concurrency: 16,
+            dns_health_history_size: 20,
         }
     }
 }
 
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct RoaSourceStatus {
+    pub name: String,
+    pub location: String,
+    // ROAs taken from the source, including ones that were already present
+    pub roas: usize,
+    pub warnings: Vec<String>,
+    pub last_success: Option<String>,
+    pub last_error: Option<String>,
+    // Last successfully read content, reused while the source is failing
+    #[serde(skip)]
+    pub parsed: Option<ParsedRoaSource>,
+}
+
 pub struct ROACache {
-    pub json_content: String,
+    pub output: RpkiClientOutput,
+    pub json_content: CachedContent,
+    // Every other RoaFormat, selected with ?format= on roa_endpoint
+    pub formatted_content: HashMap<RoaFormat, CachedContent>,
+    // roa_json_style the cached content was rendered with
+    pub json_style: RoaJsonStyle,
+    // ROAs by prefix, rebuilt together with the output
+    pub index: PrefixTrie<Vec<ROA>>,
+    // Route objects rejected or corrected during the last generation
+    pub diagnostics: Vec<RouteDiagnostic>,
+    // One entry per configured roa_sources entry, in configuration order
+    pub sources: Vec<RoaSourceStatus>,
+    // Only moves forward when the generated ROA set actually changes
     pub last_updated: std::time::SystemTime,
 }
 
 impl Default for ROACache {
     fn default() -> Self {
+        let now = std::time::SystemTime::now();
+
         ROACache {
-            json_content: String::new(),
-            last_updated: std::time::SystemTime::now(),
+            output: RpkiClientOutput::default(),
+            json_content: CachedContent::new("application/json", "", now),
+            formatted_content: RoaFormat::iter()
+                .filter(|f| *f != RoaFormat::Json)
+                .map(|f| (f, CachedContent::new(f.content_type(), "", now)))
+                .collect(),
+            json_style: RoaJsonStyle::default(),
+            index: PrefixTrie::new(),
+            diagnostics: Vec::new(),
+            sources: Vec::new(),
+            last_updated: now,
         }
     }
 }
 
 pub struct DNSCache {
+    // zone -> parsed zone, kept to detect whether a regeneration changed anything
+    pub zones: HashMap<String, DNSZone>,
     // zone -> zone content
-    pub content: HashMap<String, String>,
+    pub content: HashMap<String, CachedContent>,
+    // JSON list of zone names
+    pub config_content: CachedContent,
+    // Delegation problems found during the last generation
+    pub lint: Vec<DnsLintProblem>,
     pub last_updated: std::time::SystemTime,
 }
 
 impl Default for DNSCache {
     fn default() -> Self {
+        let now = std::time::SystemTime::now();
+
         DNSCache {
+            zones: HashMap::new(),
             content: HashMap::new(),
-            last_updated: std::time::SystemTime::now(),
+            config_content: CachedContent::new("application/json", "[]", now),
+            lint: Vec::new(),
+            last_updated: now,
         }
     }
-}
\ No newline at end of file
+}
+#[derive(Default)]
+pub struct DnsHealthCache {
+    // zone -> result of the last check, with its failure history
+    pub domains: BTreeMap<String, DomainHealth>,
+    pub last_checked: Option<String>,
+}
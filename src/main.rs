@@ -1,128 +1,80 @@
-use axum::body::Body;
-use axum::extract::State;
-use axum::http::StatusCode;
-use axum::response::{IntoResponse, Response};
-use axum::routing::get;
-use axum::Router;
-use dn42_roa_generator::io::background_updater;
-use dn42_roa_generator::{AppConfig, AppState};
-use std::env;
-use std::path::Path;
-use tracing::info;
-
-const CONFIG_PATH: &str = "config.json";
-
-fn init_default_config() -> anyhow::Result<()> {
-    let default_config = AppConfig::default();
-
-    let config_json = serde_json::to_string_pretty(&default_config)?;
-
-    std::fs::write(CONFIG_PATH, config_json)?;
-
-    info!("Wrote default configuration to {}", CONFIG_PATH);
-
-    Ok(())
-}
-
-fn init_app_state() -> AppState {
-    let config_path = env::var("CONFIG_PATH").unwrap_or_else(|_| CONFIG_PATH.to_string());
-
-    let config_path = Path::new(config_path.as_str());
+use clap::Parser;
+use dn42_roa_generator::cli::{generate_roa, generate_zones, print_default_config, validate, Cli, Command, GenerateTarget};
+use dn42_roa_generator::config::{load_config, ConfigReloader};
+use dn42_roa_generator::scheduler::run_scheduler;
+use dn42_roa_generator::server::whois::run_whois_server;
+use dn42_roa_generator::server::{build_router, reloadable_router};
+use dn42_roa_generator::task::default_tasks;
+use dn42_roa_generator::{AppState, ConfigHandle};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, RwLock};
+use tracing::{error, info};
+
+async fn serve(config_path: PathBuf) -> anyhow::Result<()> {
+    let app_config = load_config(&config_path)?;
+
+    let app_state = AppState {
+        config: ConfigHandle::new(app_config),
+        ..Default::default()
+    };
 
-    let app_config = if config_path.exists() {
-        info!("Loaded configuration from {:?}", config_path);
+    let config = app_state.config.get();
 
-        serde_json::from_reader(std::fs::File::open(config_path).unwrap())
-            .unwrap_or_else(|e| {
-                panic!("Failed to load configuration from {:?}: {:?}", config_path, e);
-            })
-    } else {
-        info!("Configuration file {:?} does not exist. Using default configuration.", config_path);
+    let update_task_app_state = app_state.clone();
+    let tasks = default_tasks(&app_state);
+    let scheduler_tasks = tasks.clone();
 
-        if let Err(e) = init_default_config() {
-            panic!("Failed to write default configuration to {:?}: {:?}", config_path, e);
+    tokio::spawn(async move {
+        if let Err(e) = run_scheduler(update_task_app_state, scheduler_tasks).await {
+            error!("Task scheduler failed to start: {:?}", e);
         }
+    });
 
-        AppConfig::default()
-    };
-
-    AppState {
-        config: std::sync::Arc::new(app_config),
-        ..Default::default()
-    }
-}
-
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
+    let whois_app_state = app_state.clone();
 
-    let app_state = init_app_state();
+    tokio::spawn(async move {
+        if let Err(e) = run_whois_server(whois_app_state).await {
+            error!("Whois server failed: {:?}", e);
+        }
+    });
 
-    let update_task_app_state = app_state.clone();
+    let router = Arc::new(RwLock::new(build_router(app_state.clone(), &config)));
 
-    tokio::spawn(async move { background_updater(update_task_app_state).await; });
+    let reloader = ConfigReloader::new(config_path, app_state.clone(), router.clone(), tasks);
+    tokio::spawn(reloader.run());
 
-    let app = Router::new()
-        .route(&app_state.config.roa_endpoint, get(get_roa_json))
-        .route(&app_state.config.dns_config_endpoint, get(get_dns_conf))
-        .route(&format!("{}/{{zone}}", app_state.config.dns_content_endpoint_directory), get(get_dns_zone))
-        .with_state(app_state.clone());
+    let app = reloadable_router(router);
 
-    let listener = tokio::net::TcpListener::bind(&app_state.config.listen_address).await?;
+    let listener = tokio::net::TcpListener::bind(&config.listen_address).await?;
 
-    info!("Listening on: {}", &app_state.config.listen_address);
+    info!("Listening on: {}", &config.listen_address);
 
     axum::serve(listener, app).await?;
 
     Ok(())
 }
 
-async fn get_roa_json(State(state): State<AppState>) -> Response<Body> {
-    let data = match state.roa_data.read() {
-        Ok(data) => data,
-        Err(_) => {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+#[tokio::main]
+async fn main() -> anyhow::Result<ExitCode> {
+    // Logs go to stderr so generated output can be piped from stdout
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
 
-    (
-        [("Content-Type", "application/json")],
-        data.json_content.clone(),
-    ).into_response()
-}
+    let cli = Cli::parse();
 
-async fn get_dns_conf(State(state): State<AppState>) -> Response<Body> {
-    let data = match state.dns_data.read() {
-        Ok(data) => data,
-        Err(_) => {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(cli.config).await?,
+        Command::Generate { target: GenerateTarget::Roa { format, registry, revision, output } } => {
+            generate_roa(&cli.config, format, registry.as_deref(), revision.as_deref(), output.as_deref())?
         }
-    };
-
-    let zone_name = data.content.keys().collect::<Vec<_>>();
-
-    (
-        [("Content-Type", "application/json")],
-        serde_json::to_string(&zone_name).unwrap_or_else(|_| "[]".to_string()),
-    ).into_response()
-}
-
-async fn get_dns_zone(
-    State(state): State<AppState>,
-    axum::extract::Path(zone_name): axum::extract::Path<String>,
-) -> Response<Body> {
-    let data = match state.dns_data.read() {
-        Ok(data) => data,
-        Err(_) => {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        Command::Generate { target: GenerateTarget::Zones { registry, revision, out_dir } } => {
+            generate_zones(&cli.config, registry.as_deref(), revision.as_deref(), &out_dir)?
         }
-    };
-
-    match data.content.get(&zone_name) {
-        Some(zone_content) => (
-            [("Content-Type", "text/plain")],
-            zone_content.clone(),
-        ).into_response(),
-        None => StatusCode::NOT_FOUND.into_response(),
+        Command::Validate { config_file, registry } => {
+            return Ok(validate(&config_file.unwrap_or(cli.config), registry.as_deref()));
+        }
+        Command::PrintDefaultConfig { format } => print_default_config(format)?,
     }
-}
\ No newline at end of file
+
+    Ok(ExitCode::SUCCESS)
+}
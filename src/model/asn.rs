@@ -0,0 +1,53 @@
+// Parses an AS number written as asplain (AS4242420000), asdot (AS64512.1, RFC 5396)
+// or without the AS prefix (4242420000), case-insensitive
+pub fn parse_asn(value: &str) -> Option<u32> {
+    let value = value.trim();
+
+    let number = match value.get(..2) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("as") => &value[2..],
+        _ => value,
+    };
+
+    let parse_part = |part: &str| {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        part.parse::<u32>().ok()
+    };
+
+    match number.split_once('.') {
+        Some((high, low)) => {
+            let high = u16::try_from(parse_part(high)?).ok()?;
+            let low = u16::try_from(parse_part(low)?).ok()?;
+
+            Some(((high as u32) << 16) | low as u32)
+        }
+        None => parse_part(number),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_asn() {
+        assert_eq!(parse_asn("AS4242420000"), Some(4242420000));
+        assert_eq!(parse_asn("as4242420000"), Some(4242420000));
+        assert_eq!(parse_asn("4242420000"), Some(4242420000));
+        assert_eq!(parse_asn(" AS64512 "), Some(64512));
+
+        // asdot
+        assert_eq!(parse_asn("AS64722.1"), Some(4241620993));
+        assert_eq!(parse_asn("0.65535"), Some(65535));
+        assert_eq!(parse_asn("AS1.0"), Some(65536));
+
+        assert_eq!(parse_asn("AS"), None);
+        assert_eq!(parse_asn("AS-FOO"), None);
+        assert_eq!(parse_asn("+5"), None);
+        assert_eq!(parse_asn("AS4294967296"), None);
+        assert_eq!(parse_asn("AS65536.1"), None);
+        assert_eq!(parse_asn("AS1."), None);
+        assert_eq!(parse_asn("AS1.2.3"), None);
+    }
+}
@@ -0,0 +1,102 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use strum::{Display, EnumIter};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Display, EnumIter)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum HealthProblemKind {
+    // No glue, and the resolver has no address for the nameserver
+    Unresolvable,
+    // The nameserver does not answer authoritatively for the delegated zone
+    Lame,
+    // The zone's own NS set differs from the registry's nameservers
+    NsMismatch,
+    // The registry's DS records match no DNSKEY served by the zone
+    DsMismatch,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct HealthProblem {
+    pub kind: HealthProblemKind,
+    pub nameserver: Option<String>,
+    pub message: String,
+}
+
+// One address of a nameserver, taken from glue or looked up through the resolver
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct NameserverCheck {
+    pub nameserver: String,
+    pub address: String,
+    pub authoritative: bool,
+    // SOA serial served for the zone
+    pub serial: Option<u32>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct HealthFailure {
+    pub time: String,
+    pub problems: Vec<HealthProblem>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DomainHealth {
+    pub zone: String,
+    pub file: String,
+    pub checked: String,
+    pub healthy: bool,
+    pub nameservers: Vec<NameserverCheck>,
+    pub problems: Vec<HealthProblem>,
+    // Failed checks, oldest first, bounded by dns_health_history_size
+    pub failures: VecDeque<HealthFailure>,
+}
+
+impl DomainHealth {
+    // Carries the failure history of the previous check over and adds this check if it failed
+    pub fn with_history(mut self, previous: Option<&DomainHealth>, capacity: usize) -> DomainHealth {
+        if let Some(previous) = previous {
+            self.failures = previous.failures.clone();
+        }
+
+        if !self.healthy {
+            self.failures.push_back(HealthFailure { time: self.checked.clone(), problems: self.problems.clone() });
+        }
+
+        while self.failures.len() > capacity {
+            self.failures.pop_front();
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(checked: &str, problems: Vec<HealthProblem>) -> DomainHealth {
+        DomainHealth {
+            zone: "example.dn42".to_string(),
+            file: "data/dns/example.dn42".to_string(),
+            checked: checked.to_string(),
+            healthy: problems.is_empty(),
+            nameservers: Vec::new(),
+            problems,
+            failures: VecDeque::new(),
+        }
+    }
+
+    #[test]
+    fn test_failure_history() {
+        let lame = || vec![HealthProblem { kind: HealthProblemKind::Lame, nameserver: None, message: "lame".to_string() }];
+
+        let first = health("1", lame()).with_history(None, 2);
+        let second = health("2", Vec::new()).with_history(Some(&first), 2);
+        let third = health("3", lame()).with_history(Some(&second), 2);
+        let fourth = health("4", lame()).with_history(Some(&third), 2);
+
+        assert_eq!(second.failures.iter().map(|f| f.time.as_str()).collect::<Vec<_>>(), vec!["1"]);
+        assert_eq!(fourth.failures.iter().map(|f| f.time.as_str()).collect::<Vec<_>>(), vec!["3", "4"]);
+        assert!(health("5", lame()).with_history(Some(&fourth), 0).failures.is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+use crate::model::output::ROA;
+use serde::Serialize;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Arc;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct VersionInfo {
+    pub version: u64,
+    // Registry commit the version was generated from
+    pub commit: Option<String>,
+    pub time: String,
+    #[serde(skip)]
+    pub timestamp: i64,
+    pub count: usize,
+}
+
+// A DNS record in zone file syntax, SOA records are left out since their serial changes on every version
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ZoneRecord {
+    pub zone: String,
+    pub record: String,
+}
+
+// ROAs are stored without their expiry, which moves forward without the authorisation changing
+pub type RoaHistory = History<ROA>;
+pub type DnsHistory = History<ZoneRecord>;
+
+#[derive(Debug)]
+pub struct HistoryEntry<T> {
+    pub info: VersionInfo,
+    pub items: Arc<BTreeSet<T>>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct HistoryDiff<T> {
+    pub from: VersionInfo,
+    pub to: VersionInfo,
+    pub added: Vec<T>,
+    pub removed: Vec<T>,
+}
+
+// The last generated outputs, oldest first. Versions keep counting up when
+// older entries are dropped, so a version number always names the same content.
+#[derive(Debug)]
+pub struct History<T> {
+    entries: VecDeque<HistoryEntry<T>>,
+    last_version: u64,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        History { entries: VecDeque::new(), last_version: 0 }
+    }
+}
+
+impl<T: Ord + Clone> History<T> {
+    // Records a new version and drops the oldest ones beyond capacity, returns the new version number
+    pub fn push(&mut self, commit: Option<String>, items: BTreeSet<T>, capacity: usize) -> u64 {
+        let now = chrono::Utc::now();
+
+        self.last_version += 1;
+
+        self.entries.push_back(HistoryEntry {
+            info: VersionInfo {
+                version: self.last_version,
+                commit,
+                time: now.to_rfc3339(),
+                timestamp: now.timestamp(),
+                count: items.len(),
+            },
+            items: Arc::new(items),
+        });
+
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+
+        self.last_version
+    }
+
+    pub fn last_version(&self) -> u64 {
+        self.last_version
+    }
+
+    pub fn versions(&self) -> Vec<VersionInfo> {
+        self.entries.iter().map(|entry| entry.info.clone()).collect()
+    }
+
+    pub fn get(&self, version: u64) -> Option<&HistoryEntry<T>> {
+        self.entries.iter().find(|entry| entry.info.version == version)
+    }
+
+    pub fn oldest(&self) -> Option<&HistoryEntry<T>> {
+        self.entries.front()
+    }
+
+    pub fn latest(&self) -> Option<&HistoryEntry<T>> {
+        self.entries.back()
+    }
+
+    // The version that was current at the given unix timestamp
+    pub fn at(&self, timestamp: i64) -> Option<&HistoryEntry<T>> {
+        self.entries.iter().rev().find(|entry| entry.info.timestamp <= timestamp)
+    }
+
+    pub fn diff(from: &HistoryEntry<T>, to: &HistoryEntry<T>) -> HistoryDiff<T> {
+        HistoryDiff {
+            from: from.info.clone(),
+            to: to.info.clone(),
+            added: to.items.difference(&from.items).cloned().collect(),
+            removed: from.items.difference(&to.items).cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(values: &[u32]) -> BTreeSet<u32> {
+        values.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_history_capacity_and_versions() {
+        let mut history = History::default();
+
+        for i in 0..5 {
+            assert_eq!(history.push(Some(format!("commit{}", i)), items(&[i]), 3), (i + 1) as u64);
+        }
+
+        assert_eq!(history.versions().iter().map(|v| v.version).collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert!(history.get(2).is_none());
+        assert_eq!(history.get(4).unwrap().info.commit.as_deref(), Some("commit3"));
+        assert_eq!(history.last_version(), 5);
+
+        let mut disabled = History::default();
+        disabled.push(None, items(&[1]), 0);
+        assert_eq!(disabled.last_version(), 1);
+        assert!(disabled.latest().is_none());
+    }
+
+    #[test]
+    fn test_history_diff_and_at() {
+        let mut history = History::default();
+        history.push(None, items(&[1, 2, 3]), 10);
+        history.push(None, items(&[2, 3, 4]), 10);
+        history.push(None, items(&[3, 4, 5]), 10);
+
+        let diff = History::diff(history.get(1).unwrap(), history.latest().unwrap());
+        assert_eq!(diff.added, vec![4, 5]);
+        assert_eq!(diff.removed, vec![1, 2]);
+        assert_eq!((diff.from.version, diff.to.version), (1, 3));
+
+        let now = chrono::Utc::now().timestamp();
+        assert_eq!(history.at(now + 10).unwrap().info.version, 3);
+        assert!(history.at(now - 3600).is_none());
+    }
+}
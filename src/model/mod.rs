@@ -1,3 +1,8 @@
 pub mod output;
 pub mod record;
-pub mod dns;
\ No newline at end of file
+pub mod dns;
+pub mod registry;
+pub mod trie;
+pub mod asn;
+pub mod history;
+pub mod health;
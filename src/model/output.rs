@@ -1,36 +1,123 @@
 use std::net::IpAddr;
 use serde::Serialize;
 
-#[derive(Serialize, Debug)]
+fn is_zero(value: &i64) -> bool {
+    *value == 0
+}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct Metadata {
     #[serde(rename = "buildtime")]
     pub build_time: String,
+    // Unix timestamp of build_time
+    pub generated: i64,
+    #[serde(rename = "generatedby")]
+    pub generated_by: String,
+    // Registry commit the ROAs were generated from, when the checkout is a git repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
     pub counts: u64,
     pub roas: u64,
+    #[serde(rename = "ipv4roas")]
+    pub ipv4_roas: u64,
+    #[serde(rename = "ipv6roas")]
+    pub ipv6_roas: u64,
+    // Set when the ROA set was compressed, roas/counts then hold the compressed size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspas: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompressionMetadata {
+    pub before: u64,
+    pub after: u64,
 }
 
 impl Default for Metadata {
     fn default() -> Self {
         Metadata {
             build_time: "".to_string(),
+            generated: 0,
+            generated_by: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            commit: None,
             counts: 0,
             roas: 0,
+            ipv4_roas: 0,
+            ipv6_roas: 0,
+            compression: None,
+            aspas: None,
         }
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ROA {
     pub asn: u32,
     pub prefix: String,
     #[serde(rename = "maxLength")]
     pub max_length: u8,
+    // Trust anchor name, set for every published ROA
+    pub ta: String,
+    // Unix timestamp after which consumers should drop the ROA, 0 when expiry is disabled
+    #[serde(skip_serializing_if = "is_zero")]
+    pub expires: i64,
 }
 
-#[derive(Serialize, Debug, Default)]
+
+// Customer AS to the set of its upstream providers, as in rpki-client's aspas section
+#[derive(Serialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Aspa {
+    pub customer_asid: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub expires: i64,
+    pub providers: Vec<u32>,
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
 pub struct RpkiClientOutput {
     pub metadata: Metadata,
     pub roas: Vec<ROA>,
+    // Only present when ASPA generation is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspas: Option<Vec<Aspa>>,
+}
+
+impl RpkiClientOutput {
+    // Recomputes the metadata counts after the ROA or ASPA set changed
+    pub fn update_counts(&mut self) {
+        let ipv6_roas = self.roas.iter().filter(|roa| roa.prefix.contains(':')).count() as u64;
+
+        self.metadata.counts = self.roas.len() as u64;
+        self.metadata.roas = self.roas.len() as u64;
+        self.metadata.ipv4_roas = self.roas.len() as u64 - ipv6_roas;
+        self.metadata.ipv6_roas = ipv6_roas;
+        self.metadata.aspas = self.aspas.as_ref().map(|aspas| aspas.len() as u64);
+    }
+
+    // Sets the expiry of every ROA and ASPA, and the trust anchor of ROAs that have none yet
+    pub fn stamp(&mut self, ta: &str, expires: i64) {
+        for roa in &mut self.roas {
+            if roa.ta.is_empty() {
+                roa.ta = ta.to_string();
+            }
+
+            roa.expires = expires;
+        }
+
+        for aspa in self.aspas.iter_mut().flatten() {
+            aspa.expires = expires;
+        }
+    }
+
+    pub fn expires(&self) -> Option<i64> {
+        self.roas
+            .iter()
+            .map(|roa| roa.expires)
+            .chain(self.aspas.iter().flatten().map(|aspa| aspa.expires))
+            .min()
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -2,165 +2,182 @@ use std::collections::HashMap;
 use std::fmt::Display;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::{Path, PathBuf};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::str::FromStr;
 use strum::{Display, EnumString};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+// Ordered by address family, then network address, then prefix length, so a
+// prefix sorts before its subnets
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Prefix {
     network: IpAddr,
     prefix_len: u8,
 }
 
-fn octets_to_bits(octets: &[u8], prefix_len: u8) -> Vec<u8> {
-    let mut bits = Vec::new();
-    let total_bits = prefix_len as usize;
-
-    for &octet in octets {
-        for i in (0..8).rev() {
-            if bits.len() < total_bits {
-                bits.push((octet >> i) & 1);
-            } else {
-                break;
-            }
-        }
-    }
-
-    bits
+fn max_prefix_len(network: &IpAddr) -> u8 {
+    if network.is_ipv4() { 32 } else { 128 }
 }
 
-fn bits_to_octets(bits: &[u8]) -> Vec<u8> {
-    let mut octets = Vec::new();
-    let mut current_octet = 0u8;
-
-    for (i, bit) in bits.iter().enumerate() {
-        current_octet = (current_octet << 1) | bit;
-
-        if (i + 1) % 8 == 0 {
-            octets.push(current_octet);
-            current_octet = 0;
-        }
+// Address as an integer, IPv4 addresses use the low 32 bits
+fn address_to_int(address: &IpAddr) -> u128 {
+    match address {
+        IpAddr::V4(ipv4) => u32::from(*ipv4) as u128,
+        IpAddr::V6(ipv6) => u128::from(*ipv6),
     }
+}
 
-    if !bits.len().is_multiple_of(8) {
-        current_octet <<= 8 - (bits.len() % 8);
-        octets.push(current_octet);
+fn int_to_address(template: &IpAddr, value: u128) -> IpAddr {
+    match template {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(value as u32)),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(value)),
     }
-
-    octets
 }
 
-fn slice_zero_fill<const N: usize>(vec: &[u8]) -> [u8; N] {
-    let mut slice: [u8; N] = [0u8; N];
-    for (i, &octet) in vec.iter().enumerate().take(N) {
-        slice[i] = octet;
-    }
-    slice
+// Host bits of a prefix with the given length, within an address of max_len bits
+fn host_mask(max_len: u8, prefix_len: u8) -> u128 {
+    let all = u128::MAX.checked_shr(128 - max_len as u32).unwrap_or(0);
+
+    all.checked_shr(prefix_len as u32).unwrap_or(0)
 }
 
 impl Prefix {
+    // Clears host bits, unlike parsing which keeps the address as written
     pub fn new(network: IpAddr, prefix_len: u8) -> Result<Self, String> {
-        let network = match network {
-            IpAddr::V4(ipv4) => {
-                if prefix_len > 32 {
-                    return Err(format!("Invalid prefix length for IPv4: {}", prefix_len));
-                }
+        if prefix_len > max_prefix_len(&network) {
+            let family = if network.is_ipv4() { "IPv4" } else { "IPv6" };
+            return Err(format!("Invalid prefix length for {}: {}", family, prefix_len));
+        }
 
-                IpAddr::V4(Ipv4Addr::from(slice_zero_fill(&bits_to_octets(&octets_to_bits(&ipv4.octets(), prefix_len)))))
-            }
-            IpAddr::V6(ipv6) => {
-                if prefix_len > 128 {
-                    return Err(format!("Invalid prefix length for IPv6: {}", prefix_len));
-                }
-
-                IpAddr::V6(Ipv6Addr::from(slice_zero_fill(&bits_to_octets(&octets_to_bits(&ipv6.octets(), prefix_len)))))
-            }
-        };
+        let host_mask = host_mask(max_prefix_len(&network), prefix_len);
 
         Ok(Prefix {
-            network,
+            network: int_to_address(&network, address_to_int(&network) & !host_mask),
             prefix_len,
         })
     }
 
+    // Host bits beyond the shorter of both lengths are cleared
     pub fn with_prefix_len(&self, new_prefix_len: u8) -> Self {
-        let mut bits = self.get_bits();
-
-        bits.truncate(new_prefix_len as usize);
-
-        let octets = bits_to_octets(&bits);
-
-        let network = match self.network {
-            IpAddr::V4(_) => {
-                let octets: [u8; 4] = slice_zero_fill(&octets);
-                IpAddr::V4(Ipv4Addr::from(octets))
-            }
-            IpAddr::V6(_) => {
-                let octets = slice_zero_fill(&octets);
-                IpAddr::V6(std::net::Ipv6Addr::from(octets))
-            }
-        };
+        let max_len = max_prefix_len(&self.network);
+        let host_mask = host_mask(max_len, new_prefix_len.min(self.prefix_len));
 
         Prefix {
-            network,
+            network: int_to_address(&self.network, address_to_int(&self.network) & !host_mask),
             prefix_len: new_prefix_len,
         }
     }
 
-    pub fn from_bits_v4(bits: &[u8]) -> Option<Self> {
-        if bits.len() > 32 {
-            return None;
-        }
+    pub fn network(&self) -> &IpAddr {
+        &self.network
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
 
-        let octets = bits_to_octets(bits);
+    pub fn is_ipv4(&self) -> bool {
+        self.network.is_ipv4()
+    }
 
-        let octets: [u8; 4] = slice_zero_fill(&octets);
+    fn host_mask(&self) -> u128 {
+        host_mask(max_prefix_len(&self.network), self.prefix_len)
+    }
 
-        Some(Prefix {
-            network: IpAddr::V4(Ipv4Addr::from(octets)),
-            prefix_len: bits.len() as u8,
-        })
+    // Lowest address inside the prefix, ignoring host bits kept from parsing
+    pub fn first_address(&self) -> IpAddr {
+        int_to_address(&self.network, address_to_int(&self.network) & !self.host_mask())
     }
 
-    pub fn from_bits_v6(bits: &[u8]) -> Option<Self> {
-        if bits.len() > 128 {
-            return None;
+    // Highest address inside the prefix
+    pub fn last_address(&self) -> IpAddr {
+        int_to_address(&self.network, address_to_int(&self.network) | self.host_mask())
+    }
+
+    // Number of addresses, saturating for ::/0
+    pub fn host_count(&self) -> u128 {
+        self.host_mask().saturating_add(1)
+    }
+
+    pub fn contains(&self, other: &Prefix) -> bool {
+        if self.network.is_ipv4() != other.network.is_ipv4() || self.prefix_len > other.prefix_len {
+            return false;
         }
 
-        let octets = bits_to_octets(bits);
+        let network_mask = !self.host_mask();
 
-        let octets = slice_zero_fill(&octets);
+        address_to_int(&self.network) & network_mask == address_to_int(&other.network) & network_mask
+    }
 
-        Some(Prefix {
-            network: IpAddr::V6(std::net::Ipv6Addr::from(octets)),
-            prefix_len: bits.len() as u8,
-        })
+    pub fn contains_address(&self, address: &IpAddr) -> bool {
+        let max_len = max_prefix_len(address);
+        self.contains(&Prefix { network: *address, prefix_len: max_len })
     }
 
-    pub fn get_bits(&self) -> Vec<u8> {
-        let to_bits = |octets: &[u8]| octets
-            .iter()
-            .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1))
-            .take(self.prefix_len as usize)
-            .collect::<Vec<u8>>();
+    // Two prefixes overlap when either one contains the other
+    pub fn overlaps(&self, other: &Prefix) -> bool {
+        self.contains(other) || other.contains(self)
+    }
 
-        match self.network {
-            IpAddr::V4(addr) => to_bits(&addr.octets()),
-            IpAddr::V6(addr) => to_bits(&addr.octets()),
-        }
+    // The prefix one bit shorter, None for a default route
+    pub fn supernet(&self) -> Option<Prefix> {
+        self.prefix_len.checked_sub(1).map(|len| self.with_prefix_len(len))
     }
 
-    pub fn network(&self) -> &IpAddr {
-        &self.network
+    // All covering prefixes from the direct supernet up to the default route
+    pub fn supernets(&self) -> impl Iterator<Item = Prefix> + '_ {
+        (0..self.prefix_len).rev().map(|len| self.with_prefix_len(len))
     }
 
-    pub fn prefix_len(&self) -> u8 {
-        self.prefix_len
+    // All subnets of the given length in address order, empty if the length is shorter or invalid
+    pub fn subnets(&self, prefix_len: u8) -> Subnets {
+        let max_len = max_prefix_len(&self.network);
+
+        if prefix_len < self.prefix_len || prefix_len > max_len {
+            return Subnets { template: self.network, prefix_len, next: None, last: 0 };
+        }
+
+        let first = address_to_int(&self.network) & !self.host_mask();
+
+        Subnets {
+            template: self.network,
+            prefix_len,
+            next: Some(first),
+            last: first | self.host_mask(),
+        }
+    }
+}
+
+pub struct Subnets {
+    template: IpAddr,
+    prefix_len: u8,
+    next: Option<u128>,
+    // Highest address of the parent prefix
+    last: u128,
+}
+
+impl Iterator for Subnets {
+    type Item = Prefix;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+        let host_mask = host_mask(max_prefix_len(&self.template), self.prefix_len);
+
+        self.next = match current | host_mask {
+            end if end >= self.last => None,
+            end => Some(end + 1),
+        };
+
+        Some(Prefix {
+            network: int_to_address(&self.template, current),
+            prefix_len: self.prefix_len,
+        })
     }
 }
 
 impl FromStr for Prefix {
     type Err = String;
 
+    // Host bits are kept as written, use Prefix::new to normalise
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let parts: Vec<&str> = s.split('/').collect();
         if parts.len() != 2 {
@@ -197,6 +214,20 @@ impl Display for Prefix {
     }
 }
 
+impl Serialize for Prefix {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Prefix {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        Prefix::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, EnumString, Display)]
 pub enum RecordField {
     #[strum(serialize = "route")]
@@ -230,6 +261,9 @@ pub enum RecordField {
 pub struct RecordFile {
     file_path: PathBuf,
     field_map: HashMap<RecordField, Vec<String>>,
+    // Every attribute in file order, including the ones RecordField does not know about
+    attributes: Vec<(String, String)>,
+    content: String,
 }
 
 fn parse_content(content: &str) -> HashMap<RecordField, Vec<String>> {
@@ -255,14 +289,93 @@ fn parse_content(content: &str) -> HashMap<RecordField, Vec<String>> {
     field_map
 }
 
+fn parse_attributes(content: &str) -> Vec<(String, String)> {
+    let mut attributes: Vec<(String, String)> = Vec::new();
+
+    for line in content.lines() {
+        // RPSL continuation lines start with whitespace or '+'
+        if line.starts_with([' ', '\t', '+']) {
+            if let Some((_, value)) = attributes.last_mut() {
+                let continuation = line.strip_prefix('+').unwrap_or(line).trim();
+
+                if !continuation.is_empty() {
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(continuation);
+                }
+            }
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            let key = key.trim();
+
+            if key.is_empty() || key.starts_with(['%', '#']) {
+                continue;
+            }
+
+            attributes.push((key.to_lowercase(), value.trim().to_owned()));
+        }
+    }
+
+    attributes
+}
+
 impl RecordFile {
     pub fn new(file: PathBuf) -> anyhow::Result<RecordFile> {
         let content = std::fs::read_to_string(&file)?;
 
-        Ok(RecordFile {
+        Ok(RecordFile::from_content(file, content))
+    }
+
+    pub fn from_content(file: PathBuf, content: String) -> RecordFile {
+        RecordFile {
             file_path: file,
             field_map: parse_content(&content),
-        })
+            attributes: parse_attributes(&content),
+            content,
+        }
+    }
+
+    // RPSL class of the object, e.g. "aut-num" or "inetnum"
+    pub fn object_type(&self) -> &str {
+        self.attributes.first().map(|(key, _)| key.as_str()).unwrap_or("")
+    }
+
+    // Lookup key of the object. inetnum objects are keyed by their cidr rather than the address range,
+    // persons and roles by their nic-hdl rather than their name.
+    pub fn primary_key(&self) -> &str {
+        let key = match self.object_type() {
+            "inetnum" | "inet6num" => self.get_attribute("cidr"),
+            "person" | "role" => self.get_attribute("nic-hdl"),
+            _ => None,
+        };
+
+        key.or_else(|| self.attributes.first().map(|(_, value)| value.as_str())).unwrap_or("")
+    }
+
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+
+    pub fn get_attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn get_attributes<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.attributes
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    // The object exactly as stored in the registry
+    pub fn content(&self) -> &str {
+        &self.content
     }
 
     pub fn get_field(&self, key: RecordField) -> Option<&Vec<String>> {
@@ -280,19 +393,155 @@ mod tests {
     use std::net::Ipv4Addr;
 
     #[test]
-    fn test_bits_to_octets() {
-        // 192 (11000000)
-        let bits = vec![1, 1, 0, 0, 0, 0, 0, 0];
-        assert_eq!(bits_to_octets(&bits), vec![192]);
+    fn test_record_attributes() {
+        let content = "aut-num:            AS4242420000\n\
+                       as-name:            EXAMPLE-AS\n\
+                       remarks:            first line\n\
+                       \x20                   second line\n\
+                       +\n\
+                       mnt-by:             EXAMPLE-MNT\n\
+                       source:             DN42\n";
 
-        // 192.168 (16 bits)
-        let mut bits = vec![1, 1, 0, 0, 0, 0, 0, 0]; // 192
-        bits.extend_from_slice(&[1, 0, 1, 0, 1, 0, 0, 0]); // 168
-        assert_eq!(bits_to_octets(&bits), vec![192, 168]);
+        let record = RecordFile::from_content(PathBuf::from("data/aut-num/AS4242420000"), content.to_string());
 
-        // 1 -> 10000000 (128)
-        let bits = vec![1];
-        assert_eq!(bits_to_octets(&bits), vec![128]);
+        assert_eq!(record.object_type(), "aut-num");
+        assert_eq!(record.primary_key(), "AS4242420000");
+        assert_eq!(record.get_attribute("remarks"), Some("first line second line"));
+        assert_eq!(record.get_attribute("MNT-BY"), Some("EXAMPLE-MNT"));
+        assert_eq!(record.attributes().len(), 5);
+        assert_eq!(record.content(), content);
+        assert_eq!(record.get_field(RecordField::Source), Some(&vec!["DN42".to_string()]));
+    }
+
+    #[test]
+    fn test_record_inetnum_primary_key() {
+        let content = "inetnum:            172.20.0.0 - 172.20.0.255\ncidr:               172.20.0.0/24\n";
+
+        let record = RecordFile::from_content(PathBuf::from("data/inetnum/172.20.0.0_24"), content.to_string());
+
+        assert_eq!(record.object_type(), "inetnum");
+        assert_eq!(record.primary_key(), "172.20.0.0/24");
+    }
+
+    #[test]
+    fn test_prefix_last_address() {
+        let p: Prefix = "172.20.0.0/24".parse().unwrap();
+        assert_eq!(p.last_address(), "172.20.0.255".parse::<IpAddr>().unwrap());
+
+        let p: Prefix = "0.0.0.0/0".parse().unwrap();
+        assert_eq!(p.last_address(), "255.255.255.255".parse::<IpAddr>().unwrap());
+
+        let p: Prefix = "fd00::/64".parse().unwrap();
+        assert_eq!(p.last_address(), "fd00::ffff:ffff:ffff:ffff".parse::<IpAddr>().unwrap());
+
+        let p: Prefix = "fd00::1/128".parse().unwrap();
+        assert_eq!(p.last_address(), "fd00::1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_prefix_contains() {
+        let p16: Prefix = "172.20.0.0/16".parse().unwrap();
+        let p24: Prefix = "172.20.1.0/24".parse().unwrap();
+        let other: Prefix = "172.21.0.0/24".parse().unwrap();
+        let v6: Prefix = "::/0".parse().unwrap();
+
+        assert!(p16.contains(&p24));
+        assert!(p16.contains(&p16));
+        assert!(!p24.contains(&p16));
+        assert!(!p16.contains(&other));
+        assert!(!v6.contains(&p24));
+    }
+
+    #[test]
+    fn test_prefix_new_masks_host_bits() {
+        let p = Prefix::new("172.20.1.1".parse().unwrap(), 16).unwrap();
+        assert_eq!(p.to_string(), "172.20.0.0/16");
+
+        let p = Prefix::new("fd42:1::1".parse().unwrap(), 31).unwrap();
+        assert_eq!(p.to_string(), "fd42::/31");
+
+        assert!(Prefix::new("10.0.0.0".parse().unwrap(), 33).is_err());
+        assert!(Prefix::new("::".parse().unwrap(), 129).is_err());
+    }
+
+    #[test]
+    fn test_prefix_first_address_and_host_count() {
+        let p: Prefix = "172.20.1.1/23".parse().unwrap();
+        assert_eq!(p.first_address(), "172.20.0.0".parse::<IpAddr>().unwrap());
+        assert_eq!(p.last_address(), "172.20.1.255".parse::<IpAddr>().unwrap());
+        assert_eq!(p.host_count(), 512);
+
+        let p: Prefix = "0.0.0.0/0".parse().unwrap();
+        assert_eq!(p.host_count(), 1 << 32);
+
+        let p: Prefix = "fd00::/64".parse().unwrap();
+        assert_eq!(p.host_count(), 1 << 64);
+
+        let p: Prefix = "::/0".parse().unwrap();
+        assert_eq!(p.host_count(), u128::MAX);
+    }
+
+    #[test]
+    fn test_prefix_overlaps() {
+        let p16: Prefix = "172.20.0.0/16".parse().unwrap();
+        let p24: Prefix = "172.20.1.0/24".parse().unwrap();
+        let other: Prefix = "172.21.0.0/24".parse().unwrap();
+
+        assert!(p16.overlaps(&p24));
+        assert!(p24.overlaps(&p16));
+        assert!(!p24.overlaps(&other));
+        assert!(p16.contains_address(&"172.20.255.1".parse().unwrap()));
+        assert!(!p16.contains_address(&"fd00::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_prefix_subnets_and_supernets() {
+        let p: Prefix = "172.20.0.0/22".parse().unwrap();
+
+        let subnets = p.subnets(24).map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(subnets, vec!["172.20.0.0/24", "172.20.1.0/24", "172.20.2.0/24", "172.20.3.0/24"]);
+
+        assert_eq!(p.subnets(22).count(), 1);
+        assert_eq!(p.subnets(21).count(), 0);
+        assert_eq!(p.subnets(33).count(), 0);
+
+        let host: Prefix = "255.255.255.254/31".parse().unwrap();
+        assert_eq!(host.subnets(32).count(), 2);
+
+        let v6: Prefix = "ffff:ffff:ffff:ffff:ffff:ffff:ffff:fffc/126".parse().unwrap();
+        assert_eq!(v6.subnets(128).count(), 4);
+
+        assert_eq!(p.supernet().unwrap().to_string(), "172.20.0.0/21");
+        assert!("0.0.0.0/0".parse::<Prefix>().unwrap().supernet().is_none());
+
+        let supernets = p.supernets().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(supernets.len(), 22);
+        assert_eq!(supernets[0], "172.20.0.0/21");
+        assert_eq!(supernets[21], "0.0.0.0/0");
+    }
+
+    #[test]
+    fn test_prefix_ordering() {
+        let mut prefixes = ["fd00::/8", "172.20.1.0/24", "172.20.0.0/24", "172.20.0.0/16", "10.0.0.0/8"]
+            .iter()
+            .map(|s| s.parse::<Prefix>().unwrap())
+            .collect::<Vec<_>>();
+
+        prefixes.sort();
+
+        let sorted = prefixes.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        assert_eq!(sorted, vec!["10.0.0.0/8", "172.20.0.0/16", "172.20.0.0/24", "172.20.1.0/24", "fd00::/8"]);
+    }
+
+    #[test]
+    fn test_prefix_serde() {
+        let p: Prefix = "172.20.1.1/24".parse().unwrap();
+
+        let json = serde_json::to_string(&p).unwrap();
+        assert_eq!(json, "\"172.20.1.1/24\"");
+        assert_eq!(serde_json::from_str::<Prefix>(&json).unwrap(), p);
+
+        assert!(serde_json::from_str::<Prefix>("\"172.20.1.1\"").is_err());
     }
 
     #[test]
@@ -319,16 +568,13 @@ mod tests {
     }
 
     #[test]
-    fn test_get_bits() {
+    fn test_address_to_int() {
         let p: Prefix = "192.0.0.0/8".parse().unwrap();
-        // 192 = 11000000
-        let bits = p.get_bits();
-        assert_eq!(bits, vec![1, 1, 0, 0, 0, 0, 0, 0]);
-        assert_eq!(bits.len(), 8);
+        assert_eq!(address_to_int(p.network()), 0xc000_0000);
+        assert_eq!(int_to_address(p.network(), 0xc000_0000), *p.network());
 
         let p2: Prefix = "255.255.0.0/10".parse().unwrap();
-        let bits2 = p2.get_bits();
-        assert_eq!(bits2, vec![1; 10]);
+        assert_eq!(address_to_int(p2.network()) >> (32 - 10), 0b11_1111_1111);
     }
 
     #[test]
@@ -350,22 +596,20 @@ mod tests {
     }
 
     #[test]
-    fn test_from_bits_v4() {
-        let bits = vec![0, 0, 0, 0, 1, 0, 1, 0];
-        let p = Prefix::from_bits_v4(&bits).unwrap();
+    fn test_new_v4() {
+        let p = Prefix::new("10.1.2.3".parse().unwrap(), 8).unwrap();
 
         assert_eq!(p.prefix_len, 8);
         assert_eq!(p.network, "10.0.0.0".parse::<IpAddr>().unwrap());
     }
 
     #[test]
-    fn test_from_bits_v6() {
-        let p = Prefix::from_bits_v6(&[]).unwrap();
+    fn test_new_v6() {
+        let p = Prefix::new("ffff::1".parse().unwrap(), 0).unwrap();
         assert_eq!(p.prefix_len, 0);
         assert_eq!(p.network, "::".parse::<IpAddr>().unwrap());
 
-        let bits = vec![1; 16];
-        let p = Prefix::from_bits_v6(&bits).unwrap();
+        let p = Prefix::new("ffff:ffff::".parse().unwrap(), 16).unwrap();
         assert_eq!(p.prefix_len, 16);
         assert_eq!(p.network, "ffff::".parse::<IpAddr>().unwrap());
     }
@@ -377,112 +621,67 @@ mod tests {
     }
 
     #[test]
-    fn test_bits_to_octets_empty() {
-        let bits = vec![];
-
-        let octets = bits_to_octets(&bits);
-
-        assert_eq!(octets.len(), 0);
-    }
-
-    #[test]
-    fn test_bits_to_octets_various_lengths() {
-        // 2 bits: 11 -> 11000000 (192)
-        assert_eq!(bits_to_octets(&[1, 1]), vec![192]);
-
-        // 3 bits: 101 -> 10100000 (160)
-        assert_eq!(bits_to_octets(&[1, 0, 1]), vec![160]);
-
-        // 4 bits: 1111 -> 11110000 (240)
-        assert_eq!(bits_to_octets(&[1, 1, 1, 1]), vec![240]);
-
-        // 5 bits: 10101 -> 10101000 (168)
-        assert_eq!(bits_to_octets(&[1, 0, 1, 0, 1]), vec![168]);
-
-        // 9 bits: 11111111 1 -> 11111111 10000000 (255, 128)
-        let bits = vec![1, 1, 1, 1, 1, 1, 1, 1, 1];
-        assert_eq!(bits_to_octets(&bits), vec![255, 128]);
-
-        // 17 bits
-        let bits = vec![1, 1, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0, 1];
-        // 11000000 10101000 1 -> 192, 168, 128
-        assert_eq!(bits_to_octets(&bits), vec![192, 168, 128]);
-    }
-
-    #[test]
-    fn test_from_bits_v4_empty() {
-        let p = Prefix::from_bits_v4(&[]).unwrap();
+    fn test_new_v4_zero_length() {
+        let p = Prefix::new("255.255.255.255".parse().unwrap(), 0).unwrap();
         assert_eq!(p.prefix_len, 0);
         assert_eq!(p.network, "0.0.0.0".parse::<IpAddr>().unwrap());
     }
 
     #[test]
-    fn test_from_bits_v4_exact_32_bits() {
-        let bits = vec![1; 32];
-        let p = Prefix::from_bits_v4(&bits).unwrap();
+    fn test_new_v4_max_length() {
+        let p = Prefix::new("255.255.255.255".parse().unwrap(), 32).unwrap();
         assert_eq!(p.prefix_len, 32);
         assert_eq!(p.network, "255.255.255.255".parse::<IpAddr>().unwrap());
     }
 
     #[test]
-    fn test_from_bits_v4_exceeds_limit() {
-        let bits = vec![1; 33];
-        assert!(Prefix::from_bits_v4(&bits).is_none());
+    fn test_new_v4_exceeds_limit() {
+        assert!(Prefix::new("255.255.255.255".parse().unwrap(), 33).is_err());
     }
 
     #[test]
-    fn test_from_bits_v4_non_octet_boundary() {
-        // 12 bits: 11000000 1010 -> 192.160.0.0/12
-        let bits = vec![1, 1, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0];
-        let p = Prefix::from_bits_v4(&bits).unwrap();
+    fn test_new_v4_non_octet_boundary() {
+        // 11000000 1010|1111 -> 192.160.0.0/12
+        let p = Prefix::new("192.175.255.255".parse().unwrap(), 12).unwrap();
         assert_eq!(p.prefix_len, 12);
         assert_eq!(p.network, "192.160.0.0".parse::<IpAddr>().unwrap());
     }
 
     #[test]
-    fn test_from_bits_v4_single_bit() {
-        let bits = vec![1];
-        let p = Prefix::from_bits_v4(&bits).unwrap();
+    fn test_new_v4_single_bit() {
+        let p = Prefix::new("255.255.255.255".parse().unwrap(), 1).unwrap();
         assert_eq!(p.prefix_len, 1);
         assert_eq!(p.network, "128.0.0.0".parse::<IpAddr>().unwrap());
 
-        let bits = vec![0];
-        let p = Prefix::from_bits_v4(&bits).unwrap();
+        let p = Prefix::new("127.255.255.255".parse().unwrap(), 1).unwrap();
         assert_eq!(p.prefix_len, 1);
         assert_eq!(p.network, "0.0.0.0".parse::<IpAddr>().unwrap());
     }
 
     #[test]
-    fn test_from_bits_v6_exact_128_bits() {
-        let bits = vec![1; 128];
-        let p = Prefix::from_bits_v6(&bits).unwrap();
+    fn test_new_v6_max_length() {
+        let address = "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff".parse::<IpAddr>().unwrap();
+        let p = Prefix::new(address, 128).unwrap();
         assert_eq!(p.prefix_len, 128);
-        assert_eq!(p.network, "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff".parse::<IpAddr>().unwrap());
+        assert_eq!(p.network, address);
     }
 
     #[test]
-    fn test_from_bits_v6_exceeds_limit() {
-        let bits = vec![1; 129];
-        assert!(Prefix::from_bits_v6(&bits).is_none());
+    fn test_new_v6_exceeds_limit() {
+        assert!(Prefix::new("::".parse().unwrap(), 129).is_err());
     }
 
     #[test]
-    fn test_from_bits_v6_non_octet_boundary() {
-        // 12 bits: 0010 0000 0001 -> 2010::/12
-        let bits = vec![0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1];
-        let p = Prefix::from_bits_v6(&bits).unwrap();
+    fn test_new_v6_non_octet_boundary() {
+        // 0010 0000 0001|1111 -> 2010::/12
+        let p = Prefix::new("201f:ffff::".parse().unwrap(), 12).unwrap();
         assert_eq!(p.prefix_len, 12);
-        // 0010 0000 0001 0000 (padding) -> 0x2010 -> 2010::
         assert_eq!(p.network, "2010::".parse::<IpAddr>().unwrap());
     }
 
     #[test]
-    fn test_from_bits_v6_64_bits() {
-        let bits = vec![0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, // 2001
-                        0, 0, 0, 0, 1, 1, 0, 1, 1, 0, 1, 1, 1, 0, 0, 0, // 0db8
-                        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // 0000
-                        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]; // 0000
-        let p = Prefix::from_bits_v6(&bits).unwrap();
+    fn test_new_v6_64_bits() {
+        let p = Prefix::new("2001:db8::ffff:1".parse().unwrap(), 64).unwrap();
         assert_eq!(p.prefix_len, 64);
         assert_eq!(p.network, "2001:db8::".parse::<IpAddr>().unwrap());
     }
@@ -557,79 +756,57 @@ mod tests {
     }
 
     #[test]
-    fn test_get_bits_zero_length() {
-        let p: Prefix = "192.168.1.1/0".parse().unwrap();
-        let bits = p.get_bits();
-        assert_eq!(bits.len(), 0);
+    fn test_host_mask_zero_length() {
+        assert_eq!(host_mask(32, 0), 0xffff_ffff);
+        assert_eq!(host_mask(128, 0), u128::MAX);
     }
 
     #[test]
-    fn test_get_bits_max_length_v4() {
-        let p: Prefix = "255.255.255.255/32".parse().unwrap();
-        let bits = p.get_bits();
-        assert_eq!(bits.len(), 32);
-        assert_eq!(bits, vec![1; 32]);
+    fn test_host_mask_max_length_v4() {
+        assert_eq!(host_mask(32, 32), 0);
+        assert_eq!(host_mask(32, 24), 0xff);
     }
 
     #[test]
-    fn test_get_bits_max_length_v6() {
-        let p: Prefix = "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff/128".parse().unwrap();
-        let bits = p.get_bits();
-        assert_eq!(bits.len(), 128);
-        assert_eq!(bits, vec![1; 128]);
+    fn test_host_mask_max_length_v6() {
+        assert_eq!(host_mask(128, 128), 0);
+        assert_eq!(host_mask(128, 64), u64::MAX as u128);
     }
 
     #[test]
-    fn test_get_bits_v6_partial() {
+    fn test_address_to_int_v6_partial() {
         let p: Prefix = "2001:db8::/32".parse().unwrap();
-        let bits = p.get_bits();
-        assert_eq!(bits.len(), 32);
-        // 2001 = 0010 0000 0000 0001
-        // 0db8 = 0000 1101 1011 1000
-        let expected = vec![
-            0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-            0, 0, 0, 0, 1, 1, 0, 1, 1, 0, 1, 1, 1, 0, 0, 0,
-        ];
-        assert_eq!(bits, expected);
+        assert_eq!(address_to_int(p.network()) >> (128 - 32), 0x2001_0db8);
+        assert_eq!(address_to_int(p.network()) & host_mask(128, 32), 0);
     }
 
     #[test]
-    fn test_get_bits_pattern_recognition() {
+    fn test_address_to_int_pattern() {
         let p: Prefix = "170.170.0.0/16".parse().unwrap();
-        let bits = p.get_bits();
         // 170 = 10101010
-        assert_eq!(bits, vec![1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0]);
+        assert_eq!(address_to_int(p.network()) >> 16, 0b1010_1010_1010_1010);
     }
 
     #[test]
-    fn test_roundtrip_from_bits_get_bits_v4() {
-        let original_bits = vec![1, 1, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0];
-        let p = Prefix::from_bits_v4(&original_bits).unwrap();
-        let retrieved_bits = p.get_bits();
+    fn test_roundtrip_new_v4() {
+        let p: Prefix = "192.168.0.0/16".parse().unwrap();
 
-        assert_eq!(original_bits, retrieved_bits);
+        assert_eq!(Prefix::new(*p.network(), p.prefix_len()).unwrap(), p);
     }
 
     #[test]
-    fn test_roundtrip_from_bits_get_bits_v6() {
-        let original_bits = vec![0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
-        let p = Prefix::from_bits_v6(&original_bits).unwrap();
-        let retrieved_bits = p.get_bits();
+    fn test_roundtrip_new_v6() {
+        let p: Prefix = "2001::/16".parse().unwrap();
 
-        assert_eq!(original_bits, retrieved_bits);
+        assert_eq!(Prefix::new(*p.network(), p.prefix_len()).unwrap(), p);
     }
 
     #[test]
-    fn test_roundtrip_with_prefix_len_get_bits() {
+    fn test_roundtrip_with_prefix_len_new() {
         let p: Prefix = "192.168.1.128/25".parse().unwrap();
         let new_p = p.with_prefix_len(24);
-        let bits = new_p.get_bits();
-
-        // Should have exactly 24 bits
-        assert_eq!(bits.len(), 24);
 
-        // Reconstruct and verify
-        let reconstructed = Prefix::from_bits_v4(&bits).unwrap();
+        let reconstructed = Prefix::new(*p.network(), 24).unwrap();
         assert_eq!(reconstructed.network, new_p.network);
         assert_eq!(reconstructed.prefix_len, new_p.prefix_len);
     }
@@ -694,11 +871,10 @@ mod tests {
 
     #[test]
     fn test_complex_workflow() {
-        // Parse -> modify -> get bits -> reconstruct
+        // Parse -> modify -> reconstruct from the address
         let p1: Prefix = "192.168.128.0/17".parse().unwrap();
         let p2 = p1.with_prefix_len(20);
-        let bits = p2.get_bits();
-        let p3 = Prefix::from_bits_v4(&bits).unwrap();
+        let p3 = Prefix::new(*p2.network(), 20).unwrap();
 
         assert_eq!(p2, p3);
         assert_eq!(p3.prefix_len, 20);
@@ -713,32 +889,29 @@ mod tests {
         assert_eq!(expanded.network, "192.168.0.0".parse::<IpAddr>().unwrap());
         assert_eq!(expanded.prefix_len, 24);
 
-        let bits = expanded.get_bits();
-        // Last 8 bits should be 0
-        assert_eq!(&bits[16..24], &[0, 0, 0, 0, 0, 0, 0, 0]);
+        // Bits 16 to 24 should be 0
+        assert_eq!((address_to_int(expanded.network()) >> 8) & 0xff, 0);
     }
 
     #[test]
     fn test_all_zeros_all_ones() {
         // All zeros
-        let p_zeros = Prefix::from_bits_v4(&vec![0; 24]).unwrap();
+        let p_zeros = Prefix::new("0.0.0.0".parse().unwrap(), 24).unwrap();
         assert_eq!(p_zeros.network, "0.0.0.0".parse::<IpAddr>().unwrap());
 
         // All ones
-        let p_ones = Prefix::from_bits_v4(&vec![1; 24]).unwrap();
+        let p_ones = Prefix::new("255.255.255.255".parse().unwrap(), 24).unwrap();
         assert_eq!(p_ones.network, "255.255.255.0".parse::<IpAddr>().unwrap());
     }
 
     #[test]
     fn test_alternating_bits() {
         // 10101010 (170)
-        let bits = vec![1, 0, 1, 0, 1, 0, 1, 0];
-        let p = Prefix::from_bits_v4(&bits).unwrap();
+        let p = Prefix::new("170.255.255.255".parse().unwrap(), 8).unwrap();
         assert_eq!(p.network, "170.0.0.0".parse::<IpAddr>().unwrap());
 
         // 01010101 (85)
-        let bits2 = vec![0, 1, 0, 1, 0, 1, 0, 1];
-        let p2 = Prefix::from_bits_v4(&bits2).unwrap();
+        let p2 = Prefix::new("85.255.255.255".parse().unwrap(), 8).unwrap();
         assert_eq!(p2.network, "85.0.0.0".parse::<IpAddr>().unwrap());
     }
 }
\ No newline at end of file
@@ -0,0 +1,296 @@
+use crate::model::record::{Prefix, RecordFile};
+use crate::model::trie::PrefixTrie;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+// Object types whose primary key is an address prefix
+const PREFIX_OBJECT_TYPES: [&str; 4] = ["inetnum", "inet6num", "route", "route6"];
+
+// Attributes that are indexed for inverse lookups (whois -i)
+pub const INVERSE_ATTRIBUTES: [&str; 9] = [
+    "mnt-by", "admin-c", "tech-c", "zone-c", "origin", "nserver", "member-of", "members", "org",
+];
+
+// All parsed registry objects with lookup indexes on top
+#[derive(Default)]
+pub struct Registry {
+    objects: Vec<RecordFile>,
+    // lowercase primary key -> object indexes, the same key may be used by several object types
+    keys: HashMap<String, Vec<usize>>,
+    // (attribute, lowercase value) -> object indexes
+    inverse: HashMap<(String, String), Vec<usize>>,
+    // object type -> prefix -> object indexes
+    prefixes: HashMap<String, PrefixTrie<Vec<usize>>>,
+    // None until the registry has been indexed once
+    last_updated: Option<SystemTime>,
+}
+
+pub fn parse_object_prefix(record: &RecordFile) -> Option<Prefix> {
+    let prefix = Prefix::from_str(record.primary_key()).ok()?;
+
+    Prefix::new(*prefix.network(), prefix.prefix_len()).ok()
+}
+
+// Accepts a bare address as a host prefix
+pub fn parse_query_prefix(query: &str) -> Option<Prefix> {
+    if let Ok(prefix) = Prefix::from_str(query) {
+        return Prefix::new(*prefix.network(), prefix.prefix_len()).ok();
+    }
+
+    let address = IpAddr::from_str(query).ok()?;
+    let prefix_len = if address.is_ipv4() { 32 } else { 128 };
+
+    Prefix::new(address, prefix_len).ok()
+}
+
+impl Registry {
+    pub fn new(objects: Vec<RecordFile>) -> Self {
+        let mut keys: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut inverse: HashMap<(String, String), Vec<usize>> = HashMap::new();
+        let mut prefixes: HashMap<String, PrefixTrie<Vec<usize>>> = HashMap::new();
+
+        for (index, object) in objects.iter().enumerate() {
+            keys.entry(object.primary_key().to_lowercase()).or_default().push(index);
+
+            for (attribute, value) in object.attributes() {
+                if INVERSE_ATTRIBUTES.contains(&attribute.as_str()) {
+                    // nserver values carry optional glue after the name
+                    let value = value.split_whitespace().next().unwrap_or("");
+                    inverse.entry((attribute.clone(), value.to_lowercase())).or_default().push(index);
+                }
+            }
+
+            if PREFIX_OBJECT_TYPES.contains(&object.object_type())
+                && let Some(prefix) = parse_object_prefix(object)
+            {
+                let trie = prefixes.entry(object.object_type().to_string()).or_default();
+
+                match trie.get_mut(&prefix) {
+                    Some(indexes) => indexes.push(index),
+                    None => {
+                        trie.insert(&prefix, vec![index]);
+                    }
+                }
+            }
+        }
+
+        Registry {
+            objects,
+            keys,
+            inverse,
+            prefixes,
+            last_updated: Some(SystemTime::now()),
+        }
+    }
+
+    pub fn last_updated(&self) -> Option<SystemTime> {
+        self.last_updated
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn objects(&self) -> &[RecordFile] {
+        &self.objects
+    }
+
+    pub fn objects_of_type<'a>(&'a self, object_type: &'a str) -> impl Iterator<Item = &'a RecordFile> + 'a {
+        self.objects.iter().filter(move |o| o.object_type() == object_type)
+    }
+
+    // Objects of any type with the given primary key, case-insensitive
+    pub fn find(&self, key: &str) -> Vec<&RecordFile> {
+        self.keys
+            .get(&key.to_lowercase())
+            .map(|indexes| indexes.iter().map(|&i| &self.objects[i]).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, object_type: &str, key: &str) -> Option<&RecordFile> {
+        self.find(key).into_iter().find(|o| o.object_type() == object_type)
+    }
+
+    pub fn inverse(&self, attribute: &str, value: &str) -> Vec<&RecordFile> {
+        self.inverse
+            .get(&(attribute.to_lowercase(), value.to_lowercase()))
+            .map(|indexes| indexes.iter().map(|&i| &self.objects[i]).collect())
+            .unwrap_or_default()
+    }
+
+    fn prefix_objects<'a, F, I>(&'a self, object_types: &[&str], lookup: F) -> Vec<(Prefix, &'a RecordFile)>
+    where
+        F: Fn(&'a PrefixTrie<Vec<usize>>) -> I,
+        I: Iterator<Item = (Prefix, &'a Vec<usize>)>,
+    {
+        object_types
+            .iter()
+            .filter_map(|object_type| self.prefixes.get(*object_type))
+            .flat_map(lookup)
+            .flat_map(|(prefix, indexes)| indexes.iter().map(move |&i| (prefix.clone(), &self.objects[i])))
+            .collect()
+    }
+
+    // Objects of the given types whose prefix covers the query, most specific first
+    pub fn covering(&self, object_types: &[&str], prefix: &Prefix) -> Vec<(Prefix, &RecordFile)> {
+        let mut covering = self.prefix_objects(object_types, |trie| trie.covering(prefix));
+
+        covering.sort_by_key(|(p, _)| std::cmp::Reverse(p.prefix_len()));
+
+        covering
+    }
+
+    // Objects of the given types whose prefix lies inside the query (including the query itself), least specific first
+    pub fn covered(&self, object_types: &[&str], prefix: &Prefix) -> Vec<(Prefix, &RecordFile)> {
+        let mut covered = self.prefix_objects(object_types, |trie| trie.covered(prefix));
+
+        covered.sort_by_key(|(p, _)| (p.prefix_len(), *p.network()));
+
+        covered
+    }
+
+    pub fn covering_inetnums(&self, prefix: &Prefix) -> Vec<&RecordFile> {
+        self.covering(&["inetnum", "inet6num"], prefix).into_iter().map(|(_, record)| record).collect()
+    }
+
+    pub fn more_specific_routes(&self, prefix: &Prefix) -> Vec<&RecordFile> {
+        self.covered(&["route", "route6"], prefix).into_iter().map(|(_, record)| record).collect()
+    }
+
+    // The most specific inetnum delegating reverse DNS (carrying nserver attributes) for the query
+    pub fn reverse_delegation(&self, prefix: &Prefix) -> Option<(Prefix, &RecordFile)> {
+        self.covering(&["inetnum", "inet6num"], prefix)
+            .into_iter()
+            .find(|(_, record)| record.get_attribute("nserver").is_some())
+    }
+
+    pub fn most_specific_inetnum(&self, prefix: &Prefix) -> Option<&RecordFile> {
+        self.covering(&["inetnum", "inet6num"], prefix).into_iter().next().map(|(_, record)| record)
+    }
+
+    // Route objects with the longest prefix covering the query, several origins may announce the same prefix
+    pub fn most_specific_routes(&self, prefix: &Prefix) -> Vec<&RecordFile> {
+        let covering = self.covering(&["route", "route6"], prefix);
+
+        let most_specific_len = match covering.first() {
+            Some((p, _)) => p.prefix_len(),
+            None => return Vec::new(),
+        };
+
+        covering
+            .into_iter()
+            .take_while(|(p, _)| p.prefix_len() == most_specific_len)
+            .map(|(_, record)| record)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    pub(crate) fn record(path: &str, content: &str) -> RecordFile {
+        RecordFile::from_content(PathBuf::from(path), content.to_string())
+    }
+
+    pub(crate) fn test_registry() -> Registry {
+        Registry::new(vec![
+            record("data/aut-num/AS4242420000", "aut-num: AS4242420000\nas-name: EXAMPLE-AS\nadmin-c: EXAMPLE-DN42\nmnt-by: EXAMPLE-MNT\nsource: DN42\n"),
+            record("data/mntner/EXAMPLE-MNT", "mntner: EXAMPLE-MNT\nadmin-c: EXAMPLE-DN42\nmnt-by: EXAMPLE-MNT\nsource: DN42\n"),
+            record("data/person/EXAMPLE-DN42", "person: Example Person\nnic-hdl: EXAMPLE-DN42\nmnt-by: EXAMPLE-MNT\nsource: DN42\n"),
+            record("data/inetnum/172.20.0.0_14", "inetnum: 172.20.0.0 - 172.23.255.255\ncidr: 172.20.0.0/14\nnetname: DN42-V4\nnserver: ns1.example.dn42\nsource: DN42\n"),
+            record("data/inetnum/172.20.0.0_24", "inetnum: 172.20.0.0 - 172.20.0.255\ncidr: 172.20.0.0/24\nnetname: EXAMPLE-NET\nmnt-by: EXAMPLE-MNT\nsource: DN42\n"),
+            record("data/route/172.20.0.0_24", "route: 172.20.0.0/24\norigin: AS4242420000\nmnt-by: EXAMPLE-MNT\nsource: DN42\n"),
+            record("data/route/172.20.0.0_24-2", "route: 172.20.0.0/24\norigin: AS4242420001\nmnt-by: OTHER-MNT\nsource: DN42\n"),
+            record("data/route/172.20.0.0_16", "route: 172.20.0.0/16\norigin: AS4242420002\nsource: DN42\n"),
+            record("data/domain/example.dn42", "domain: example.dn42\nnserver: ns1.example.dn42 172.20.0.53\nmnt-by: EXAMPLE-MNT\nsource: DN42\n"),
+        ])
+    }
+
+    #[test]
+    fn test_find_is_case_insensitive() {
+        let registry = test_registry();
+
+        assert_eq!(registry.find("as4242420000").len(), 1);
+        assert_eq!(registry.get("mntner", "example-mnt").unwrap().primary_key(), "EXAMPLE-MNT");
+        assert!(registry.get("aut-num", "EXAMPLE-MNT").is_none());
+        assert!(registry.find("NOBODY-MNT").is_empty());
+    }
+
+    #[test]
+    fn test_person_is_keyed_by_nic_hdl() {
+        let registry = test_registry();
+
+        assert_eq!(registry.get("person", "example-dn42").unwrap().get_attribute("person"), Some("Example Person"));
+        assert!(registry.find("Example Person").is_empty());
+    }
+
+    #[test]
+    fn test_inverse() {
+        let registry = test_registry();
+
+        assert_eq!(registry.inverse("mnt-by", "example-mnt").len(), 6);
+        assert_eq!(registry.inverse("origin", "AS4242420001").len(), 1);
+        assert_eq!(registry.inverse("nserver", "ns1.example.dn42").len(), 2);
+        assert!(registry.inverse("descr", "anything").is_empty());
+    }
+
+    #[test]
+    fn test_most_specific_lookup() {
+        let registry = test_registry();
+
+        let address = parse_query_prefix("172.20.0.1").unwrap();
+        assert_eq!(registry.most_specific_inetnum(&address).unwrap().primary_key(), "172.20.0.0/24");
+        assert_eq!(registry.most_specific_routes(&address).len(), 2);
+
+        let address = parse_query_prefix("172.21.0.1").unwrap();
+        assert_eq!(registry.most_specific_inetnum(&address).unwrap().primary_key(), "172.20.0.0/14");
+        assert!(registry.most_specific_routes(&address).is_empty());
+
+        let address = parse_query_prefix("fd00::1").unwrap();
+        assert!(registry.most_specific_inetnum(&address).is_none());
+    }
+
+    #[test]
+    fn test_covering_and_covered() {
+        let registry = test_registry();
+
+        let address = parse_query_prefix("172.20.0.1").unwrap();
+        let keys = registry.covering_inetnums(&address).iter().map(|r| r.primary_key()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["172.20.0.0/24", "172.20.0.0/14"]);
+        assert!(registry.more_specific_routes(&address).is_empty());
+
+        let prefix = parse_query_prefix("172.20.0.0/14").unwrap();
+        let keys = registry.more_specific_routes(&prefix).iter().map(|r| r.primary_key()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["172.20.0.0/16", "172.20.0.0/24", "172.20.0.0/24"]);
+
+        let (delegated, _) = registry.reverse_delegation(&address).unwrap();
+        assert_eq!(delegated.to_string(), "172.20.0.0/14");
+    }
+
+    #[test]
+    fn test_covered_orders_addresses_numerically() {
+        let registry = Registry::new(vec![
+            record("data/route/172.20.10.0_24", "route: 172.20.10.0/24\norigin: AS4242420000\nsource: DN42\n"),
+            record("data/route/172.20.9.0_24", "route: 172.20.9.0/24\norigin: AS4242420000\nsource: DN42\n"),
+            record("data/route/172.20.100.0_23", "route: 172.20.100.0/23\norigin: AS4242420000\nsource: DN42\n"),
+            record("data/route6/fd00:10::_32", "route6: fd00:10::/32\norigin: AS4242420000\nsource: DN42\n"),
+            record("data/route6/fd00:9::_32", "route6: fd00:9::/32\norigin: AS4242420000\nsource: DN42\n"),
+        ]);
+
+        let prefix = parse_query_prefix("172.20.0.0/16").unwrap();
+        let keys = registry.more_specific_routes(&prefix).iter().map(|r| r.primary_key()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["172.20.100.0/23", "172.20.9.0/24", "172.20.10.0/24"]);
+
+        let prefix = parse_query_prefix("fd00::/8").unwrap();
+        let keys = registry.more_specific_routes(&prefix).iter().map(|r| r.primary_key()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["fd00:9::/32", "fd00:10::/32"]);
+    }
+}
@@ -0,0 +1,891 @@
+use crate::model::record::Prefix;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+// Path compressed binary trie keyed by prefix, one root per address family.
+// Nodes without a value only exist where two branches split, so a valued node
+// without children is never covering another entry.
+pub struct PrefixTrie<T> {
+    ipv4: Option<Box<Node<T>>>,
+    ipv6: Option<Box<Node<T>>>,
+    len: usize,
+}
+
+struct Node<T> {
+    // Left aligned key, bits after len are always zero
+    bits: u128,
+    len: u8,
+    value: Option<T>,
+    children: [Option<Box<Node<T>>>; 2],
+}
+
+fn mask(len: u8) -> u128 {
+    u128::MAX.checked_shl(128 - len as u32).unwrap_or(0)
+}
+
+fn bit_at(bits: u128, index: u8) -> usize {
+    ((bits >> (127 - index)) & 1) as usize
+}
+
+fn common_len(a: u128, a_len: u8, b: u128, b_len: u8) -> u8 {
+    let max = a_len.min(b_len);
+    let common = (a ^ b).leading_zeros().min(128) as u8;
+
+    common.min(max)
+}
+
+// (is ipv4, left aligned bits, prefix length)
+fn to_key(prefix: &Prefix) -> (bool, u128, u8) {
+    let (is_ipv4, bits, len) = match prefix.network() {
+        IpAddr::V4(ipv4) => (true, (u32::from(*ipv4) as u128) << 96, prefix.prefix_len()),
+        IpAddr::V6(ipv6) => (false, u128::from(*ipv6), prefix.prefix_len()),
+    };
+
+    (is_ipv4, bits & mask(len), len)
+}
+
+fn to_prefix(is_ipv4: bool, bits: u128, len: u8) -> Prefix {
+    let network = if is_ipv4 {
+        IpAddr::V4(Ipv4Addr::from((bits >> 96) as u32))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(bits))
+    };
+
+    Prefix::new(network, len).expect("trie keys are valid prefixes")
+}
+
+impl<T> Node<T> {
+    fn new(bits: u128, len: u8, value: Option<T>) -> Box<Self> {
+        Box::new(Node {
+            bits,
+            len,
+            value,
+            children: [None, None],
+        })
+    }
+
+    fn has_children(&self) -> bool {
+        self.children.iter().any(|c| c.is_some())
+    }
+}
+
+fn insert_node<T>(slot: &mut Option<Box<Node<T>>>, bits: u128, len: u8, value: T) -> Option<T> {
+    let node = match slot {
+        Some(node) => node,
+        None => {
+            *slot = Some(Node::new(bits, len, Some(value)));
+            return None;
+        }
+    };
+
+    let common = common_len(node.bits, node.len, bits, len);
+
+    if common == node.len && common == len {
+        return node.value.replace(value);
+    }
+
+    if common == node.len {
+        let child = bit_at(bits, node.len);
+        return insert_node(&mut node.children[child], bits, len, value);
+    }
+
+    // The key diverges inside this node's label, split it at the common part
+    let old = slot.take().unwrap();
+    let mut split = Node::new(bits & mask(common), common, None);
+
+    let old_side = bit_at(old.bits, common);
+    split.children[old_side] = Some(old);
+
+    if common == len {
+        split.value = Some(value);
+    } else {
+        split.children[1 - old_side] = Some(Node::new(bits, len, Some(value)));
+    }
+
+    *slot = Some(split);
+
+    None
+}
+
+fn remove_node<T>(slot: &mut Option<Box<Node<T>>>, bits: u128, len: u8) -> Option<T> {
+    let node = slot.as_mut()?;
+
+    if common_len(node.bits, node.len, bits, len) < node.len {
+        return None;
+    }
+
+    let removed = if node.len == len {
+        node.value.take()
+    } else {
+        let child = bit_at(bits, node.len);
+        remove_node(&mut node.children[child], bits, len)
+    };
+
+    // Keep the trie compressed: drop empty nodes and merge pass-through nodes into their child
+    if node.value.is_none() {
+        match (node.children[0].is_some(), node.children[1].is_some()) {
+            (false, false) => *slot = None,
+            (true, false) => *slot = node.children[0].take(),
+            (false, true) => *slot = node.children[1].take(),
+            (true, true) => {}
+        }
+    }
+
+    removed
+}
+
+impl<T> Default for PrefixTrie<T> {
+    fn default() -> Self {
+        PrefixTrie::new()
+    }
+}
+
+impl<T> PrefixTrie<T> {
+    pub fn new() -> Self {
+        PrefixTrie {
+            ipv4: None,
+            ipv6: None,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn root(&self, is_ipv4: bool) -> &Option<Box<Node<T>>> {
+        if is_ipv4 { &self.ipv4 } else { &self.ipv6 }
+    }
+
+    // Returns the previous value stored for the prefix
+    pub fn insert(&mut self, prefix: &Prefix, value: T) -> Option<T> {
+        let (is_ipv4, bits, len) = to_key(prefix);
+        let root = if is_ipv4 { &mut self.ipv4 } else { &mut self.ipv6 };
+
+        let previous = insert_node(root, bits, len, value);
+
+        if previous.is_none() {
+            self.len += 1;
+        }
+
+        previous
+    }
+
+    pub fn remove(&mut self, prefix: &Prefix) -> Option<T> {
+        let (is_ipv4, bits, len) = to_key(prefix);
+        let root = if is_ipv4 { &mut self.ipv4 } else { &mut self.ipv6 };
+
+        let removed = remove_node(root, bits, len);
+
+        if removed.is_some() {
+            self.len -= 1;
+        }
+
+        removed
+    }
+
+    // Visits the nodes on the path towards the prefix, least specific first
+    fn walk<'a>(&'a self, prefix: &Prefix, mut f: impl FnMut(&'a Node<T>)) {
+        let (is_ipv4, bits, len) = to_key(prefix);
+        let mut current = self.root(is_ipv4);
+
+        while let Some(node) = current {
+            if node.len > len || common_len(node.bits, node.len, bits, len) < node.len {
+                return;
+            }
+
+            f(node);
+
+            if node.len == len {
+                return;
+            }
+
+            current = &node.children[bit_at(bits, node.len)];
+        }
+    }
+
+    pub fn get(&self, prefix: &Prefix) -> Option<&T> {
+        let (_, _, len) = to_key(prefix);
+        let mut found = None;
+
+        self.walk(prefix, |node| {
+            if node.len == len {
+                found = node.value.as_ref();
+            }
+        });
+
+        found
+    }
+
+    pub fn get_mut(&mut self, prefix: &Prefix) -> Option<&mut T> {
+        let (is_ipv4, bits, len) = to_key(prefix);
+        let mut current = if is_ipv4 { &mut self.ipv4 } else { &mut self.ipv6 };
+
+        while let Some(node) = current {
+            if node.len > len || common_len(node.bits, node.len, bits, len) < node.len {
+                return None;
+            }
+
+            if node.len == len {
+                return node.value.as_mut();
+            }
+
+            current = &mut node.children[bit_at(bits, node.len)];
+        }
+
+        None
+    }
+
+    pub fn contains_key(&self, prefix: &Prefix) -> bool {
+        self.get(prefix).is_some()
+    }
+
+    // Entries equal to or covering the prefix, least specific first
+    pub fn covering<'a>(&'a self, prefix: &Prefix) -> impl Iterator<Item = (Prefix, &'a T)> + use<'a, T> {
+        let (is_ipv4, bits, len) = to_key(prefix);
+        let mut current = self.root(is_ipv4).as_deref();
+
+        std::iter::from_fn(move || {
+            while let Some(node) = current {
+                if node.len > len || common_len(node.bits, node.len, bits, len) < node.len {
+                    current = None;
+                    break;
+                }
+
+                current = if node.len == len { None } else { node.children[bit_at(bits, node.len)].as_deref() };
+
+                if let Some(value) = &node.value {
+                    return Some((to_prefix(is_ipv4, node.bits, node.len), value));
+                }
+            }
+
+            None
+        })
+    }
+
+    pub fn longest_match(&self, prefix: &Prefix) -> Option<(Prefix, &T)> {
+        self.covering(prefix).last()
+    }
+
+    // Entries equal to or inside the prefix, in address order with covering entries before their subnets
+    pub fn covered<'a>(&'a self, prefix: &Prefix) -> impl Iterator<Item = (Prefix, &'a T)> + use<'a, T> {
+        let (is_ipv4, bits, len) = to_key(prefix);
+        let mut current = self.root(is_ipv4).as_deref();
+        let mut start = None;
+
+        while let Some(node) = current {
+            let common = common_len(node.bits, node.len, bits, len);
+
+            if node.len >= len {
+                // Every key below this node starts with the node's bits
+                if common == len {
+                    start = Some((node, is_ipv4));
+                }
+                break;
+            }
+
+            if common < node.len {
+                break;
+            }
+
+            current = node.children[bit_at(bits, node.len)].as_deref();
+        }
+
+        Nodes(start.into_iter().collect()).filter_map(entry)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Prefix, &T)> {
+        self.nodes().filter_map(entry)
+    }
+
+    // Entries that do not cover any other entry
+    pub fn leaves(&self) -> impl Iterator<Item = (Prefix, &T)> {
+        self.nodes().filter(|(node, _)| !node.has_children()).filter_map(entry)
+    }
+
+    fn nodes(&self) -> Nodes<'_, T> {
+        // Popped from the end, so IPv4 comes first
+        Nodes([(&self.ipv6, false), (&self.ipv4, true)].into_iter().filter_map(|(root, is_ipv4)| Some((root.as_deref()?, is_ipv4))).collect())
+    }
+}
+
+fn entry<T>((node, is_ipv4): (&Node<T>, bool)) -> Option<(Prefix, &T)> {
+    node.value.as_ref().map(|value| (to_prefix(is_ipv4, node.bits, node.len), value))
+}
+
+// Depth first walk over the nodes, each node before its children and the 0 branch before the 1 branch
+struct Nodes<'a, T>(Vec<(&'a Node<T>, bool)>);
+
+impl<'a, T> Iterator for Nodes<'a, T> {
+    type Item = (&'a Node<T>, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, is_ipv4) = self.0.pop()?;
+
+        self.0.extend(node.children.iter().rev().flatten().map(|child| (child.as_ref(), is_ipv4)));
+
+        Some((node, is_ipv4))
+    }
+}
+
+impl<T> FromIterator<(Prefix, T)> for PrefixTrie<T> {
+    fn from_iter<I: IntoIterator<Item = (Prefix, T)>>(iter: I) -> Self {
+        let mut trie = PrefixTrie::new();
+
+        for (prefix, value) in iter {
+            trie.insert(&prefix, value);
+        }
+
+        trie
+    }
+}
+
+// Serialized as a map of "network/length" to value
+impl<T: Serialize> Serialize for PrefixTrie<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter().map(|(prefix, value)| (prefix.to_string(), value)))
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for PrefixTrie<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = HashMap::<String, T>::deserialize(deserializer)?;
+        let mut trie = PrefixTrie::new();
+
+        for (key, value) in entries {
+            let prefix = Prefix::from_str(&key).map_err(D::Error::custom)?;
+            trie.insert(&prefix, value);
+        }
+
+        Ok(trie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn p(s: &str) -> Prefix {
+        Prefix::from_str(s).unwrap()
+    }
+
+    fn collect_leaves(tree: &PrefixTrie<()>) -> Vec<Prefix> {
+        tree.leaves().map(|(prefix, _)| prefix).collect()
+    }
+
+    fn keys<'a, T: 'a>(entries: impl Iterator<Item = (Prefix, &'a T)>) -> Vec<String> {
+        entries.map(|(prefix, _)| prefix.to_string()).collect()
+    }
+
+    fn sample() -> PrefixTrie<u32> {
+        [
+            ("172.20.0.0/14", 1),
+            ("172.20.0.0/24", 2),
+            ("172.20.1.0/24", 3),
+            ("172.22.0.0/16", 4),
+            ("10.0.0.0/8", 5),
+            ("fd00::/8", 6),
+            ("fd42::/16", 7),
+        ]
+        .into_iter()
+        .map(|(prefix, value)| (p(prefix), value))
+        .collect()
+    }
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut trie = sample();
+
+        assert_eq!(trie.len(), 7);
+        assert_eq!(trie.get(&p("172.20.1.0/24")), Some(&3));
+        assert_eq!(trie.get(&p("172.20.0.0/16")), None);
+        assert_eq!(trie.get(&p("fd42::/16")), Some(&7));
+        assert_eq!(trie.insert(&p("172.20.1.0/24"), 30), Some(3));
+        assert_eq!(trie.len(), 7);
+
+        *trie.get_mut(&p("10.0.0.0/8")).unwrap() += 1;
+        assert_eq!(trie.get(&p("10.0.0.0/8")), Some(&6));
+    }
+
+    #[test]
+    fn test_host_bits_are_ignored() {
+        let mut trie = PrefixTrie::new();
+        trie.insert(&p("172.20.0.1/24"), ());
+
+        assert!(trie.contains_key(&p("172.20.0.0/24")));
+        assert_eq!(keys(trie.iter()), vec!["172.20.0.0/24"]);
+    }
+
+    #[test]
+    fn test_families_are_separate() {
+        let mut trie = PrefixTrie::new();
+        trie.insert(&p("0.0.0.0/0"), 4);
+        trie.insert(&p("::/0"), 6);
+
+        assert_eq!(trie.longest_match(&p("10.0.0.1/32")).unwrap().1, &4);
+        assert_eq!(trie.longest_match(&p("fd00::1/128")).unwrap().1, &6);
+    }
+
+    #[test]
+    fn test_longest_match_and_covering() {
+        let trie = sample();
+
+        assert_eq!(trie.longest_match(&p("172.20.0.5/32")).unwrap().1, &2);
+        assert_eq!(trie.longest_match(&p("172.21.0.5/32")).unwrap().1, &1);
+        assert!(trie.longest_match(&p("192.168.0.1/32")).is_none());
+
+        assert_eq!(keys(trie.covering(&p("172.20.1.1/32"))), vec!["172.20.0.0/14", "172.20.1.0/24"]);
+        assert_eq!(keys(trie.covering(&p("172.20.0.0/14"))), vec!["172.20.0.0/14"]);
+        assert_eq!(trie.covering(&p("172.0.0.0/8")).count(), 0);
+    }
+
+    #[test]
+    fn test_covered() {
+        let trie = sample();
+
+        assert_eq!(keys(trie.covered(&p("172.20.0.0/14"))), vec!["172.20.0.0/14", "172.20.0.0/24", "172.20.1.0/24", "172.22.0.0/16"]);
+        assert_eq!(keys(trie.covered(&p("172.20.0.0/23"))), vec!["172.20.0.0/24", "172.20.1.0/24"]);
+        assert_eq!(keys(trie.covered(&p("172.0.0.0/8"))), vec!["172.20.0.0/14", "172.20.0.0/24", "172.20.1.0/24", "172.22.0.0/16"]);
+        assert_eq!(keys(trie.covered(&p("fd00::/7"))), vec!["fd00::/8", "fd42::/16"]);
+        assert_eq!(trie.covered(&p("172.20.2.0/24")).count(), 0);
+    }
+
+    #[test]
+    fn test_remove_keeps_trie_compressed() {
+        let mut trie = sample();
+
+        assert_eq!(trie.remove(&p("172.20.0.0/14")), Some(1));
+        assert_eq!(trie.remove(&p("172.20.0.0/14")), None);
+        assert_eq!(trie.remove(&p("172.20.0.0/16")), None);
+        assert_eq!(trie.len(), 6);
+
+        assert_eq!(trie.longest_match(&p("172.21.0.5/32")), None);
+        assert_eq!(keys(trie.covered(&p("172.0.0.0/8"))), vec!["172.20.0.0/24", "172.20.1.0/24", "172.22.0.0/16"]);
+
+        for prefix in ["172.20.0.0/24", "172.20.1.0/24", "172.22.0.0/16", "10.0.0.0/8", "fd00::/8", "fd42::/16"] {
+            assert!(trie.remove(&p(prefix)).is_some(), "{}", prefix);
+        }
+
+        assert!(trie.is_empty());
+        assert!(trie.ipv4.is_none() && trie.ipv6.is_none());
+    }
+
+    #[test]
+    fn test_leaves() {
+        let trie = sample();
+
+        assert_eq!(keys(trie.leaves()), vec!["10.0.0.0/8", "172.20.0.0/24", "172.20.1.0/24", "172.22.0.0/16", "fd42::/16"]);
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let trie = sample();
+
+        let json = serde_json::to_string(&trie).unwrap();
+        let decoded: PrefixTrie<u32> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded.len(), trie.len());
+        assert_eq!(keys(decoded.iter()), keys(trie.iter()));
+        assert_eq!(decoded.get(&p("fd42::/16")), Some(&7));
+
+        assert!(serde_json::from_str::<PrefixTrie<u32>>(r#"{"not-a-prefix": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_insert_basic_ipv4() {
+        let mut tree = PrefixTrie::new();
+        let p = Prefix::from_str("192.168.1.0/24").unwrap();
+
+        tree.insert(&p, ());
+
+        let leaves = collect_leaves(&tree);
+
+        // Assert that we found exactly one leaf and it is the one we inserted.
+        // If the implementation is buggy (off-by-one), this usually fails
+        // because the node for /24 is never created, only up to /23.
+        assert_eq!(leaves.len(), 1, "Should have exactly 1 leaf");
+        assert_eq!(leaves[0], p, "The leaf should match the inserted prefix");
+    }
+
+    #[test]
+    fn test_insert_basic_ipv6() {
+        let mut tree = PrefixTrie::new();
+        let p = Prefix::from_str("2001:db8::/32").unwrap();
+
+        tree.insert(&p, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(leaves[0], p);
+    }
+
+    #[test]
+    fn test_insert_branching() {
+        let mut tree = PrefixTrie::new();
+        // 0.0.0.0/1 (starts with 0)
+        let p1 = Prefix::from_str("0.0.0.0/1").unwrap();
+        // 128.0.0.0/1 (starts with 1)
+        let p2 = Prefix::from_str("128.0.0.0/1").unwrap();
+
+        tree.insert(&p1, ());
+        tree.insert(&p2, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 2);
+
+        let leaf_set: HashSet<Prefix> = leaves.into_iter().collect();
+        assert!(leaf_set.contains(&p1));
+        assert!(leaf_set.contains(&p2));
+    }
+
+    #[test]
+    fn test_leaf_logic_nested() {
+        // Test that leaves() only returns actual nodes with no children.
+        // If we insert /24 and /25, /24 becomes an internal node,
+        // so only /25 should be returned by leaves().
+        let mut tree = PrefixTrie::new();
+        let parent = Prefix::from_str("10.0.0.0/24").unwrap();
+        let child = Prefix::from_str("10.0.0.0/25").unwrap();
+
+        tree.insert(&parent, ());
+        tree.insert(&child, ());
+
+        let leaves = collect_leaves(&tree);
+
+        assert_eq!(leaves.len(), 1, "Should only have 1 leaf (the most specific one)");
+        assert_eq!(leaves[0], child, "The leaf should be the /25 prefix");
+    }
+
+    #[test]
+    fn test_insert_root() {
+        let mut tree = PrefixTrie::new();
+        let root_prefix = Prefix::from_str("0.0.0.0/0").unwrap();
+        tree.insert(&root_prefix, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(leaves[0], root_prefix);
+    }
+
+    #[test]
+    fn test_multiple_branches() {
+        let mut tree = PrefixTrie::new();
+        // 10.0.0.0/8
+        let p1 = Prefix::from_str("10.0.0.0/8").unwrap();
+        // 192.168.1.0/24
+        let p2 = Prefix::from_str("192.168.1.0/24").unwrap();
+        // 192.168.2.0/24
+        let p3 = Prefix::from_str("192.168.2.0/24").unwrap();
+
+        tree.insert(&p1, ());
+        tree.insert(&p2, ());
+        tree.insert(&p3, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 3);
+
+        let leaf_set: HashSet<Prefix> = leaves.into_iter().collect();
+        assert!(leaf_set.contains(&p1));
+        assert!(leaf_set.contains(&p2));
+        assert!(leaf_set.contains(&p3));
+    }
+
+    #[test]
+    fn test_ipv4_max_prefix_length() {
+        // Test inserting a /32 prefix (maximum for IPv4)
+        let mut tree = PrefixTrie::new();
+        let p = Prefix::from_str("192.168.1.1/32").unwrap();
+
+        tree.insert(&p, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(leaves[0], p);
+    }
+
+    #[test]
+    fn test_ipv6_max_prefix_length() {
+        // Test inserting a /128 prefix (maximum for IPv6)
+        let mut tree = PrefixTrie::new();
+        let p = Prefix::from_str("2001:db8::1/128").unwrap();
+
+        tree.insert(&p, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(leaves[0], p);
+    }
+
+    #[test]
+    fn test_empty_tree_leaves() {
+        // Ensure leaves() on an empty tree doesn't crash
+        let tree = PrefixTrie::new();
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 0, "Empty tree should have no leaves");
+    }
+
+    #[test]
+    fn test_insert_duplicate_prefix() {
+        // Inserting the same prefix twice should still result in one leaf
+        let mut tree = PrefixTrie::new();
+        let p = Prefix::from_str("10.0.0.0/16").unwrap();
+
+        tree.insert(&p, ());
+        tree.insert(&p, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(leaves[0], p);
+    }
+
+    #[test]
+    fn test_child_before_parent_insertion() {
+        // Insert child first, then parent - parent should become internal node
+        let mut tree = PrefixTrie::new();
+        let child = Prefix::from_str("192.168.1.128/25").unwrap();
+        let parent = Prefix::from_str("192.168.1.0/24").unwrap();
+
+        tree.insert(&child, ());
+        tree.insert(&parent, ());
+
+        let leaves = collect_leaves(&tree);
+        // The /24 should become an internal node, only /25 should be a leaf
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(leaves[0], child);
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        // Test a deep chain of prefixes where each is more specific than the previous
+        let mut tree = PrefixTrie::new();
+        let p1 = Prefix::from_str("10.0.0.0/8").unwrap();
+        let p2 = Prefix::from_str("10.1.0.0/16").unwrap();
+        let p3 = Prefix::from_str("10.1.1.0/24").unwrap();
+        let p4 = Prefix::from_str("10.1.1.128/25").unwrap();
+
+        tree.insert(&p1, ());
+        tree.insert(&p2, ());
+        tree.insert(&p3, ());
+        tree.insert(&p4, ());
+
+        let leaves = collect_leaves(&tree);
+        // Only the most specific prefix should be a leaf
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(leaves[0], p4);
+    }
+
+    #[test]
+    fn test_sibling_branches_different_depths() {
+        // Test that siblings can have different depths
+        let mut tree = PrefixTrie::new();
+        let p1 = Prefix::from_str("10.0.0.0/24").unwrap();    // Deep branch
+        let p2 = Prefix::from_str("192.0.0.0/8").unwrap();    // Shallow branch
+
+        tree.insert(&p1, ());
+        tree.insert(&p2, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 2);
+
+        let leaf_set: HashSet<Prefix> = leaves.into_iter().collect();
+        assert!(leaf_set.contains(&p1));
+        assert!(leaf_set.contains(&p2));
+    }
+
+
+    #[test]
+    fn test_multiple_prefixes_same_branch() {
+        // Insert multiple prefixes along the same branch
+        let mut tree = PrefixTrie::new();
+        let p1 = Prefix::from_str("10.0.0.0/8").unwrap();
+        let p2 = Prefix::from_str("10.128.0.0/9").unwrap();  // Different second bit
+        let p3 = Prefix::from_str("10.0.0.0/9").unwrap();    // Same as first 9 bits of p1
+
+        tree.insert(&p1, ());
+        tree.insert(&p2, ());
+        tree.insert(&p3, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 2, "Should have 2 leaves (two /9 prefixes)");
+
+        let leaf_set: HashSet<Prefix> = leaves.into_iter().collect();
+        assert!(leaf_set.contains(&p2));
+        assert!(leaf_set.contains(&p3));
+        assert!(!leaf_set.contains(&p1), "/8 should be internal node, not a leaf");
+    }
+
+    #[test]
+    fn test_ipv6_various_lengths() {
+        // Test IPv6 with various prefix lengths
+        let mut tree = PrefixTrie::new();
+        let p32 = Prefix::from_str("2001:db8::/32").unwrap();
+        let p48 = Prefix::from_str("2001:db8:1::/48").unwrap();
+        let p64 = Prefix::from_str("2001:db8:1:2::/64").unwrap();
+
+        tree.insert(&p32, ());
+        tree.insert(&p48, ());
+        tree.insert(&p64, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(leaves[0], p64, "Most specific prefix should be the leaf");
+    }
+
+    #[test]
+    fn test_complex_tree_structure() {
+        // Build a more complex tree with multiple levels and branches
+        let mut tree = PrefixTrie::new();
+        let prefixes = vec![
+            "10.0.0.0/8",
+            "10.1.0.0/16",
+            "10.2.0.0/16",
+            "10.1.1.0/24",
+            "172.16.0.0/12",
+            "172.16.1.0/24",
+            "192.168.0.0/16",
+            "192.168.1.0/24",
+            "192.168.2.0/24",
+        ];
+
+        let parsed_prefixes: Vec<Prefix> = prefixes
+            .iter()
+            .map(|s| Prefix::from_str(s).unwrap())
+            .collect();
+
+        for p in &parsed_prefixes {
+            tree.insert(p, ());
+        }
+
+        let leaves = collect_leaves(&tree);
+        // Count actual leaves (most specific prefixes with no more specific children):
+        // 10.1.1.0/24, 10.2.0.0/16, 172.16.1.0/24, 192.168.1.0/24, 192.168.2.0/24
+        assert_eq!(leaves.len(), 5);
+    }
+
+    #[test]
+    fn test_single_bit_prefixes() {
+        // Test with /1 prefixes (single bit differentiation)
+        let mut tree = PrefixTrie::new();
+        let p_zero = Prefix::from_str("0.0.0.0/1").unwrap();
+        let p_one = Prefix::from_str("128.0.0.0/1").unwrap();
+
+        tree.insert(&p_zero, ());
+        tree.insert(&p_one, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 2);
+
+        let leaf_set: HashSet<Prefix> = leaves.into_iter().collect();
+        assert!(leaf_set.contains(&p_zero));
+        assert!(leaf_set.contains(&p_one));
+    }
+
+    #[test]
+    fn test_ipv6_compressed_notation() {
+        // Test IPv6 with compressed notation (should be treated identically)
+        let mut tree = PrefixTrie::new();
+        let p1 = Prefix::from_str("2001:db8::1/128").unwrap();
+        let p2 = Prefix::from_str("2001:db8:0:0:0:0:0:1/128").unwrap();
+
+        // These should be the same prefix
+        tree.insert(&p1, ());
+        tree.insert(&p2, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 1, "Compressed and full notation should represent the same prefix");
+        assert_eq!(leaves[0], p1);
+    }
+
+    #[test]
+    fn test_ordering_independence() {
+        // Test that insertion order doesn't affect the final tree structure
+        let mut tree1 = PrefixTrie::new();
+        let mut tree2 = PrefixTrie::new();
+
+        let p1 = Prefix::from_str("10.0.0.0/8").unwrap();
+        let p2 = Prefix::from_str("10.1.0.0/16").unwrap();
+        let p3 = Prefix::from_str("10.1.1.0/24").unwrap();
+
+        // Insert in forward order
+        tree1.insert(&p1, ());
+        tree1.insert(&p2, ());
+        tree1.insert(&p3, ());
+
+        // Insert in reverse order
+        tree2.insert(&p3, ());
+        tree2.insert(&p2, ());
+        tree2.insert(&p1, ());
+
+        let leaves1 = collect_leaves(&tree1);
+        let leaves2 = collect_leaves(&tree2);
+
+        assert_eq!(leaves1.len(), leaves2.len());
+        assert_eq!(leaves1, leaves2, "Insertion order should not affect final tree structure");
+    }
+
+    #[test]
+    fn test_adjacent_prefixes() {
+        // Test inserting adjacent prefixes that share a common parent
+        let mut tree = PrefixTrie::new();
+        // These two /25 prefixes combine to form 192.168.1.0/24
+        let p1 = Prefix::from_str("192.168.1.0/25").unwrap();
+        let p2 = Prefix::from_str("192.168.1.128/25").unwrap();
+
+        tree.insert(&p1, ());
+        tree.insert(&p2, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 2);
+
+        let leaf_set: HashSet<Prefix> = leaves.into_iter().collect();
+        assert!(leaf_set.contains(&p1));
+        assert!(leaf_set.contains(&p2));
+    }
+
+    #[test]
+    fn test_three_level_branching() {
+        // Test a tree with three levels of branching
+        let mut tree = PrefixTrie::new();
+        let p1 = Prefix::from_str("10.0.0.0/8").unwrap();
+        let p2 = Prefix::from_str("10.0.0.0/16").unwrap();
+        let p3 = Prefix::from_str("10.0.0.0/24").unwrap();
+        let p4 = Prefix::from_str("10.0.1.0/24").unwrap();  // Sibling of p3
+        let p5 = Prefix::from_str("10.1.0.0/16").unwrap();  // Sibling of p2
+
+        tree.insert(&p1, ());
+        tree.insert(&p2, ());
+        tree.insert(&p3, ());
+        tree.insert(&p4, ());
+        tree.insert(&p5, ());
+
+        let leaves = collect_leaves(&tree);
+        // Leaves should be: p3, p4, p5
+        assert_eq!(leaves.len(), 3);
+
+        let leaf_set: HashSet<Prefix> = leaves.into_iter().collect();
+        assert!(leaf_set.contains(&p3));
+        assert!(leaf_set.contains(&p4));
+        assert!(leaf_set.contains(&p5));
+    }
+
+    #[test]
+    fn test_ipv6_zero_prefix() {
+        // Test IPv6 ::/0 (default route)
+        let mut tree = PrefixTrie::new();
+        let p = Prefix::from_str("::/0").unwrap();
+
+        tree.insert(&p, ());
+
+        let leaves = collect_leaves(&tree);
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(leaves[0], p);
+    }
+}
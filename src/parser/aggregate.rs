@@ -0,0 +1,247 @@
+use crate::model::output::ROA;
+use crate::model::record::Prefix;
+use crate::model::trie::PrefixTrie;
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+
+// Only the largest max length per prefix matters, a smaller one authorises a subset
+type AsnRoas = PrefixTrie<u8>;
+
+// Whether some ROA in the set already makes the prefix a valid announcement
+fn is_authorised(roas: &AsnRoas, prefix: &Prefix) -> bool {
+    roas.covering(prefix).any(|(_, max_length)| *max_length >= prefix.prefix_len())
+}
+
+// Drops ROAs whose authorised announcements are a subset of a covering ROA's
+fn remove_nested(roas: &mut AsnRoas) -> bool {
+    let nested = roas
+        .iter()
+        .filter(|(prefix, max_length)| {
+            roas.covering(prefix).any(|(covering, covering_max)| covering != *prefix && covering_max >= *max_length)
+        })
+        .map(|(prefix, _)| prefix)
+        .collect::<Vec<_>>();
+
+    for prefix in &nested {
+        roas.remove(prefix);
+    }
+
+    !nested.is_empty()
+}
+
+// Merges two sibling ROAs with the same max length into their parent. The parent
+// itself is the only announcement this adds, so it must already be authorised.
+fn merge_siblings(roas: &mut AsnRoas) -> bool {
+    let entries = roas.iter().map(|(prefix, &max_length)| (prefix, max_length)).collect::<Vec<_>>();
+
+    for (prefix, max_length) in entries {
+        let Some(parent) = prefix.supernet() else {
+            continue;
+        };
+
+        let Some(sibling) = parent.subnets(prefix.prefix_len()).find(|s| *s != prefix) else {
+            continue;
+        };
+
+        if roas.get(&sibling) != Some(&max_length) || !is_authorised(roas, &parent) {
+            continue;
+        }
+
+        roas.remove(&prefix);
+        roas.remove(&sibling);
+
+        let parent_max = roas.get(&parent).copied().unwrap_or(0).max(max_length);
+        roas.insert(&parent, parent_max);
+
+        return true;
+    }
+
+    false
+}
+
+// Minimal ROA set authorising exactly the same (prefix, origin) announcements, so
+// route origin validation gives the same result for every route
+pub fn compress_roas(roas: &[ROA]) -> Vec<ROA> {
+    let mut by_asn: BTreeMap<u32, AsnRoas> = BTreeMap::new();
+    // ROAs with a prefix we cannot reason about are passed through untouched
+    let mut unparsed = Vec::new();
+
+    for roa in roas {
+        let prefix = match Prefix::from_str(&roa.prefix) {
+            Ok(prefix) if roa.max_length >= prefix.prefix_len() => prefix.with_prefix_len(prefix.prefix_len()),
+            _ => {
+                unparsed.push(roa.clone());
+                continue;
+            }
+        };
+
+        let asn_roas = by_asn.entry(roa.asn).or_default();
+        let max_length = asn_roas.get(&prefix).copied().unwrap_or(0).max(roa.max_length);
+        asn_roas.insert(&prefix, max_length);
+    }
+
+    for asn_roas in by_asn.values_mut() {
+        while remove_nested(asn_roas) || merge_siblings(asn_roas) {}
+    }
+
+    // Keep surviving ROAs in input order with their prefix as written, merged ones follow
+    let mut emitted = HashSet::new();
+    let mut compressed = Vec::new();
+
+    for roa in roas {
+        let Ok(prefix) = Prefix::from_str(&roa.prefix) else {
+            continue;
+        };
+        let prefix = prefix.with_prefix_len(prefix.prefix_len());
+
+        let kept = by_asn
+            .get(&roa.asn)
+            .and_then(|asn_roas| asn_roas.get(&prefix))
+            .is_some_and(|&max_length| max_length == roa.max_length);
+
+        if kept && emitted.insert((roa.asn, prefix)) {
+            compressed.push(roa.clone());
+        }
+    }
+
+    for (&asn, asn_roas) in &by_asn {
+        for (prefix, &max_length) in asn_roas.iter() {
+            if emitted.insert((asn, prefix.clone())) {
+                compressed.push(ROA {
+                    asn,
+                    prefix: prefix.to_string(),
+                    max_length,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    compressed.extend(unparsed);
+
+    compressed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roa(asn: u32, prefix: &str, max_length: u8) -> ROA {
+        ROA { asn, prefix: prefix.to_string(), max_length, ..Default::default() }
+    }
+
+    fn summary(roas: &[ROA]) -> Vec<(u32, &str, u8)> {
+        let mut summary = roas.iter().map(|r| (r.asn, r.prefix.as_str(), r.max_length)).collect::<Vec<_>>();
+        summary.sort();
+        summary
+    }
+
+    // Whether the announcement is valid for the origin under the given ROAs (RFC 6811)
+    fn is_valid(roas: &[ROA], asn: u32, route: &Prefix) -> bool {
+        roas.iter().any(|r| {
+            let prefix = Prefix::from_str(&r.prefix).unwrap();
+            r.asn == asn && prefix.contains(route) && route.prefix_len() <= r.max_length
+        })
+    }
+
+    fn is_covered(roas: &[ROA], route: &Prefix) -> bool {
+        roas.iter().any(|r| Prefix::from_str(&r.prefix).unwrap().contains(route))
+    }
+
+    #[test]
+    fn test_nested_and_duplicate_roas_are_removed() {
+        let roas = vec![
+            roa(4242420000, "172.20.0.0/16", 24),
+            roa(4242420000, "172.20.1.0/24", 24),
+            roa(4242420000, "172.20.0.0/16", 20),
+            roa(4242420000, "172.20.0.0/16", 24),
+            // Longer max length than the covering ROA, must stay
+            roa(4242420000, "172.20.2.0/24", 28),
+            // Different origin, must stay
+            roa(4242420001, "172.20.3.0/24", 24),
+        ];
+
+        assert_eq!(summary(&compress_roas(&roas)), vec![
+            (4242420000, "172.20.0.0/16", 24),
+            (4242420000, "172.20.2.0/24", 28),
+            (4242420001, "172.20.3.0/24", 24),
+        ]);
+    }
+
+    #[test]
+    fn test_siblings_merge_only_when_parent_is_authorised() {
+        // Merging would newly authorise 172.20.0.0/23 itself
+        let roas = vec![
+            roa(4242420000, "172.20.0.0/24", 24),
+            roa(4242420000, "172.20.1.0/24", 24),
+        ];
+        assert_eq!(compress_roas(&roas).len(), 2);
+
+        let roas = vec![
+            roa(4242420000, "172.20.0.0/23", 23),
+            roa(4242420000, "172.20.0.0/24", 28),
+            roa(4242420000, "172.20.1.0/24", 28),
+        ];
+        assert_eq!(summary(&compress_roas(&roas)), vec![(4242420000, "172.20.0.0/23", 28)]);
+
+        // Different max lengths cannot be merged
+        let roas = vec![
+            roa(4242420000, "172.20.0.0/23", 23),
+            roa(4242420000, "172.20.0.0/24", 28),
+            roa(4242420000, "172.20.1.0/24", 26),
+        ];
+        assert_eq!(compress_roas(&roas).len(), 3);
+    }
+
+    #[test]
+    fn test_merges_cascade() {
+        let roas = vec![
+            roa(4242420000, "fd42::/46", 46),
+            roa(4242420000, "fd42::/48", 64),
+            roa(4242420000, "fd42:0:1::/48", 64),
+            roa(4242420000, "fd42:0:2::/48", 64),
+            roa(4242420000, "fd42:0:3::/48", 64),
+            // The /47s become authorised by the /46 only after it is known
+            roa(4242420000, "fd42::/47", 47),
+            roa(4242420000, "fd42:0:2::/47", 47),
+        ];
+
+        assert_eq!(summary(&compress_roas(&roas)), vec![(4242420000, "fd42::/46", 64)]);
+    }
+
+    #[test]
+    fn test_compression_preserves_validation_outcomes() {
+        let roas = vec![
+            roa(4242420000, "172.20.0.0/22", 22),
+            roa(4242420000, "172.20.0.0/23", 24),
+            roa(4242420000, "172.20.2.0/24", 24),
+            roa(4242420000, "172.20.3.0/24", 24),
+            roa(4242420000, "172.20.0.0/24", 24),
+            roa(4242420001, "172.20.0.0/24", 26),
+            roa(4242420001, "172.20.1.0/24", 26),
+            roa(4242420002, "172.20.4.0/25", 25),
+            roa(4242420002, "172.20.4.128/25", 25),
+        ];
+
+        let compressed = compress_roas(&roas);
+        assert!(compressed.len() < roas.len());
+
+        let base = Prefix::from_str("172.20.0.0/21").unwrap();
+
+        for len in 21..=28 {
+            for route in base.subnets(len) {
+                for asn in [4242420000, 4242420001, 4242420002, 4242420003] {
+                    assert_eq!(is_valid(&roas, asn, &route), is_valid(&compressed, asn, &route), "AS{} {}", asn, route);
+                }
+                assert_eq!(is_covered(&roas, &route), is_covered(&compressed, &route), "{}", route);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unparsable_roas_pass_through() {
+        let roas = vec![roa(4242420000, "not-a-prefix", 24), roa(4242420000, "172.20.0.0/24", 16)];
+
+        assert_eq!(compress_roas(&roas), roas);
+    }
+}
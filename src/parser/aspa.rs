@@ -0,0 +1,76 @@
+use crate::model::asn::parse_asn;
+use crate::model::output::Aspa;
+use crate::model::record::RecordFile;
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+// One ASPA per aut-num carrying the provider attribute. Values may list several
+// providers separated by whitespace or commas, AS0 declares that there are none.
+pub fn get_parsed_aspas(aut_num_records: &[RecordFile], provider_attribute: &str) -> Vec<Aspa> {
+    let mut aspas: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+
+    for record_file in aut_num_records {
+        let mut values = record_file.get_attributes(provider_attribute).peekable();
+
+        if values.peek().is_none() {
+            continue;
+        }
+
+        let Some(customer_asid) = parse_asn(record_file.primary_key()) else {
+            warn!("Invalid aut-num {:?} in record: {:?}", record_file.primary_key(), record_file.get_file_path());
+            continue;
+        };
+
+        let providers = aspas.entry(customer_asid).or_default();
+
+        for provider in values.flat_map(|v| v.split(|c: char| c == ',' || c.is_whitespace())).filter(|p| !p.is_empty()) {
+            match parse_asn(provider) {
+                Some(0) => {}
+                Some(asn) if asn == customer_asid => {
+                    warn!("aut-num {} lists itself as a provider: {:?}", record_file.primary_key(), record_file.get_file_path());
+                }
+                Some(asn) => providers.push(asn),
+                None => warn!("Invalid provider ASN {:?} in record: {:?}", provider, record_file.get_file_path()),
+            }
+        }
+    }
+
+    let aspas = aspas
+        .into_iter()
+        .map(|(customer_asid, mut providers)| {
+            providers.sort_unstable();
+            providers.dedup();
+
+            Aspa { customer_asid, providers, ..Default::default() }
+        })
+        .collect::<Vec<_>>();
+
+    info!("Generated {} ASPA entries.", aspas.len());
+
+    aspas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::registry::tests::record;
+
+    #[test]
+    fn test_get_parsed_aspas() {
+        let records = vec![
+            record("data/aut-num/AS4242420000", "aut-num: AS4242420000\naspa-provider: AS4242420002, AS4242420001\naspa-provider: 4242420001 AS-BAD\n"),
+            record("data/aut-num/AS4242420001", "aut-num: AS4242420001\naspa-provider: AS0\n"),
+            record("data/aut-num/AS4242420002", "aut-num: AS4242420002\nas-name: NO-ASPA\n"),
+        ];
+
+        let aspas = get_parsed_aspas(&records, "aspa-provider");
+
+        assert_eq!(aspas, vec![
+            Aspa { customer_asid: 4242420000, providers: vec![4242420001, 4242420002], ..Default::default() },
+            Aspa { customer_asid: 4242420001, providers: vec![], ..Default::default() },
+        ]);
+
+        let json = serde_json::to_value(&aspas[0]).unwrap();
+        assert_eq!(json, serde_json::json!({"customer_asid": 4242420000u32, "providers": [4242420001u32, 4242420002u32]}));
+    }
+}
@@ -1,5 +1,6 @@
-use crate::model::dns::{DNSClass, DNSRecord, DNSRecordData, DNSZone, FQDNName, PrefixTree};
+use crate::model::dns::{DNSClass, DNSRecord, DNSRecordData, DNSZone, FQDNName};
 use crate::model::record::{Prefix, RecordField, RecordFile};
+use crate::model::trie::PrefixTrie;
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr};
 use std::str::FromStr;
@@ -8,9 +9,9 @@ use tracing::{error, info, warn};
 const DEFAULT_TTL: u32 = 3600;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct ExtractedNameServerInfo {
-    name_server: FQDNName,
-    name_server_ip: Option<IpAddr>,
+pub(crate) struct ExtractedNameServerInfo {
+    pub(crate) name_server: FQDNName,
+    pub(crate) name_server_ip: Option<IpAddr>,
 }
 
 impl TryFrom<&RecordFile> for Vec<ExtractedNameServerInfo> {
@@ -51,18 +52,18 @@ impl TryFrom<&RecordFile> for Vec<ExtractedNameServerInfo> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct ExtractedNetworkInfo {
-    cidr: Prefix,
-    name_servers: Vec<ExtractedNameServerInfo>,
-    ds_rdata: Vec<String>,
+pub(crate) struct ExtractedNetworkInfo {
+    pub(crate) cidr: Prefix,
+    pub(crate) name_servers: Vec<ExtractedNameServerInfo>,
+    pub(crate) ds_rdata: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct ExtractedDomainInfo {
-    domain: FQDNName,
-    tld: String,
-    name_servers: Vec<ExtractedNameServerInfo>,
-    ds_rdata: Vec<String>,
+pub(crate) struct ExtractedDomainInfo {
+    pub(crate) domain: FQDNName,
+    pub(crate) tld: String,
+    pub(crate) name_servers: Vec<ExtractedNameServerInfo>,
+    pub(crate) ds_rdata: Vec<String>,
 }
 
 impl TryFrom<&RecordFile> for ExtractedDomainInfo
@@ -137,7 +138,7 @@ fn new_zone(tld: &str, dns_primary_master: String, dns_responsible_person: Strin
     })
 }
 
-fn is_registry_sync_domain(domain: &FQDNName) -> bool {
+pub(crate) fn is_registry_sync_domain(domain: &FQDNName) -> bool {
     domain.as_str().ends_with("registry-sync.dn42") && domain.as_str() != "registry-sync.dn42"
 }
 
@@ -209,7 +210,7 @@ fn extract_ipv6_from_labels(labels: &[&str]) -> Result<IpAddr, String> {
 
 // 1.1.25.10.ipv4.registry-sync.dn42
 // 1.0.1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.a.b.f.f.a.b.f.f.0.0.d.f.ipv6.registry-sync.dn42
-fn extract_ip_from_registry_sync_domain(domain: &FQDNName) -> Result<IpAddr, String> {
+pub(crate) fn extract_ip_from_registry_sync_domain(domain: &FQDNName) -> Result<IpAddr, String> {
     let labels: Vec<&str> = domain.as_str().split('.').collect();
     if labels.len() < 4 {
         return Err(format!("Invalid registry-sync domain: {}, should have at least 4 labels", domain));
@@ -356,7 +357,7 @@ struct ReverseRecordCounter {
     ipv6_non_align: usize,
 }
 
-fn generate_reverse_record_name(cidr: &Prefix) -> Option<FQDNName> {
+pub fn generate_reverse_record_name(cidr: &Prefix) -> Option<FQDNName> {
     match cidr.network() {
         IpAddr::V4(ipv4) => {
             if cidr.prefix_len().is_multiple_of(8) {
@@ -608,11 +609,8 @@ pub fn generate_reverse_zones(record_files: &[RecordFile], dns_primary_master: &
         data: DNSRecordData::NS(dns_primary_master.to_string()),
     }).unwrap();
 
-    let mut ipv4_tree = PrefixTree::new();
-    let mut ipv6_tree = PrefixTree::new();
-
-    let mut cidr_to_nameservers: HashMap<Prefix, Vec<ExtractedNameServerInfo>> = HashMap::new();
-    let mut cidr_to_ds_rdata: HashMap<Prefix, Vec<String>> = HashMap::new();
+    // Delegated prefix to its name servers and DS records, only the most specific delegations get records
+    let mut delegations: PrefixTrie<(Vec<ExtractedNameServerInfo>, Vec<String>)> = PrefixTrie::new();
 
     for record_file in record_files {
         let extracted_info = match ExtractedNetworkInfo::try_from(record_file) {
@@ -624,54 +622,30 @@ pub fn generate_reverse_zones(record_files: &[RecordFile], dns_primary_master: &
         };
 
         if !extracted_info.name_servers.is_empty() {
-            cidr_to_nameservers.insert(extracted_info.cidr.clone(), extracted_info.name_servers.clone());
-            cidr_to_ds_rdata.insert(extracted_info.cidr.clone(), extracted_info.ds_rdata.clone());
-
-            match extracted_info.cidr.network() {
-                IpAddr::V4(_) => {
-                    ipv4_tree.insert(extracted_info.cidr.clone());
-                }
-                IpAddr::V6(_) => {
-                    ipv6_tree.insert(extracted_info.cidr.clone());
-                }
-            }
+            delegations.insert(&extracted_info.cidr, (extracted_info.name_servers, extracted_info.ds_rdata));
         }
     }
 
     let mut counter = ReverseRecordCounter::default();
 
-    ipv4_tree.visit_leaf(&mut |prefix| {
-        let name_servers = cidr_to_nameservers.get(prefix).unwrap();
-        let ds_rdata = cidr_to_ds_rdata.get(prefix).unwrap();
-
-        for record in generate_reverse_records(prefix, name_servers, &mut counter) {
-            if let Err(e) = ipv4_zone.add_record(record) {
-                error!("Failed to add reverse record to IPv4 zone {}: {}", ipv4_zone.origin(), e);
-            }
-        }
-
-        for record in generate_reverse_ds_record(prefix, ds_rdata) {
-            if let Err(e) = ipv4_zone.add_record(record) {
-                error!("Failed to add DS record to IPv4 zone {}: {}", ipv4_zone.origin(), e);
-            }
-        }
-    });
-
-    ipv6_tree.visit_leaf(&mut |prefix| {
-        let name_servers = cidr_to_nameservers.get(prefix).unwrap();
+    for (prefix, (name_servers, ds_rdata)) in delegations.leaves() {
+        let zone = match prefix.network() {
+            IpAddr::V4(_) => &mut ipv4_zone,
+            IpAddr::V6(_) => &mut ipv6_zone,
+        };
 
-        for record in generate_reverse_records(prefix, name_servers, &mut counter) {
-            if let Err(e) = ipv6_zone.add_record(record) {
-                error!("Failed to add reverse record to IPv6 zone {}: {}", ipv6_zone.origin(), e);
+        for record in generate_reverse_records(&prefix, name_servers, &mut counter) {
+            if let Err(e) = zone.add_record(record) {
+                error!("Failed to add reverse record to zone {}: {}", zone.origin(), e);
             }
         }
 
-        for record in generate_reverse_ds_record(prefix, cidr_to_ds_rdata.get(prefix).unwrap()) {
-            if let Err(e) = ipv6_zone.add_record(record) {
-                error!("Failed to add DS record to IPv6 zone {}: {}", ipv6_zone.origin(), e);
+        for record in generate_reverse_ds_record(&prefix, ds_rdata) {
+            if let Err(e) = zone.add_record(record) {
+                error!("Failed to add DS record to zone {}: {}", zone.origin(), e);
             }
         }
-    });
+    }
 
     info!("Generated {} IPv4 reverse records ({} aligned, {} non-aligned).", counter.ipv4_align + counter.ipv4_non_align, counter.ipv4_align, counter.ipv4_non_align);
     info!("Generated {} IPv6 reverse records ({} aligned). {} non-aligned not generated", counter.ipv6_align + counter.ipv6_non_align, counter.ipv6_align, counter.ipv6_non_align);
@@ -682,6 +656,7 @@ pub fn generate_reverse_zones(record_files: &[RecordFile], dns_primary_master: &
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
     #[test]
@@ -785,10 +760,10 @@ mod tests {
         let cname_rec = records.iter().find(|r| r.name.as_str() == ptr_name_0 && matches!(r.data, DNSRecordData::CNAME(_)));
         assert!(cname_rec.is_some(), "CNAME record for host 0 missing");
 
-        if let Some(rec) = cname_rec {
-            if let DNSRecordData::CNAME(target) = &rec.data {
-                assert_eq!(target, "0.0/25.2.0.192.in-addr.arpa", "RFC2317 CNAME target format is incorrect");
-            }
+        if let Some(rec) = cname_rec
+            && let DNSRecordData::CNAME(target) = &rec.data
+        {
+            assert_eq!(target, "0.0/25.2.0.192.in-addr.arpa", "RFC2317 CNAME target format is incorrect");
         }
     }
 
@@ -1241,4 +1216,25 @@ mod tests {
             .find(|r| r.name.as_str() == "239.2.0.192.in-addr.arpa");
         assert!(before.is_none(), "Should not have CNAME for IP outside range");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_reverse_zones_use_most_specific_delegation() {
+        let records = vec![
+            RecordFile::from_content("data/inetnum/172.20.0.0_16".into(), "inetnum: 172.20.0.0 - 172.20.255.255\ncidr: 172.20.0.0/16\nnserver: ns1.example.dn42\n".to_string()),
+            // Host bits set in the cidr used to panic when looking up the leaf
+            RecordFile::from_content("data/inetnum/172.20.1.0_24".into(), "inetnum: 172.20.1.0 - 172.20.1.255\ncidr: 172.20.1.1/24\nnserver: ns2.example.dn42\n".to_string()),
+        ];
+
+        let zones = generate_reverse_zones(&records, "ns.example.dn42", "admin.example.dn42");
+        let ipv4_zone = &zones[0];
+
+        let ns_names = ipv4_zone.records()
+            .iter()
+            .filter(|r| matches!(r.data, DNSRecordData::NS(_)))
+            .map(|r| r.name.as_str().to_string())
+            .collect::<HashSet<_>>();
+
+        assert!(ns_names.contains("1.20.172.in-addr.arpa"));
+        assert!(!ns_names.contains("20.172.in-addr.arpa"));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::model::dns::FQDNName;
+use crate::model::record::RecordFile;
+use crate::parser::dns::{
+    extract_ip_from_registry_sync_domain, generate_reverse_record_name, is_registry_sync_domain, ExtractedDomainInfo,
+    ExtractedNameServerInfo, ExtractedNetworkInfo,
+};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::net::IpAddr;
+use strum::{Display, EnumIter};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Display, EnumIter)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum DnsLintKind {
+    // The nameserver is inside the delegated zone but has no address, resolvers cannot reach it
+    MissingGlue,
+    // An address is given for a nameserver outside the delegated zone
+    OutOfBailiwickGlue,
+    // Delegations give different addresses for the same nameserver
+    ConflictingGlue,
+    // The nameserver is under a TLD generated from the registry, but no domain object covers it
+    UnknownNameserver,
+    // The registry-sync name cannot be decoded or its address differs from the one in the name
+    RegistrySyncMismatch,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DnsLintProblem {
+    pub file: String,
+    // Delegated domain or reverse zone
+    pub zone: String,
+    pub nameserver: String,
+    pub kind: DnsLintKind,
+    pub message: String,
+}
+
+// A zone delegated by a domain, inetnum or inet6num object
+pub(crate) struct Delegation {
+    pub(crate) file: String,
+    pub(crate) zone: FQDNName,
+    pub(crate) name_servers: Vec<ExtractedNameServerInfo>,
+    pub(crate) ds_rdata: Vec<String>,
+}
+
+impl Delegation {
+    fn problem(&self, nameserver: &FQDNName, kind: DnsLintKind, message: String) -> DnsLintProblem {
+        DnsLintProblem {
+            file: self.file.clone(),
+            zone: self.zone.to_string(),
+            nameserver: nameserver.to_string(),
+            kind,
+            message,
+        }
+    }
+
+    // Addresses per nameserver, in the order the nameservers are listed
+    pub(crate) fn glue(&self) -> Vec<(&FQDNName, BTreeSet<IpAddr>)> {
+        let mut glue: Vec<(&FQDNName, BTreeSet<IpAddr>)> = Vec::new();
+
+        for name_server in &self.name_servers {
+            let index = match glue.iter().position(|(name, _)| *name == &name_server.name_server) {
+                Some(index) => index,
+                None => {
+                    glue.push((&name_server.name_server, BTreeSet::new()));
+                    glue.len() - 1
+                }
+            };
+
+            glue[index].1.extend(name_server.name_server_ip);
+        }
+
+        glue
+    }
+}
+
+fn format_addresses(addresses: &BTreeSet<IpAddr>) -> String {
+    addresses.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn labels(name: &FQDNName) -> Vec<&str> {
+    name.as_str().trim_end_matches('.').split('.').collect()
+}
+
+// Whether a domain object other than the TLD itself covers the name
+fn is_delegated(name: &FQDNName, delegated: &HashSet<String>) -> bool {
+    let labels = labels(name);
+
+    (0..labels.len().saturating_sub(1)).any(|start| delegated.contains(&labels[start..].join(".")))
+}
+
+// Records that cannot be parsed are skipped, zone generation already reports them
+pub(crate) fn forward_delegations(domain_records: &[RecordFile]) -> Vec<Delegation> {
+    domain_records
+        .iter()
+        .filter_map(|record_file| {
+            let info = ExtractedDomainInfo::try_from(record_file).ok()?;
+
+            Some(Delegation {
+                file: record_file.get_file_path().display().to_string(),
+                zone: info.domain,
+                name_servers: info.name_servers,
+                ds_rdata: info.ds_rdata,
+            })
+        })
+        .collect()
+}
+
+// Only inetnums carrying nserver attributes delegate, IPv6 prefixes off a nibble boundary have no zone
+pub(crate) fn reverse_delegations(inetnum_records: &[RecordFile]) -> Vec<Delegation> {
+    inetnum_records
+        .iter()
+        .filter_map(|record_file| {
+            let info = ExtractedNetworkInfo::try_from(record_file).ok().filter(|info| !info.name_servers.is_empty())?;
+
+            Some(Delegation {
+                file: record_file.get_file_path().display().to_string(),
+                zone: generate_reverse_record_name(&info.cidr)?,
+                name_servers: info.name_servers,
+                ds_rdata: info.ds_rdata,
+            })
+        })
+        .collect()
+}
+
+// Checks forward (domain) and reverse (inetnum/inet6num) delegations for nameserver and glue problems
+pub fn lint_delegations(domain_records: &[RecordFile], inetnum_records: &[RecordFile]) -> Vec<DnsLintProblem> {
+    let mut delegations = forward_delegations(domain_records);
+
+    let served_tlds = delegations.iter().filter_map(|d| d.zone.tld()).collect::<HashSet<_>>();
+    let delegated = delegations
+        .iter()
+        .map(|d| labels(&d.zone).join("."))
+        .filter(|name| name.contains('.'))
+        .collect::<HashSet<_>>();
+
+    delegations.extend(reverse_delegations(inetnum_records));
+
+    let mut problems = Vec::new();
+    // nameserver -> (delegation index, addresses) for every delegation giving it glue
+    let mut glue_by_name_server: HashMap<&FQDNName, Vec<(usize, BTreeSet<IpAddr>)>> = HashMap::new();
+
+    for (index, delegation) in delegations.iter().enumerate() {
+        for (name_server, addresses) in delegation.glue() {
+            if is_registry_sync_domain(name_server) {
+                match extract_ip_from_registry_sync_domain(name_server) {
+                    Ok(encoded) => {
+                        for address in addresses.iter().filter(|address| **address != encoded) {
+                            let message = format!("Address {} of {} does not match {} encoded in the name", address, name_server, encoded);
+                            problems.push(delegation.problem(name_server, DnsLintKind::RegistrySyncMismatch, message));
+                        }
+                    }
+                    Err(e) => problems.push(delegation.problem(name_server, DnsLintKind::RegistrySyncMismatch, e)),
+                }
+
+                continue;
+            }
+
+            let in_bailiwick = name_server.relative_to(&delegation.zone).is_some();
+
+            if in_bailiwick && addresses.is_empty() {
+                let message = format!("Nameserver {} is inside {} but has no glue address", name_server, delegation.zone);
+                problems.push(delegation.problem(name_server, DnsLintKind::MissingGlue, message));
+            }
+
+            if !in_bailiwick && !addresses.is_empty() {
+                let message = format!("Glue {} given for {}, which is outside {}", format_addresses(&addresses), name_server, delegation.zone);
+                problems.push(delegation.problem(name_server, DnsLintKind::OutOfBailiwickGlue, message));
+            }
+
+            if !in_bailiwick
+                && name_server.tld().is_some_and(|tld| served_tlds.contains(&tld))
+                && !is_delegated(name_server, &delegated)
+            {
+                let message = format!("Nameserver {} does not exist, no domain object covers it", name_server);
+                problems.push(delegation.problem(name_server, DnsLintKind::UnknownNameserver, message));
+            }
+
+            if !addresses.is_empty() {
+                glue_by_name_server.entry(name_server).or_default().push((index, addresses));
+            }
+        }
+    }
+
+    for (name_server, entries) in &glue_by_name_server {
+        for (index, addresses) in entries {
+            let others = entries
+                .iter()
+                .filter(|(_, other)| other != addresses)
+                .map(|(other_index, other)| format!("{} ({})", delegations[*other_index].zone, format_addresses(other)))
+                .collect::<Vec<_>>();
+
+            if !others.is_empty() {
+                let message = format!("Glue {} for {} conflicts with {}", format_addresses(addresses), name_server, others.join(", "));
+                problems.push(delegations[*index].problem(name_server, DnsLintKind::ConflictingGlue, message));
+            }
+        }
+    }
+
+    problems.sort_by(|a, b| (&a.zone, &a.nameserver, a.kind).cmp(&(&b.zone, &b.nameserver, b.kind)));
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::registry::tests::record;
+
+    fn kinds(problems: &[DnsLintProblem]) -> Vec<(&str, &str, DnsLintKind)> {
+        problems.iter().map(|p| (p.zone.as_str(), p.nameserver.as_str(), p.kind)).collect()
+    }
+
+    #[test]
+    fn test_consistent_delegations() {
+        let domains = [
+            record("data/dns/dn42", "domain: dn42\nnserver: ns1.root.dn42 172.20.0.1\nsource: DN42\n"),
+            record("data/dns/root.dn42", "domain: root.dn42\nnserver: ns1.root.dn42 172.20.0.1\nsource: DN42\n"),
+            record("data/dns/example.dn42", "domain: example.dn42\nnserver: ns1.example.dn42 172.20.0.53\nnserver: ns1.example.dn42 fd00::53\nnserver: ns1.root.dn42\nsource: DN42\n"),
+        ];
+        let inetnums = [
+            record("data/inetnum/172.20.0.0_24", "inetnum: 172.20.0.0 - 172.20.0.255\ncidr: 172.20.0.0/24\nnserver: ns1.example.dn42\nsource: DN42\n"),
+        ];
+
+        assert!(lint_delegations(&domains, &inetnums).is_empty());
+    }
+
+    #[test]
+    fn test_inconsistent_delegations() {
+        let domains = [
+            record("data/dns/example.dn42", "domain: example.dn42\nnserver: ns1.example.dn42\nnserver: ns2.other.dn42 172.20.1.53\nsource: DN42\n"),
+            record("data/dns/other.dn42", "domain: other.dn42\nnserver: ns2.other.dn42 172.20.1.54\nnserver: ns.missing.dn42\nsource: DN42\n"),
+            record("data/dns/sync.dn42", "domain: sync.dn42\nnserver: 53.0.20.172.ipv4.registry-sync.dn42 172.20.0.53\nnserver: 54.0.20.172.ipv4.registry-sync.dn42 172.20.0.55\nnserver: x.ipv4.registry-sync.dn42\nsource: DN42\n"),
+            record("data/dns/example.com", "domain: example.com\nnserver: ns.example.net 192.0.2.1\nsource: DN42\n"),
+        ];
+
+        let problems = lint_delegations(&domains, &[]);
+
+        assert_eq!(kinds(&problems), vec![
+            ("example.com", "ns.example.net", DnsLintKind::OutOfBailiwickGlue),
+            ("example.dn42", "ns1.example.dn42", DnsLintKind::MissingGlue),
+            ("example.dn42", "ns2.other.dn42", DnsLintKind::OutOfBailiwickGlue),
+            ("example.dn42", "ns2.other.dn42", DnsLintKind::ConflictingGlue),
+            ("other.dn42", "ns.missing.dn42", DnsLintKind::UnknownNameserver),
+            ("other.dn42", "ns2.other.dn42", DnsLintKind::ConflictingGlue),
+            ("sync.dn42", "54.0.20.172.ipv4.registry-sync.dn42", DnsLintKind::RegistrySyncMismatch),
+            ("sync.dn42", "x.ipv4.registry-sync.dn42", DnsLintKind::RegistrySyncMismatch),
+        ]);
+
+        assert_eq!(problems[3].message, "Glue 172.20.1.53 for ns2.other.dn42 conflicts with other.dn42 (172.20.1.54)");
+        assert_eq!(problems[6].message, "Address 172.20.0.55 of 54.0.20.172.ipv4.registry-sync.dn42 does not match 172.20.0.54 encoded in the name");
+    }
+}
@@ -0,0 +1,389 @@
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_NS: u16 = 2;
+pub const TYPE_SOA: u16 = 6;
+pub const TYPE_AAAA: u16 = 28;
+pub const TYPE_DNSKEY: u16 = 48;
+const TYPE_OPT: u16 = 41;
+const CLASS_IN: u16 = 1;
+
+pub const RCODE_NOERROR: u8 = 0;
+
+// Advertised with EDNS, DNSKEY answers rarely fit in 512 bytes
+const EDNS_PAYLOAD_SIZE: u16 = 1232;
+const MAX_COMPRESSION_POINTERS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Ns(String),
+    Soa { mname: String, serial: u32 },
+    // Kept as raw rdata, the DS digest is computed over it
+    Dnskey { algorithm: u8, rdata: Vec<u8> },
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceRecord {
+    // Lowercase, without the trailing dot
+    pub name: String,
+    pub data: RecordData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsResponse {
+    pub id: u16,
+    pub authoritative: bool,
+    pub truncated: bool,
+    pub rcode: u8,
+    pub answers: Vec<ResourceRecord>,
+}
+
+pub fn rcode_name(rcode: u8) -> String {
+    match rcode {
+        0 => "NOERROR".to_string(),
+        1 => "FORMERR".to_string(),
+        2 => "SERVFAIL".to_string(),
+        3 => "NXDOMAIN".to_string(),
+        4 => "NOTIMP".to_string(),
+        5 => "REFUSED".to_string(),
+        _ => format!("RCODE{}", rcode),
+    }
+}
+
+pub fn encode_name(name: &str) -> Result<Vec<u8>, String> {
+    let mut encoded = Vec::new();
+
+    for label in name.trim_end_matches('.').split('.').filter(|label| !label.is_empty()) {
+        if label.len() > 63 {
+            return Err(format!("Label {:?} of {} exceeds 63 characters", label, name));
+        }
+
+        encoded.push(label.len() as u8);
+        encoded.extend(label.to_ascii_lowercase().bytes());
+    }
+
+    encoded.push(0);
+
+    Ok(encoded)
+}
+
+// A single question with an EDNS OPT record, recursion is only requested from resolvers
+pub fn encode_query(id: u16, name: &str, record_type: u16, recursion_desired: bool) -> Result<Vec<u8>, String> {
+    let flags: u16 = if recursion_desired { 0x0100 } else { 0 };
+
+    let mut query = Vec::with_capacity(64);
+
+    for value in [id, flags, 1, 0, 0, 1] {
+        query.extend(value.to_be_bytes());
+    }
+
+    query.extend(encode_name(name)?);
+    query.extend(record_type.to_be_bytes());
+    query.extend(CLASS_IN.to_be_bytes());
+
+    // OPT: root name, type, payload size as class, no extended flags or options
+    query.push(0);
+    query.extend(TYPE_OPT.to_be_bytes());
+    query.extend(EDNS_PAYLOAD_SIZE.to_be_bytes());
+    query.extend([0; 6]);
+
+    Ok(query)
+}
+
+struct Reader<'a> {
+    message: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.position + count;
+        let bytes = self.message.get(self.position..end).ok_or("Truncated message")?;
+        self.position = end;
+
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // Follows compression pointers, which may only point backwards and are limited to guard against loops
+    fn name(&mut self) -> Result<String, String> {
+        let mut labels = Vec::new();
+        let mut position = self.position;
+        let mut resume = None;
+        let mut jumps = 0;
+
+        loop {
+            let length = *self.message.get(position).ok_or("Truncated name")? as usize;
+
+            match length {
+                0 => {
+                    position += 1;
+                    break;
+                }
+                l if l & 0xc0 == 0xc0 => {
+                    let low = *self.message.get(position + 1).ok_or("Truncated name")? as usize;
+                    let target = ((l & 0x3f) << 8) | low;
+
+                    jumps += 1;
+
+                    if target >= position || jumps > MAX_COMPRESSION_POINTERS {
+                        return Err("Invalid name compression pointer".to_string());
+                    }
+
+                    resume.get_or_insert(position + 2);
+                    position = target;
+                }
+                l if l <= 63 => {
+                    let label = self.message.get(position + 1..position + 1 + l).ok_or("Truncated name")?;
+                    labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+                    position += 1 + l;
+                }
+                _ => return Err("Invalid label length".to_string()),
+            }
+        }
+
+        self.position = resume.unwrap_or(position);
+
+        Ok(labels.join("."))
+    }
+}
+
+fn decode_record(reader: &mut Reader, record_type: u16, length: usize) -> Result<RecordData, String> {
+    let end = reader.position + length;
+
+    let data = match record_type {
+        TYPE_A if length == 4 => {
+            let b = reader.bytes(4)?;
+            RecordData::A(Ipv4Addr::new(b[0], b[1], b[2], b[3]))
+        }
+        TYPE_AAAA if length == 16 => {
+            let octets: [u8; 16] = reader.bytes(16)?.try_into().map_err(|_| "Invalid AAAA record")?;
+            RecordData::Aaaa(Ipv6Addr::from(octets))
+        }
+        TYPE_NS => RecordData::Ns(reader.name()?),
+        TYPE_SOA => {
+            let mname = reader.name()?;
+            let _rname = reader.name()?;
+            RecordData::Soa { mname, serial: reader.u32()? }
+        }
+        TYPE_DNSKEY if length >= 4 => {
+            let rdata = reader.bytes(length)?.to_vec();
+            RecordData::Dnskey { algorithm: rdata[3], rdata }
+        }
+        _ => RecordData::Other,
+    };
+
+    if reader.position > end {
+        return Err("Record data exceeds its length".to_string());
+    }
+
+    reader.position = end;
+
+    Ok(data)
+}
+
+// Reads the header and answer section, authority and additional records are not needed
+pub fn decode_response(message: &[u8]) -> Result<DnsResponse, String> {
+    let mut reader = Reader { message, position: 0 };
+
+    let id = reader.u16()?;
+    let flags = reader.u16()?;
+    let question_count = reader.u16()?;
+    let answer_count = reader.u16()?;
+    reader.bytes(4)?;
+
+    if flags & 0x8000 == 0 {
+        return Err("Message is not a response".to_string());
+    }
+
+    for _ in 0..question_count {
+        reader.name()?;
+        reader.bytes(4)?;
+    }
+
+    let mut answers = Vec::with_capacity(answer_count as usize);
+
+    for _ in 0..answer_count {
+        let name = reader.name()?;
+        let record_type = reader.u16()?;
+        let _class = reader.u16()?;
+        let _ttl = reader.u32()?;
+        let length = reader.u16()? as usize;
+
+        answers.push(ResourceRecord { name, data: decode_record(&mut reader, record_type, length)? });
+    }
+
+    Ok(DnsResponse {
+        id,
+        authoritative: flags & 0x0400 != 0,
+        truncated: flags & 0x0200 != 0,
+        rcode: (flags & 0x000f) as u8,
+        answers,
+    })
+}
+
+// RFC 4034 appendix B
+pub fn key_tag(dnskey_rdata: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+
+    for (i, byte) in dnskey_rdata.iter().enumerate() {
+        sum += if i % 2 == 0 { (*byte as u32) << 8 } else { *byte as u32 };
+    }
+
+    sum += (sum >> 16) & 0xffff;
+
+    (sum & 0xffff) as u16
+}
+
+// RFC 4034 section 5.1.4, None for unsupported digest types
+pub fn ds_digest(owner: &str, dnskey_rdata: &[u8], digest_type: u8) -> Option<Vec<u8>> {
+    let mut data = encode_name(owner).ok()?;
+    data.extend(dnskey_rdata);
+
+    match digest_type {
+        1 => Some(Sha1::digest(&data).to_vec()),
+        2 => Some(Sha256::digest(&data).to_vec()),
+        4 => Some(Sha384::digest(&data).to_vec()),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DsRecord {
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub digest_type: u8,
+    pub digest: Vec<u8>,
+}
+
+// ds-rdata as written in the registry, e.g. "60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118".
+// The digest may be split by whitespace.
+pub fn parse_ds_rdata(ds_rdata: &str) -> Result<DsRecord, String> {
+    let mut parts = ds_rdata.split_whitespace();
+
+    let mut number = |field: &str| {
+        parts
+            .next()
+            .and_then(|part| part.parse::<u16>().ok())
+            .ok_or_else(|| format!("Invalid {} in DS record {:?}", field, ds_rdata))
+    };
+
+    let key_tag = number("key tag")?;
+    let algorithm = u8::try_from(number("algorithm")?).map_err(|_| format!("Invalid algorithm in DS record {:?}", ds_rdata))?;
+    let digest_type = u8::try_from(number("digest type")?).map_err(|_| format!("Invalid digest type in DS record {:?}", ds_rdata))?;
+
+    let digest = hex::decode(parts.collect::<String>()).map_err(|_| format!("Invalid digest in DS record {:?}", ds_rdata))?;
+
+    if digest.is_empty() {
+        return Err(format!("Missing digest in DS record {:?}", ds_rdata));
+    }
+
+    Ok(DsRecord { key_tag, algorithm, digest_type, digest })
+}
+
+impl DsRecord {
+    pub fn matches(&self, owner: &str, algorithm: u8, rdata: &[u8]) -> bool {
+        algorithm == self.algorithm
+            && key_tag(rdata) == self.key_tag
+            && ds_digest(owner, rdata, self.digest_type).is_some_and(|digest| digest == self.digest)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // RFC 4034 section 5.4
+    pub(crate) const EXAMPLE_DNSKEY: &str = "0100030501039e8a247418e318903b215a848acfd5f37f026bd4062db26c774c690968d5d56df8bfda91e6f36d9a279888f41333357c5e6029990d10fdf5663062a512763326980a615ddbf17a05ddfcce7e5fb3abcca05a31b0957452d4521e83870789063115bf97f6c308ccf57cdc9ce7fe10f6ed1bd0cc0660038c50dcdb0feb963c2f17";
+    pub(crate) const EXAMPLE_DS: &str = "60485 5 1 2BB183AF5F22588179A53B0A 98631FAD1A292118";
+
+    // Answers the question of a query, for stand-in servers in tests
+    pub(crate) fn encode_response(query: &[u8], authoritative: bool, rcode: u8, answers: &[(&str, u16, Vec<u8>)]) -> Vec<u8> {
+        let mut reader = Reader { message: query, position: 12 };
+        reader.name().unwrap();
+        reader.bytes(4).unwrap();
+
+        let flags = 0x8000 | if authoritative { 0x0400 } else { 0 } | rcode as u16;
+
+        let mut response = Vec::new();
+        for value in [u16::from_be_bytes([query[0], query[1]]), flags, 1, answers.len() as u16, 0, 0] {
+            response.extend(value.to_be_bytes());
+        }
+
+        response.extend(&query[12..reader.position]);
+
+        for (name, record_type, rdata) in answers {
+            response.extend(encode_name(name).unwrap());
+            response.extend(record_type.to_be_bytes());
+            response.extend(CLASS_IN.to_be_bytes());
+            response.extend(3600u32.to_be_bytes());
+            response.extend((rdata.len() as u16).to_be_bytes());
+            response.extend(rdata);
+        }
+
+        response
+    }
+
+    #[test]
+    fn test_query_and_response() {
+        let query = encode_query(0x1234, "Example.DN42.", TYPE_SOA, false).unwrap();
+
+        assert_eq!(&query[..4], &[0x12, 0x34, 0, 0]);
+        assert_eq!(&query[12..26], b"\x07example\x04dn42\x00");
+
+        let mut soa = encode_name("ns1.example.dn42").unwrap();
+        soa.extend(encode_name("hostmaster.example.dn42").unwrap());
+        soa.extend(2024010101u32.to_be_bytes());
+        soa.extend([0; 16]);
+
+        // Compression pointer back to the question name
+        let ns = [&b"\x03ns2"[..], &[0xc0, 12]].concat();
+
+        let response = encode_response(&query, true, RCODE_NOERROR, &[
+            ("example.dn42", TYPE_SOA, soa),
+            ("example.dn42", TYPE_NS, ns),
+            ("ns2.example.dn42", TYPE_A, vec![172, 20, 0, 53]),
+        ]);
+
+        let decoded = decode_response(&response).unwrap();
+
+        assert_eq!(decoded.id, 0x1234);
+        assert!(decoded.authoritative);
+        assert_eq!(decoded.rcode, RCODE_NOERROR);
+        assert_eq!(decoded.answers.iter().map(|a| a.data.clone()).collect::<Vec<_>>(), vec![
+            RecordData::Soa { mname: "ns1.example.dn42".to_string(), serial: 2024010101 },
+            RecordData::Ns("ns2.example.dn42".to_string()),
+            RecordData::A(Ipv4Addr::new(172, 20, 0, 53)),
+        ]);
+
+        assert!(decode_response(&query).is_err());
+        assert!(decode_response(&response[..response.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn test_ds_matches_dnskey() {
+        let rdata = hex::decode(EXAMPLE_DNSKEY).unwrap();
+        let ds = parse_ds_rdata(EXAMPLE_DS).unwrap();
+
+        assert_eq!(key_tag(&rdata), 60485);
+        assert!(ds.matches("dskey.example.com", 5, &rdata));
+        assert!(!ds.matches("other.example.com", 5, &rdata));
+
+        assert!(parse_ds_rdata("60485 5 1").is_err());
+        assert!(parse_ds_rdata("60485 5 1 XYZ").is_err());
+    }
+}
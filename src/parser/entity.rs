@@ -0,0 +1,280 @@
+use crate::model::asn::parse_asn;
+use crate::model::output::ROA;
+use crate::model::record::{Prefix, RecordFile};
+use crate::model::registry::{parse_object_prefix, Registry};
+use crate::parser::dns_lint::DnsLintProblem;
+use crate::parser::lookup::{reverse_delegation, RegistryObjectSummary, ReverseDelegation};
+use crate::parser::route::RouteDiagnostic;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DomainSummary {
+    pub domain: String,
+    pub nameservers: Vec<String>,
+    pub ds_rdata: Vec<String>,
+}
+
+// Everything the registry holds for an aut-num or a mntner
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct EntitySummary {
+    pub query: String,
+    // The aut-num or mntner object itself, if it exists
+    pub object: Option<RegistryObjectSummary>,
+    // Maintainers whose domains, inetnums and reverse delegations are listed
+    pub maintainers: Vec<String>,
+    pub routes: Vec<RegistryObjectSummary>,
+    pub roas: Vec<ROA>,
+    pub domains: Vec<DomainSummary>,
+    pub inetnums: Vec<RegistryObjectSummary>,
+    pub reverse_delegations: Vec<ReverseDelegation>,
+    // Problems found in the listed route objects while generating ROAs
+    pub problems: Vec<RouteDiagnostic>,
+    // Nameserver and glue problems of the listed domains and inetnums
+    pub dns_problems: Vec<DnsLintProblem>,
+}
+
+// Problems found while generating ROAs and DNS zones, filtered down to the entity's objects
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KnownProblems<'a> {
+    pub routes: &'a [RouteDiagnostic],
+    pub delegations: &'a [DnsLintProblem],
+}
+
+impl EntitySummary {
+    pub fn is_empty(&self) -> bool {
+        self.object.is_none() && self.routes.is_empty() && self.roas.is_empty() && self.domains.is_empty() && self.inetnums.is_empty()
+    }
+}
+
+fn of_types<'a>(records: Vec<&'a RecordFile>, object_types: &[&str]) -> Vec<&'a RecordFile> {
+    records.into_iter().filter(|record| object_types.contains(&record.object_type())).collect()
+}
+
+fn maintained<'a>(registry: &'a Registry, maintainers: &[String], object_types: &[&str]) -> Vec<&'a RecordFile> {
+    let mut seen = HashSet::new();
+
+    maintainers
+        .iter()
+        .flat_map(|maintainer| of_types(registry.inverse("mnt-by", maintainer), object_types))
+        .filter(|record| seen.insert(record.get_file_path()))
+        .collect()
+}
+
+// Problems name the full path of the file they came from, the same path the registry holds
+fn file_paths<'a>(records: impl Iterator<Item = &'a RecordFile>) -> HashSet<&'a Path> {
+    records.map(|record| record.get_file_path()).collect()
+}
+
+fn from_files(file: &str, files: &HashSet<&Path>) -> bool {
+    files.contains(Path::new(file))
+}
+
+fn summarise(
+    registry: &Registry,
+    query: String,
+    object: Option<&RecordFile>,
+    maintainers: Vec<String>,
+    routes: Vec<&RecordFile>,
+    roas: Vec<ROA>,
+    known: KnownProblems,
+) -> EntitySummary {
+    let inetnums = maintained(registry, &maintainers, &["inetnum", "inet6num"]);
+
+    let reverse_delegations = inetnums
+        .iter()
+        .filter(|record| record.get_attribute("nserver").is_some())
+        .filter_map(|record| parse_object_prefix(record).map(|prefix| reverse_delegation(&prefix, record)))
+        .collect();
+
+    let domain_records = maintained(registry, &maintainers, &["domain"]);
+
+    let route_files = file_paths(routes.iter().copied());
+    let problems = known.routes.iter().filter(|d| from_files(&d.file, &route_files)).cloned().collect();
+
+    let delegation_files = file_paths(domain_records.iter().chain(&inetnums).copied());
+    let dns_problems = known.delegations.iter().filter(|p| from_files(&p.file, &delegation_files)).cloned().collect();
+
+    let domains = domain_records
+        .into_iter()
+        .map(|record| DomainSummary {
+            domain: record.primary_key().to_string(),
+            nameservers: record.get_attributes("nserver").map(|ns| ns.to_string()).collect(),
+            ds_rdata: record.get_attributes("ds-rdata").map(|ds| ds.to_string()).collect(),
+        })
+        .collect();
+
+    EntitySummary {
+        query,
+        object: object.map(|record| record.into()),
+        maintainers,
+        routes: routes.into_iter().map(|record| record.into()).collect(),
+        roas,
+        domains,
+        inetnums: inetnums.into_iter().map(|record| record.into()).collect(),
+        reverse_delegations,
+        problems,
+        dns_problems,
+    }
+}
+
+// Routes and ROAs originated by the AS, the rest is taken from the aut-num's maintainers
+pub fn lookup_asn(registry: &Registry, roas: &[ROA], known: KnownProblems, asn: u32) -> EntitySummary {
+    let handle = format!("AS{}", asn);
+    let object = registry.get("aut-num", &handle);
+
+    let maintainers = object
+        .map(|record| record.get_attributes("mnt-by").map(|m| m.to_string()).collect())
+        .unwrap_or_default();
+
+    let routes = of_types(registry.inverse("origin", &handle), &["route", "route6"]);
+    let roas = roas.iter().filter(|roa| roa.asn == asn).cloned().collect();
+
+    summarise(registry, handle, object, maintainers, routes, roas, known)
+}
+
+// Routes maintained by the mntner and the ROAs generated from them
+pub fn lookup_mntner(registry: &Registry, roas: &[ROA], known: KnownProblems, handle: &str) -> EntitySummary {
+    let object = registry.get("mntner", handle);
+    let handle = object.map_or(handle, |record| record.primary_key()).to_string();
+
+    let routes = maintained(registry, std::slice::from_ref(&handle), &["route", "route6"]);
+
+    let authorised = routes
+        .iter()
+        .filter_map(|record| {
+            let prefix = parse_object_prefix(record)?;
+            let origins = record.get_attributes("origin").filter_map(parse_asn).collect::<Vec<_>>();
+
+            Some((prefix, origins))
+        })
+        .collect::<Vec<_>>();
+
+    let roas = roas
+        .iter()
+        .filter(|roa| {
+            let Ok(prefix) = Prefix::from_str(&roa.prefix) else {
+                return false;
+            };
+
+            // Compression and prefix normalisation change the ROA prefix, so any ROA of the route's origin
+            // inside the route, or covering it with a long enough max length, comes from or authorises it
+            authorised.iter().any(|(route, origins)| {
+                origins.contains(&roa.asn)
+                    && (route.contains(&prefix) || (prefix.contains(route) && roa.max_length >= route.prefix_len()))
+            })
+        })
+        .cloned()
+        .collect();
+
+    summarise(registry, handle.clone(), object, vec![handle], routes, roas, known)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::registry::tests::test_registry;
+    use crate::parser::dns_lint::DnsLintKind;
+    use crate::parser::route::DiagnosticAction;
+
+    fn roas() -> Vec<ROA> {
+        vec![
+            ROA { asn: 4242420000, prefix: "172.20.0.0/24".to_string(), max_length: 28, ..Default::default() },
+            ROA { asn: 4242420001, prefix: "172.20.0.0/24".to_string(), max_length: 24, ..Default::default() },
+            ROA { asn: 4242420000, prefix: "fd00::/48".to_string(), max_length: 64, ..Default::default() },
+        ]
+    }
+
+    fn diagnostics() -> Vec<RouteDiagnostic> {
+        vec![RouteDiagnostic {
+            file: "data/route/172.20.0.0_24-2".to_string(),
+            route: Some("172.20.0.0/24".to_string()),
+            action: DiagnosticAction::Flagged,
+            message: "Origin AS4242420001 has no aut-num object".to_string(),
+        }, RouteDiagnostic {
+            // Same file name as a route of AS4242420000, but not a file of the registry
+            file: "other/data/route/172.20.0.0_24".to_string(),
+            route: Some("172.20.0.0/24".to_string()),
+            action: DiagnosticAction::Rejected,
+            message: "Duplicate route".to_string(),
+        }]
+    }
+
+    fn lint() -> Vec<DnsLintProblem> {
+        vec![DnsLintProblem {
+            file: "data/domain/example.dn42".to_string(),
+            zone: "example.dn42".to_string(),
+            nameserver: "ns1.example.dn42".to_string(),
+            kind: DnsLintKind::ConflictingGlue,
+            message: "Glue 172.20.0.53 for ns1.example.dn42 conflicts with other.dn42 (172.20.0.54)".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_lookup_asn() {
+        let registry = test_registry();
+        let (routes, delegations) = (diagnostics(), lint());
+        let known = KnownProblems { routes: &routes, delegations: &delegations };
+
+        let summary = lookup_asn(&registry, &roas(), known, 4242420000);
+
+        assert_eq!(summary.query, "AS4242420000");
+        assert_eq!(summary.object.unwrap().object_type, "aut-num");
+        assert_eq!(summary.maintainers, vec!["EXAMPLE-MNT"]);
+        assert_eq!(summary.routes.iter().map(|r| r.primary_key.as_str()).collect::<Vec<_>>(), vec!["172.20.0.0/24"]);
+        // ROAs from other sources count as well
+        assert_eq!(summary.roas.len(), 2);
+        assert_eq!(summary.domains, vec![DomainSummary {
+            domain: "example.dn42".to_string(),
+            nameservers: vec!["ns1.example.dn42 172.20.0.53".to_string()],
+            ds_rdata: Vec::new(),
+        }]);
+        assert_eq!(summary.inetnums.iter().map(|r| r.primary_key.as_str()).collect::<Vec<_>>(), vec!["172.20.0.0/24"]);
+        assert!(summary.reverse_delegations.is_empty());
+        assert!(summary.problems.is_empty());
+        assert_eq!(summary.dns_problems.len(), 1);
+
+        let summary = lookup_asn(&registry, &roas(), known, 4242420001);
+        assert!(summary.object.is_none());
+        assert_eq!(summary.routes.len(), 1);
+        assert_eq!(summary.problems.len(), 1);
+
+        assert!(lookup_asn(&registry, &roas(), known, 4242429999).is_empty());
+    }
+
+    #[test]
+    fn test_lookup_mntner() {
+        let registry = test_registry();
+        let (routes, delegations) = (diagnostics(), lint());
+        let known = KnownProblems { routes: &routes, delegations: &delegations };
+
+        let summary = lookup_mntner(&registry, &roas(), known, "example-mnt");
+
+        assert_eq!(summary.query, "EXAMPLE-MNT");
+        assert_eq!(summary.routes.len(), 1);
+        // Only ROAs generated from the mntner's own route objects
+        assert_eq!(summary.roas.iter().map(|r| (r.asn, r.prefix.as_str())).collect::<Vec<_>>(), vec![(4242420000, "172.20.0.0/24")]);
+        assert_eq!(summary.domains.len(), 1);
+        assert!(summary.problems.is_empty());
+
+        // ROAs changed by compression are still attributed to the route they come from
+        let compressed = vec![
+            ROA { asn: 4242420000, prefix: "172.20.0.0/25".to_string(), max_length: 28, ..Default::default() },
+            ROA { asn: 4242420000, prefix: "172.20.0.0/23".to_string(), max_length: 24, ..Default::default() },
+            ROA { asn: 4242420000, prefix: "172.20.0.0/22".to_string(), max_length: 22, ..Default::default() },
+            ROA { asn: 4242420002, prefix: "172.20.0.0/24".to_string(), max_length: 24, ..Default::default() },
+        ];
+        let summary = lookup_mntner(&registry, &compressed, known, "EXAMPLE-MNT");
+        assert_eq!(summary.roas.iter().map(|r| r.prefix.as_str()).collect::<Vec<_>>(), vec!["172.20.0.0/25", "172.20.0.0/23"]);
+
+        let summary = lookup_mntner(&registry, &roas(), known, "OTHER-MNT");
+        assert!(summary.object.is_none());
+        assert_eq!(summary.roas.iter().map(|r| r.asn).collect::<Vec<_>>(), vec![4242420001]);
+        assert_eq!(summary.problems.len(), 1);
+        assert!(summary.dns_problems.is_empty());
+
+        assert!(lookup_mntner(&registry, &roas(), known, "NOBODY-MNT").is_empty());
+    }
+}
@@ -0,0 +1,310 @@
+use crate::model::asn::parse_asn;
+use crate::model::record::Prefix;
+use crate::model::registry::{parse_object_prefix, Registry};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+// A prefix together with the range of lengths a filter accepts for it
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FilterPrefix {
+    pub prefix: Prefix,
+    pub min_length: u8,
+    pub max_length: u8,
+}
+
+impl FilterPrefix {
+    pub fn is_exact(&self) -> bool {
+        self.min_length == self.prefix.prefix_len() && self.max_length == self.prefix.prefix_len()
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SetExpansion {
+    pub name: String,
+    pub asns: Vec<u32>,
+    // IPv4 before IPv6, each in address order
+    pub prefixes: Vec<FilterPrefix>,
+    // Loops, unknown members and truncated branches, the expansion is still usable
+    pub warnings: Vec<String>,
+}
+
+// Parses an RPSL prefix with an optional range operator (RFC 2622 section 2),
+// e.g. 172.20.0.0/16^+, 172.20.0.0/16^-, 172.20.0.0/16^24 or 172.20.0.0/16^24-28
+fn parse_range_prefix(value: &str) -> Option<FilterPrefix> {
+    let (prefix, operator) = match value.split_once('^') {
+        Some((prefix, operator)) => (prefix, Some(operator)),
+        None => (value, None),
+    };
+
+    let prefix = Prefix::from_str(prefix).ok()?;
+    let prefix = prefix.with_prefix_len(prefix.prefix_len());
+    let len = prefix.prefix_len();
+    let max = if prefix.is_ipv4() { 32 } else { 128 };
+
+    let (min_length, max_length) = match operator {
+        None => (len, len),
+        Some("+") => (len, max),
+        Some("-") => (len.checked_add(1)?, max),
+        Some(range) => match range.split_once('-') {
+            Some((low, high)) => (low.parse().ok()?, high.parse().ok()?),
+            None => {
+                let exact = range.parse().ok()?;
+                (exact, exact)
+            }
+        },
+    };
+
+    if min_length < len || min_length > max_length || max_length > max {
+        return None;
+    }
+
+    Some(FilterPrefix { prefix, min_length, max_length })
+}
+
+struct Expander<'a> {
+    registry: &'a Registry,
+    max_depth: usize,
+    // Sets on the current expansion path, for loop detection
+    path: Vec<String>,
+    // Set -> depth it was expanded at, a set reached again at a shallower depth is expanded again
+    // since members cut off by the depth limit may now be within it
+    expanded: HashMap<String, usize>,
+    // Sets cut off by the depth limit, reported unless they were expanded on another path
+    truncated: Vec<String>,
+    asns: BTreeSet<u32>,
+    prefixes: BTreeSet<FilterPrefix>,
+    warnings: Vec<String>,
+}
+
+impl Expander<'_> {
+    fn expand_member(&mut self, member: &str, depth: usize) {
+        if let Some(asn) = parse_asn(member) {
+            self.asns.insert(asn);
+        } else if let Some(prefix) = parse_range_prefix(member) {
+            self.prefixes.insert(prefix);
+        } else {
+            self.expand_set(member, depth + 1);
+        }
+    }
+
+    fn expand_set(&mut self, name: &str, depth: usize) {
+        let key = name.to_uppercase();
+
+        if let Some(start) = self.path.iter().position(|n| *n == key) {
+            let mut cycle = self.path[start..].to_vec();
+            cycle.push(key);
+            self.warnings.push(format!("Loop detected: {}", cycle.join(" -> ")));
+            return;
+        }
+
+        if self.expanded.get(&key).is_some_and(|expanded_at| *expanded_at <= depth) {
+            return;
+        }
+
+        if depth > self.max_depth {
+            if !self.truncated.contains(&key) {
+                self.truncated.push(key);
+            }
+
+            return;
+        }
+
+        let record = match self.registry.get("as-set", name).or_else(|| self.registry.get("route-set", name)) {
+            Some(record) => record,
+            None => {
+                let parent = self.path.last().map(|p| format!(" (member of {})", p)).unwrap_or_default();
+                self.warnings.push(format!("Unknown set {}{}", key, parent));
+                return;
+            }
+        };
+
+        self.path.push(key.clone());
+
+        let members = record
+            .get_attributes("members")
+            .chain(record.get_attributes("mp-members"))
+            .flat_map(|value| value.split(|c: char| c == ',' || c.is_whitespace()))
+            .filter(|member| !member.is_empty())
+            .map(|member| member.to_string())
+            .collect::<Vec<_>>();
+
+        for member in members {
+            self.expand_member(&member, depth);
+        }
+
+        self.path.pop();
+        self.expanded.insert(key, depth);
+    }
+
+    // Route objects originated by the collected ASNs, accepting up to their max-length
+    fn collect_routes(&mut self) {
+        for asn in &self.asns {
+            for record in self.registry.inverse("origin", &format!("AS{}", asn)) {
+                if !matches!(record.object_type(), "route" | "route6") {
+                    continue;
+                }
+
+                let Some(prefix) = parse_object_prefix(record) else {
+                    continue;
+                };
+
+                let len = prefix.prefix_len();
+                let max = if prefix.is_ipv4() { 32 } else { 128 };
+
+                let max_length = record
+                    .get_attribute("max-length")
+                    .and_then(|m| m.parse::<u8>().ok())
+                    .filter(|m| (len..=max).contains(m))
+                    .unwrap_or(len);
+
+                self.prefixes.insert(FilterPrefix { prefix, min_length: len, max_length });
+            }
+        }
+    }
+}
+
+// Expands an as-set, route-set or single AS number into the ASNs it contains and the
+// prefixes they may announce. Errors only when the requested set itself does not exist.
+pub fn expand_set(registry: &Registry, name: &str, max_depth: usize) -> Result<SetExpansion, String> {
+    let mut expander = Expander {
+        registry,
+        max_depth,
+        path: Vec::new(),
+        expanded: HashMap::new(),
+        truncated: Vec::new(),
+        asns: BTreeSet::new(),
+        prefixes: BTreeSet::new(),
+        warnings: Vec::new(),
+    };
+
+    if let Some(asn) = parse_asn(name) {
+        expander.asns.insert(asn);
+    } else if registry.get("as-set", name).is_some() || registry.get("route-set", name).is_some() {
+        expander.expand_set(name, 0);
+    } else {
+        return Err(format!("No as-set or route-set named {}", name));
+    }
+
+    expander.collect_routes();
+
+    for key in &expander.truncated {
+        if !expander.expanded.contains_key(key) {
+            expander.warnings.push(format!("{} not expanded, depth limit of {} reached", key, max_depth));
+        }
+    }
+
+    Ok(SetExpansion {
+        name: name.to_uppercase(),
+        asns: expander.asns.into_iter().collect(),
+        prefixes: expander.prefixes.into_iter().collect(),
+        warnings: expander.warnings,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::model::registry::tests::record;
+
+    pub(crate) fn filter_registry() -> Registry {
+        Registry::new(vec![
+            record("data/as-set/AS-EXAMPLE", "as-set: AS-EXAMPLE\nmembers: AS4242420000, AS-CUSTOMERS\nmembers: AS-MISSING\n"),
+            record("data/as-set/AS-CUSTOMERS", "as-set: AS-CUSTOMERS\nmembers: AS4242420001 AS-EXAMPLE AS-DEEP\n"),
+            record("data/as-set/AS-DEEP", "as-set: AS-DEEP\nmembers: AS4242420002\n"),
+            record("data/route-set/RS-EXAMPLE", "route-set: RS-EXAMPLE\nmembers: 172.20.0.0/16^24-28\nmp-members: fd42::/32^+, AS4242420001\n"),
+            record("data/route/172.20.0.0_24", "route: 172.20.0.0/24\norigin: AS4242420000\nmax-length: 28\n"),
+            record("data/route6/fd00::_48", "route6: fd00::/48\norigin: AS4242420000\n"),
+            record("data/route/172.21.0.0_24", "route: 172.21.0.0/24\norigin: AS4242420001\n"),
+            record("data/route/172.22.0.0_24", "route: 172.22.0.0/24\norigin: AS4242420002\nmax-length: 99\n"),
+        ])
+    }
+
+    fn prefixes(expansion: &SetExpansion) -> Vec<String> {
+        expansion
+            .prefixes
+            .iter()
+            .map(|p| format!("{} {}-{}", p.prefix, p.min_length, p.max_length))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_range_prefix() {
+        let parse = |s| parse_range_prefix(s).map(|p| (p.prefix.to_string(), p.min_length, p.max_length));
+
+        assert_eq!(parse("172.20.0.0/16"), Some(("172.20.0.0/16".to_string(), 16, 16)));
+        assert_eq!(parse("172.20.0.0/16^+"), Some(("172.20.0.0/16".to_string(), 16, 32)));
+        assert_eq!(parse("172.20.0.0/16^-"), Some(("172.20.0.0/16".to_string(), 17, 32)));
+        assert_eq!(parse("172.20.0.0/16^24"), Some(("172.20.0.0/16".to_string(), 24, 24)));
+        assert_eq!(parse("fd42::1/32^48-64"), Some(("fd42::/32".to_string(), 48, 64)));
+
+        assert_eq!(parse("172.20.0.0/16^8"), None);
+        assert_eq!(parse("172.20.0.0/16^28-24"), None);
+        assert_eq!(parse("172.20.0.0/16^33"), None);
+        assert_eq!(parse("AS-FOO"), None);
+    }
+
+    #[test]
+    fn test_expand_as_set_with_loop_and_unknown_member() {
+        let expansion = expand_set(&filter_registry(), "as-example", 10).unwrap();
+
+        assert_eq!(expansion.name, "AS-EXAMPLE");
+        assert_eq!(expansion.asns, vec![4242420000, 4242420001, 4242420002]);
+        assert_eq!(prefixes(&expansion), vec![
+            "172.20.0.0/24 24-28",
+            "172.21.0.0/24 24-24",
+            "172.22.0.0/24 24-24",
+            "fd00::/48 48-48",
+        ]);
+        assert_eq!(expansion.warnings, vec![
+            "Loop detected: AS-EXAMPLE -> AS-CUSTOMERS -> AS-EXAMPLE",
+            "Unknown set AS-MISSING (member of AS-EXAMPLE)",
+        ]);
+    }
+
+    #[test]
+    fn test_expand_depth_limit() {
+        let expansion = expand_set(&filter_registry(), "AS-EXAMPLE", 1).unwrap();
+
+        assert_eq!(expansion.asns, vec![4242420000, 4242420001]);
+        assert!(expansion.warnings.contains(&"AS-DEEP not expanded, depth limit of 1 reached".to_string()));
+    }
+
+    #[test]
+    fn test_expand_set_again_at_shallower_depth() {
+        // AS-MID is first reached through AS-A and AS-B, where AS-LEAF is beyond the limit
+        let registry = Registry::new(vec![
+            record("data/as-set/AS-TOP", "as-set: AS-TOP\nmembers: AS-A, AS-MID\n"),
+            record("data/as-set/AS-A", "as-set: AS-A\nmembers: AS-B\n"),
+            record("data/as-set/AS-B", "as-set: AS-B\nmembers: AS-MID\n"),
+            record("data/as-set/AS-MID", "as-set: AS-MID\nmembers: AS4242420000, AS-LEAF\n"),
+            record("data/as-set/AS-LEAF", "as-set: AS-LEAF\nmembers: AS4242420001\n"),
+        ]);
+
+        let expansion = expand_set(&registry, "AS-TOP", 3).unwrap();
+
+        assert_eq!(expansion.asns, vec![4242420000, 4242420001]);
+        assert!(expansion.warnings.is_empty(), "{:?}", expansion.warnings);
+
+        let expansion = expand_set(&registry, "AS-TOP", 1).unwrap();
+
+        assert_eq!(expansion.asns, vec![4242420000]);
+        assert_eq!(expansion.warnings, vec![
+            "AS-B not expanded, depth limit of 1 reached",
+            "AS-LEAF not expanded, depth limit of 1 reached",
+        ]);
+    }
+
+    #[test]
+    fn test_expand_route_set_and_asn() {
+        let expansion = expand_set(&filter_registry(), "RS-EXAMPLE", 10).unwrap();
+
+        assert_eq!(expansion.asns, vec![4242420001]);
+        assert_eq!(prefixes(&expansion), vec!["172.20.0.0/16 24-28", "172.21.0.0/24 24-24", "fd42::/32 32-128"]);
+
+        let expansion = expand_set(&filter_registry(), "AS4242420000", 10).unwrap();
+        assert_eq!(prefixes(&expansion), vec!["172.20.0.0/24 24-28", "fd00::/48 48-48"]);
+
+        assert!(expand_set(&filter_registry(), "AS-NOPE", 10).is_err());
+    }
+}
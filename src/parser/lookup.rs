@@ -0,0 +1,160 @@
+use crate::model::output::ROA;
+use crate::model::record::{Prefix, RecordFile};
+use crate::model::registry::Registry;
+use crate::model::trie::PrefixTrie;
+use crate::parser::dns::generate_reverse_record_name;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RegistryObjectSummary {
+    pub object_type: String,
+    pub primary_key: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl From<&RecordFile> for RegistryObjectSummary {
+    fn from(record: &RecordFile) -> Self {
+        RegistryObjectSummary {
+            object_type: record.object_type().to_string(),
+            primary_key: record.primary_key().to_string(),
+            attributes: record.attributes().to_vec(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ApplicableRoa {
+    #[serde(flatten)]
+    pub roa: ROA,
+    // Whether the query prefix is within the ROA max length, i.e. could be announced as valid
+    pub length_matches: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReverseDelegation {
+    pub prefix: String,
+    // None for IPv6 prefixes not aligned to a nibble boundary
+    pub zone: Option<String>,
+    pub nameservers: Vec<String>,
+    pub ds_rdata: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PrefixLookup {
+    pub query: String,
+    pub most_specific_inetnum: Option<RegistryObjectSummary>,
+    // Most specific first
+    pub covering_inetnums: Vec<RegistryObjectSummary>,
+    // Least specific first
+    pub more_specific_routes: Vec<RegistryObjectSummary>,
+    pub roas: Vec<ApplicableRoa>,
+    pub reverse_delegation: Option<ReverseDelegation>,
+}
+
+// ROAs whose prefix covers the query, most specific first
+pub fn covering_roas<'a>(index: &'a PrefixTrie<Vec<ROA>>, prefix: &Prefix) -> Vec<&'a ROA> {
+    index
+        .covering(prefix)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .flat_map(|(_, roas)| {
+            let mut roas = roas.iter().collect::<Vec<_>>();
+            roas.sort_by_key(|roa| roa.asn);
+            roas
+        })
+        .collect()
+}
+
+// The reverse zone delegated by an inetnum or inet6num carrying nserver attributes
+pub fn reverse_delegation(delegated: &Prefix, record: &RecordFile) -> ReverseDelegation {
+    ReverseDelegation {
+        prefix: delegated.to_string(),
+        zone: generate_reverse_record_name(delegated).map(|name| name.to_string()),
+        nameservers: record.get_attributes("nserver").map(|ns| ns.to_string()).collect(),
+        ds_rdata: record.get_attributes("ds-rdata").map(|ds| ds.to_string()).collect(),
+    }
+}
+
+pub fn lookup_prefix(registry: &Registry, roas: &PrefixTrie<Vec<ROA>>, prefix: &Prefix) -> PrefixLookup {
+    let covering_inetnums = registry.covering_inetnums(prefix);
+
+    let reverse_delegation = registry.reverse_delegation(prefix).map(|(delegated, record)| reverse_delegation(&delegated, record));
+
+    PrefixLookup {
+        query: prefix.to_string(),
+        most_specific_inetnum: covering_inetnums.first().map(|record| (*record).into()),
+        covering_inetnums: covering_inetnums.iter().map(|record| (*record).into()).collect(),
+        more_specific_routes: registry.more_specific_routes(prefix).into_iter().map(|record| record.into()).collect(),
+        roas: covering_roas(roas, prefix)
+            .into_iter()
+            .map(|roa| ApplicableRoa {
+                roa: roa.clone(),
+                length_matches: prefix.prefix_len() <= roa.max_length,
+            })
+            .collect(),
+        reverse_delegation,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::registry::parse_query_prefix;
+    use crate::model::registry::tests::test_registry;
+    use crate::parser::route::build_roa_index;
+
+    fn roas() -> PrefixTrie<Vec<ROA>> {
+        build_roa_index(&[
+            ROA { asn: 4242420000, prefix: "172.20.0.0/24".to_string(), max_length: 28, ..Default::default() },
+            ROA { asn: 4242420002, prefix: "172.20.0.0/16".to_string(), max_length: 16, ..Default::default() },
+            ROA { asn: 4242420003, prefix: "172.21.0.0/16".to_string(), max_length: 24, ..Default::default() },
+        ])
+    }
+
+    #[test]
+    fn test_lookup_address() {
+        let registry = test_registry();
+        let prefix = parse_query_prefix("172.20.0.1").unwrap();
+
+        let lookup = lookup_prefix(&registry, &roas(), &prefix);
+
+        assert_eq!(lookup.query, "172.20.0.1/32");
+        assert_eq!(lookup.most_specific_inetnum.unwrap().primary_key, "172.20.0.0/24");
+        assert_eq!(lookup.covering_inetnums.len(), 2);
+        assert!(lookup.more_specific_routes.is_empty());
+
+        let roas = lookup.roas.iter().map(|r| (r.roa.asn, r.length_matches)).collect::<Vec<_>>();
+        assert_eq!(roas, vec![(4242420000, false), (4242420002, false)]);
+
+        let delegation = lookup.reverse_delegation.unwrap();
+        assert_eq!(delegation.prefix, "172.20.0.0/14");
+        // Not octet aligned, delegated RFC 2317 style like the generated reverse zones
+        assert_eq!(delegation.zone.as_deref(), Some("20/14.172.in-addr.arpa"));
+        assert_eq!(delegation.nameservers, vec!["ns1.example.dn42"]);
+    }
+
+    #[test]
+    fn test_lookup_prefix() {
+        let registry = test_registry();
+        let prefix = parse_query_prefix("172.20.0.0/24").unwrap();
+
+        let lookup = lookup_prefix(&registry, &roas(), &prefix);
+
+        assert_eq!(lookup.more_specific_routes.len(), 2);
+        assert_eq!(lookup.roas[0].roa.asn, 4242420000);
+        assert!(lookup.roas[0].length_matches);
+    }
+
+    #[test]
+    fn test_lookup_unknown() {
+        let registry = test_registry();
+        let prefix = parse_query_prefix("fd00::/48").unwrap();
+
+        let lookup = lookup_prefix(&registry, &roas(), &prefix);
+
+        assert!(lookup.most_specific_inetnum.is_none());
+        assert!(lookup.roas.is_empty());
+        assert!(lookup.reverse_delegation.is_none());
+    }
+}
@@ -1,2 +1,10 @@
 pub mod dns;
-pub mod route;
\ No newline at end of file
+pub mod dns_lint;
+pub mod dns_wire;
+pub mod route;
+pub mod lookup;
+pub mod aggregate;
+pub mod aspa;
+pub mod filter;
+pub mod source;
+pub mod entity;
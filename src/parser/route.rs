@@ -1,10 +1,92 @@
-use crate::model::output::{Metadata, RpkiClientOutput};
+use crate::model::asn::parse_asn;
+use crate::model::output::{Metadata, RpkiClientOutput, ROA};
 use crate::model::record::{Prefix, RecordField, RecordFile};
+use crate::model::trie::PrefixTrie;
+use serde::Serialize;
+use std::collections::HashSet;
 use std::str::FromStr;
 use tracing::{info, warn};
 
-pub fn get_parsed_roa_routes(record_files: &[RecordFile]) -> RpkiClientOutput {
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticAction {
+    // Nothing from the record (or the named origin) made it into the ROA set
+    Rejected,
+    // The route was accepted after normalising it
+    Corrected,
+    // The route was accepted as is but looks wrong
+    Flagged,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RouteDiagnostic {
+    pub file: String,
+    pub route: Option<String>,
+    pub action: DiagnosticAction,
+    pub message: String,
+}
+
+impl RouteDiagnostic {
+    fn new(record_file: &RecordFile, route: Option<&str>, action: DiagnosticAction, message: String) -> Self {
+        RouteDiagnostic {
+            file: record_file.get_file_path().display().to_string(),
+            route: route.map(|r| r.to_string()),
+            action,
+            message,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RouteOptions {
+    pub normalise_prefixes: bool,
+    // AS numbers with an aut-num object, None skips the origin check
+    pub registered_origins: Option<HashSet<u32>>,
+    pub exclude_unregistered_origins: bool,
+}
+
+// Validates the prefix and max-length of a route object, returning the prefix to publish
+fn validate_route(route_str: &str, max_length_strs: Option<&Vec<String>>, normalise: bool) -> Result<(Prefix, u8, Option<String>), String> {
+    let prefix = Prefix::from_str(route_str).map_err(|e| format!("Invalid prefix {:?}: {}", route_str, e))?;
+    let max_prefix_len = if prefix.is_ipv4() { 32 } else { 128 };
+
+    let max_length = match max_length_strs.map(|m| m.as_slice()) {
+        None => prefix.prefix_len(),
+        Some([max_length_str]) => max_length_str
+            .parse::<u8>()
+            .map_err(|_| format!("Invalid max-length {:?}", max_length_str))?,
+        Some(_) => return Err("Multiple max-length fields".to_string()),
+    };
+
+    if max_length < prefix.prefix_len() {
+        return Err(format!("max-length {} is shorter than the prefix length {}", max_length, prefix.prefix_len()));
+    }
+
+    if max_length > max_prefix_len {
+        return Err(format!("max-length {} exceeds {}", max_length, max_prefix_len));
+    }
+
+    let network = prefix.with_prefix_len(prefix.prefix_len());
+
+    if network == prefix {
+        return Ok((prefix, max_length, None));
+    }
+
+    if !normalise {
+        return Err(format!("Prefix {} has host bits set, the network address is {}", prefix, network));
+    }
+
+    let correction = format!("Prefix {} has host bits set, normalised to {}", prefix, network);
+
+    Ok((network, max_length, Some(correction)))
+}
+
+// Route objects that fail validation are left out and reported, see RouteOptions for
+// the checks that can either correct, flag or reject a route
+pub fn get_parsed_roa_routes(record_files: &[RecordFile], options: &RouteOptions) -> (RpkiClientOutput, Vec<RouteDiagnostic>) {
     let mut roas = Vec::with_capacity(record_files.len());
+    let mut diagnostics = Vec::new();
+
     for record_file in record_files {
         let asn_strs = record_file.get_field(RecordField::Origin);
         let route_strs = record_file.get_field(RecordField::Route);
@@ -17,64 +99,201 @@ pub fn get_parsed_roa_routes(record_files: &[RecordFile]) -> RpkiClientOutput {
             _ => None,
         };
 
-        if let (Some(asn_strs), Some(route_strs)) =
-            (asn_strs, route_str)
-        {
-            if route_strs.len() != 1 {
-                warn!("Multiple route fields in record: {:?}", record_file.get_file_path());
+        let (asn_strs, route_strs) = match (asn_strs, route_str) {
+            (Some(asn_strs), Some(route_strs)) => (asn_strs, route_strs),
+            _ => {
+                diagnostics.push(RouteDiagnostic::new(record_file, None, DiagnosticAction::Rejected, "Missing required fields".to_string()));
+                continue;
+            }
+        };
+
+        if route_strs.len() != 1 {
+            diagnostics.push(RouteDiagnostic::new(record_file, None, DiagnosticAction::Rejected, "Multiple route fields".to_string()));
+            continue;
+        }
+
+        let route_str = &route_strs[0];
+
+        let (prefix, max_length) = match validate_route(route_str, max_length_strs, options.normalise_prefixes) {
+            Ok((prefix, max_length, correction)) => {
+                if let Some(message) = correction {
+                    diagnostics.push(RouteDiagnostic::new(record_file, Some(route_str), DiagnosticAction::Corrected, message));
+                }
+
+                (prefix, max_length)
+            }
+            Err(message) => {
+                diagnostics.push(RouteDiagnostic::new(record_file, Some(route_str), DiagnosticAction::Rejected, message));
                 continue;
             }
+        };
+
+        for asn_str in asn_strs {
+            let Some(asn) = parse_asn(asn_str) else {
+                diagnostics.push(RouteDiagnostic::new(record_file, Some(route_str), DiagnosticAction::Rejected, format!("Invalid ASN {:?}", asn_str)));
+                continue;
+            };
+
+            if let Some(registered_origins) = &options.registered_origins
+                && !registered_origins.contains(&asn)
+            {
+                let message = format!("Origin AS{} has no aut-num object", asn);
 
-            let route_str = &route_strs[0];
-
-            if let Ok(prefix) = Prefix::from_str(route_str) {
-                let max_length = match max_length_strs {
-                    Some(max_length_strs) => {
-                        if max_length_strs.len() != 1 {
-                            warn!("Multiple max-length fields in record: {:?}", record_file.get_file_path());
-                            continue;
-                        }
-                        let max_length_str = &max_length_strs[0];
-
-                        match max_length_str.parse::<u8>() {
-                            Ok(length) => length,
-                            Err(_) => continue,
-                        }
-                    }
-                    None => prefix.prefix_len(),
-                };
-
-                for asn_str in asn_strs {
-                    if let Some((_, number_part)) = asn_str.split_once("AS") {
-                        if let Ok(asn) = number_part.parse::<u32>() {
-                            let roa = crate::model::output::ROA {
-                                asn,
-                                prefix: route_str.to_string(),
-                                max_length,
-                            };
-                            roas.push(roa);
-                        } else {
-                            warn!("Invalid ASN {:?} in record: {:?}", asn_str, record_file.get_file_path());
-                        }
-                    } else {
-                        warn!("Invalid ASN {:?} in record: {:?}", asn_str, record_file.get_file_path());
-                    }
+                if options.exclude_unregistered_origins {
+                    diagnostics.push(RouteDiagnostic::new(record_file, Some(route_str), DiagnosticAction::Rejected, message));
+                    continue;
                 }
-            } else {
-                warn!("Invalid prefix {:?} in record: {:?}", route_str, record_file.get_file_path());
+
+                diagnostics.push(RouteDiagnostic::new(record_file, Some(route_str), DiagnosticAction::Flagged, message));
             }
-        } else {
-            warn!("Missing required fields in record: {:?}", record_file.get_file_path());
+
+            roas.push(ROA {
+                asn,
+                prefix: prefix.to_string(),
+                max_length,
+                ..Default::default()
+            });
         }
     }
 
+    for diagnostic in &diagnostics {
+        warn!("{:?} route in {}: {}", diagnostic.action, diagnostic.file, diagnostic.message);
+    }
+
     info!("Generated {} ROA entries.", roas.len());
 
+    let now = chrono::Utc::now();
+
     let metadata = Metadata {
-        build_time: chrono::Utc::now().to_rfc3339(),
-        counts: roas.len() as u64,
-        roas: roas.len() as u64,
+        build_time: now.to_rfc3339(),
+        generated: now.timestamp(),
+        ..Default::default()
     };
 
-    RpkiClientOutput { metadata, roas }
-}
\ No newline at end of file
+    let mut output = RpkiClientOutput { metadata, roas, aspas: None };
+    output.update_counts();
+
+    (output, diagnostics)
+}
+
+// ROAs grouped by their prefix for covering lookups
+pub fn build_roa_index(roas: &[ROA]) -> PrefixTrie<Vec<ROA>> {
+    let mut index: PrefixTrie<Vec<ROA>> = PrefixTrie::new();
+
+    for roa in roas {
+        let Ok(prefix) = Prefix::from_str(&roa.prefix) else {
+            continue;
+        };
+
+        match index.get_mut(&prefix) {
+            Some(roas) => roas.push(roa.clone()),
+            None => {
+                index.insert(&prefix, vec![roa.clone()]);
+            }
+        }
+    }
+
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::registry::tests::record;
+
+    fn route(max_length: Option<&str>, prefix: &str) -> RecordFile {
+        let mut content = format!("route: {}\norigin: AS4242420000\n", prefix);
+        if let Some(max_length) = max_length {
+            content.push_str(&format!("max-length: {}\n", max_length));
+        }
+        record(&format!("data/route/{}", prefix.replace('/', "_")), &content)
+    }
+
+    fn messages(diagnostics: &[RouteDiagnostic]) -> Vec<(DiagnosticAction, &str)> {
+        diagnostics.iter().map(|d| (d.action, d.message.as_str())).collect()
+    }
+
+    #[test]
+    fn test_invalid_max_length_is_rejected() {
+        let records = vec![
+            route(Some("24"), "172.20.0.0/24"),
+            route(Some("abc"), "172.20.1.0/24"),
+            route(Some("16"), "172.20.2.0/24"),
+            route(Some("33"), "172.20.3.0/24"),
+            route(Some("129"), "fd42::/48"),
+            route(None, "fd42:1::/48"),
+        ];
+
+        let (output, diagnostics) = get_parsed_roa_routes(&records, &RouteOptions::default());
+
+        let roas = output.roas.iter().map(|r| (r.prefix.as_str(), r.max_length)).collect::<Vec<_>>();
+        assert_eq!(roas, vec![("172.20.0.0/24", 24), ("fd42:1::/48", 48)]);
+
+        assert_eq!(messages(&diagnostics), vec![
+            (DiagnosticAction::Rejected, "Invalid max-length \"abc\""),
+            (DiagnosticAction::Rejected, "max-length 16 is shorter than the prefix length 24"),
+            (DiagnosticAction::Rejected, "max-length 33 exceeds 32"),
+            (DiagnosticAction::Rejected, "max-length 129 exceeds 128"),
+        ]);
+        assert_eq!(diagnostics[0].file, "data/route/172.20.1.0_24");
+        assert_eq!(diagnostics[0].route.as_deref(), Some("172.20.1.0/24"));
+    }
+
+    #[test]
+    fn test_host_bits_rejected_or_normalised() {
+        let records = vec![route(Some("28"), "172.20.1.5/24")];
+
+        let (output, diagnostics) = get_parsed_roa_routes(&records, &RouteOptions::default());
+        assert!(output.roas.is_empty());
+        assert_eq!(messages(&diagnostics), vec![
+            (DiagnosticAction::Rejected, "Prefix 172.20.1.5/24 has host bits set, the network address is 172.20.1.0/24"),
+        ]);
+
+        let (output, diagnostics) = get_parsed_roa_routes(&records, &RouteOptions { normalise_prefixes: true, ..Default::default() });
+        assert_eq!(output.roas, vec![ROA { asn: 4242420000, prefix: "172.20.1.0/24".to_string(), max_length: 28, ..Default::default() }]);
+        assert_eq!(messages(&diagnostics), vec![
+            (DiagnosticAction::Corrected, "Prefix 172.20.1.5/24 has host bits set, normalised to 172.20.1.0/24"),
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_origin_only_drops_that_origin() {
+        let records = vec![record("data/route/172.20.0.0_24", "route: 172.20.0.0/24\norigin: AS4242420000\norigin: AS-FOO\n")];
+
+        let (output, diagnostics) = get_parsed_roa_routes(&records, &RouteOptions::default());
+
+        assert_eq!(output.roas.len(), 1);
+        assert_eq!(messages(&diagnostics), vec![(DiagnosticAction::Rejected, "Invalid ASN \"AS-FOO\"")]);
+    }
+
+    #[test]
+    fn test_origin_formats() {
+        let records = vec![record("data/route/172.20.0.0_24", "route: 172.20.0.0/24\norigin: as4242420000\norigin: 4242420001\norigin: AS64734.12578\n")];
+
+        let (output, diagnostics) = get_parsed_roa_routes(&records, &RouteOptions::default());
+
+        let asns = output.roas.iter().map(|r| r.asn).collect::<Vec<_>>();
+        assert_eq!(asns, vec![4242420000, 4242420001, 4242420002]);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_unregistered_origins() {
+        let records = vec![record("data/route/172.20.0.0_24", "route: 172.20.0.0/24\norigin: AS4242420000\norigin: AS4242420001\n")];
+
+        let mut options = RouteOptions {
+            registered_origins: Some(HashSet::from([4242420000])),
+            ..Default::default()
+        };
+
+        let (output, diagnostics) = get_parsed_roa_routes(&records, &options);
+        assert_eq!(output.roas.len(), 2);
+        assert_eq!(messages(&diagnostics), vec![(DiagnosticAction::Flagged, "Origin AS4242420001 has no aut-num object")]);
+
+        options.exclude_unregistered_origins = true;
+
+        let (output, diagnostics) = get_parsed_roa_routes(&records, &options);
+        assert_eq!(output.roas.len(), 1);
+        assert_eq!(messages(&diagnostics), vec![(DiagnosticAction::Rejected, "Origin AS4242420001 has no aut-num object")]);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::model::asn::parse_asn;
+use crate::model::output::ROA;
+use crate::model::record::Prefix;
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RoaSourceFormat {
+    // rpki-client, StayRTR or Routinator JSON, ASNs may be numbers or AS-prefixed strings
+    #[default]
+    Json,
+    // Routinator's csv output: ASN,IP Prefix,Max Length,Trust Anchor
+    Csv,
+    // RFC 8416 local exceptions, prefix assertions are added and prefix filters applied to the merged set
+    Slurm,
+}
+
+// RFC 8416 prefix filter, removes every ROA matching all of the given fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixFilter {
+    pub prefix: Option<Prefix>,
+    pub asn: Option<u32>,
+}
+
+impl PrefixFilter {
+    pub fn matches(&self, roa: &ROA) -> bool {
+        let prefix_matches = match &self.prefix {
+            Some(filter) => Prefix::from_str(&roa.prefix).is_ok_and(|prefix| filter.contains(&prefix)),
+            None => true,
+        };
+
+        prefix_matches && self.asn.is_none_or(|asn| asn == roa.asn)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParsedRoaSource {
+    pub roas: Vec<ROA>,
+    pub filters: Vec<PrefixFilter>,
+    // Entries that were skipped, the rest of the source is still used
+    pub warnings: Vec<String>,
+}
+
+fn parse_entry(asn: &str, prefix: &str, max_length: Option<&str>, ta: &str) -> Result<ROA, String> {
+    let asn = parse_asn(asn).ok_or_else(|| format!("Invalid ASN {}", asn))?;
+    let parsed = Prefix::from_str(prefix).map_err(|_| format!("Invalid prefix {}", prefix))?;
+    let len = parsed.prefix_len();
+
+    if parsed != parsed.with_prefix_len(len) {
+        return Err(format!("Prefix {} has host bits set", prefix));
+    }
+
+    let max_length = match max_length {
+        Some(max_length) => max_length.parse::<u8>().map_err(|_| format!("Invalid max length {} for {}", max_length, prefix))?,
+        None => len,
+    };
+
+    if max_length < len || max_length > if parsed.is_ipv4() { 32 } else { 128 } {
+        return Err(format!("Max length {} out of range for {}", max_length, prefix));
+    }
+
+    Ok(ROA {
+        asn,
+        prefix: parsed.to_string(),
+        max_length,
+        ta: ta.to_string(),
+        ..Default::default()
+    })
+}
+
+// JSON numbers and strings alike, e.g. 4242420000, "4242420000" or "AS4242420000"
+fn value_str(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn parse_json(content: &str, ta: &str) -> anyhow::Result<ParsedRoaSource> {
+    let value: Value = serde_json::from_str(content).context("Invalid JSON")?;
+    let entries = value["roas"].as_array().ok_or_else(|| anyhow!("No roas array"))?;
+
+    let mut parsed = ParsedRoaSource::default();
+
+    for entry in entries {
+        let fields = (value_str(&entry["asn"]), value_str(&entry["prefix"]), value_str(&entry["maxLength"]));
+
+        let result = match fields {
+            (Some(asn), Some(prefix), max_length) => parse_entry(&asn, &prefix, max_length.as_deref(), ta),
+            _ => Err(format!("Incomplete entry {}", entry)),
+        };
+
+        match result {
+            Ok(roa) => parsed.roas.push(roa),
+            Err(e) => parsed.warnings.push(e),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_csv(content: &str, ta: &str) -> anyhow::Result<ParsedRoaSource> {
+    let mut parsed = ParsedRoaSource::default();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || (i == 0 && line.starts_with("ASN,")) {
+            continue;
+        }
+
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+
+        let result = match fields.as_slice() {
+            [asn, prefix, max_length, ..] => parse_entry(asn, prefix, Some(max_length), ta),
+            _ => Err(format!("Line {}: expected ASN,IP Prefix,Max Length", i + 1)),
+        };
+
+        match result {
+            Ok(roa) => parsed.roas.push(roa),
+            Err(e) => parsed.warnings.push(e),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_slurm(content: &str, ta: &str) -> anyhow::Result<ParsedRoaSource> {
+    let value: Value = serde_json::from_str(content).context("Invalid JSON")?;
+
+    if value["slurmVersion"] != 1 {
+        bail!("Unsupported slurmVersion {}", value["slurmVersion"]);
+    }
+
+    let mut parsed = ParsedRoaSource::default();
+
+    for assertion in value["locallyAddedAssertions"]["prefixAssertions"].as_array().into_iter().flatten() {
+        let fields = (value_str(&assertion["asn"]), value_str(&assertion["prefix"]), value_str(&assertion["maxPrefixLength"]));
+
+        let result = match fields {
+            (Some(asn), Some(prefix), max_length) => parse_entry(&asn, &prefix, max_length.as_deref(), ta),
+            _ => Err(format!("Incomplete prefix assertion {}", assertion)),
+        };
+
+        match result {
+            Ok(roa) => parsed.roas.push(roa),
+            Err(e) => parsed.warnings.push(e),
+        }
+    }
+
+    for filter in value["validationOutputFilters"]["prefixFilters"].as_array().into_iter().flatten() {
+        let prefix = value_str(&filter["prefix"]).map(|p| Prefix::from_str(&p).map(|p| p.with_prefix_len(p.prefix_len())));
+        let asn = value_str(&filter["asn"]).map(|a| parse_asn(&a).ok_or(a));
+
+        match (prefix.transpose(), asn.transpose()) {
+            (Ok(None), Ok(None)) => parsed.warnings.push(format!("Prefix filter without prefix or asn {}", filter)),
+            (Ok(prefix), Ok(asn)) => parsed.filters.push(PrefixFilter { prefix, asn }),
+            _ => parsed.warnings.push(format!("Invalid prefix filter {}", filter)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+// Errors only when the source as a whole cannot be read, invalid entries become warnings
+pub fn parse_roa_source(content: &str, format: RoaSourceFormat, ta: &str) -> anyhow::Result<ParsedRoaSource> {
+    match format {
+        RoaSourceFormat::Json => parse_json(content, ta),
+        RoaSourceFormat::Csv => parse_csv(content, ta),
+        RoaSourceFormat::Slurm => parse_slurm(content, ta),
+    }
+}
+
+// Appends the source ROAs not already present, earlier entries win so registry ROAs keep
+// their trust anchor, then drops everything matched by a SLURM prefix filter.
+// Returns the number of filtered ROAs.
+pub fn merge_roa_sources(roas: &mut Vec<ROA>, sources: &[&ParsedRoaSource]) -> usize {
+    let mut seen = roas.iter().map(|roa| (roa.asn, roa.prefix.clone(), roa.max_length)).collect::<HashSet<_>>();
+
+    for source in sources {
+        for roa in &source.roas {
+            if seen.insert((roa.asn, roa.prefix.clone(), roa.max_length)) {
+                roas.push(roa.clone());
+            }
+        }
+    }
+
+    let filters = sources.iter().flat_map(|source| &source.filters).collect::<Vec<_>>();
+    let before = roas.len();
+
+    roas.retain(|roa| !filters.iter().any(|filter| filter.matches(roa)));
+
+    before - roas.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(parsed: &ParsedRoaSource) -> Vec<String> {
+        parsed.roas.iter().map(|roa| format!("{} {} {} {}", roa.asn, roa.prefix, roa.max_length, roa.ta)).collect()
+    }
+
+    #[test]
+    fn test_parse_json_source() {
+        let content = r#"{"metadata": {}, "roas": [
+            {"asn": 4242420000, "prefix": "10.10.0.0/16", "maxLength": 24, "ta": "other"},
+            {"asn": "AS4242420001", "prefix": "fd10::/32", "maxLength": 48},
+            {"asn": "AS4242420002", "prefix": "10.11.0.1/16", "maxLength": 24},
+            {"asn": "AS4242420003", "prefix": "10.12.0.0/16", "maxLength": 8},
+            {"prefix": "10.13.0.0/16"}
+        ]}"#;
+
+        let parsed = parse_roa_source(content, RoaSourceFormat::Json, "peer").unwrap();
+
+        assert_eq!(summary(&parsed), vec!["4242420000 10.10.0.0/16 24 peer", "4242420001 fd10::/32 48 peer"]);
+        assert_eq!(parsed.warnings.len(), 3);
+
+        assert!(parse_roa_source("{}", RoaSourceFormat::Json, "peer").is_err());
+        assert!(parse_roa_source("not json", RoaSourceFormat::Json, "peer").is_err());
+    }
+
+    #[test]
+    fn test_parse_csv_source() {
+        let content = "ASN,IP Prefix,Max Length,Trust Anchor\nAS4242420000,10.10.0.0/16,24,arin\n\n4242420001, fd10::/32, 48\nbroken\n";
+
+        let parsed = parse_roa_source(content, RoaSourceFormat::Csv, "peer").unwrap();
+
+        assert_eq!(summary(&parsed), vec!["4242420000 10.10.0.0/16 24 peer", "4242420001 fd10::/32 48 peer"]);
+        assert_eq!(parsed.warnings, vec!["Line 5: expected ASN,IP Prefix,Max Length"]);
+    }
+
+    #[test]
+    fn test_parse_slurm_source() {
+        let content = r#"{
+            "slurmVersion": 1,
+            "validationOutputFilters": {
+                "prefixFilters": [{"prefix": "172.20.0.0/16", "comment": "all of 172.20/16"}, {"asn": 4242420009}],
+                "bgpsecFilters": []
+            },
+            "locallyAddedAssertions": {
+                "prefixAssertions": [{"asn": 4242420000, "prefix": "10.10.0.0/16", "comment": "no max length"}],
+                "bgpsecAssertions": []
+            }
+        }"#;
+
+        let parsed = parse_roa_source(content, RoaSourceFormat::Slurm, "slurm").unwrap();
+
+        assert_eq!(summary(&parsed), vec!["4242420000 10.10.0.0/16 16 slurm"]);
+        assert_eq!(parsed.filters.len(), 2);
+
+        let roa = |asn, prefix: &str| ROA { asn, prefix: prefix.to_string(), max_length: 24, ..Default::default() };
+
+        assert!(parsed.filters[0].matches(&roa(4242420000, "172.20.1.0/24")));
+        assert!(!parsed.filters[0].matches(&roa(4242420000, "172.21.0.0/24")));
+        assert!(parsed.filters[1].matches(&roa(4242420009, "fd00::/48")));
+
+        assert!(parse_roa_source(r#"{"slurmVersion": 2}"#, RoaSourceFormat::Slurm, "slurm").is_err());
+    }
+
+    #[test]
+    fn test_merge_roa_sources() {
+        let roa = |asn, prefix: &str, ta: &str| ROA { asn, prefix: prefix.to_string(), max_length: 24, ta: ta.to_string(), ..Default::default() };
+
+        let mut roas = vec![roa(4242420000, "172.20.0.0/24", ""), roa(4242420001, "172.21.0.0/24", "")];
+
+        let first = ParsedRoaSource {
+            roas: vec![roa(4242420000, "172.20.0.0/24", "first"), roa(4242420005, "10.10.0.0/24", "first")],
+            ..Default::default()
+        };
+        let second = ParsedRoaSource {
+            roas: vec![roa(4242420005, "10.10.0.0/24", "second"), roa(4242420006, "10.11.0.0/24", "second")],
+            filters: vec![PrefixFilter { prefix: None, asn: Some(4242420001) }],
+            ..Default::default()
+        };
+
+        assert_eq!(merge_roa_sources(&mut roas, &[&first, &second]), 1);
+
+        let merged = roas.iter().map(|roa| format!("{} {}", roa.prefix, roa.ta)).collect::<Vec<_>>();
+        assert_eq!(merged, vec!["172.20.0.0/24 ", "10.10.0.0/24 first", "10.11.0.0/24 second"]);
+    }
+}
@@ -0,0 +1,675 @@
+use crate::task::Task;
+use crate::{AppConfig, AppState};
+use anyhow::bail;
+use serde::Serialize;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+const DEFAULT_TIMEOUT_SECONDS: u64 = 600;
+const DEFAULT_MAX_BACKOFF_SECONDS: u64 = 3600;
+const BACKOFF_BASE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskSettings {
+    pub enabled: bool,
+    // None: only runs after its dependencies
+    pub interval: Option<Duration>,
+    pub after: Vec<String>,
+    pub timeout: Duration,
+    pub jitter: Duration,
+    pub max_backoff: Duration,
+}
+
+impl TaskSettings {
+    pub fn resolve(task: &dyn Task, config: &AppConfig) -> Self {
+        let overrides = config.tasks.get(task.id()).cloned().unwrap_or_default();
+
+        let after = overrides.after.unwrap_or_else(|| task.dependencies());
+
+        let default_interval = if after.is_empty() { config.update_interval_seconds } else { 0 };
+
+        let interval = match overrides.interval_seconds.unwrap_or(default_interval) {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        };
+
+        TaskSettings {
+            enabled: overrides.enabled.unwrap_or(true),
+            interval,
+            after,
+            timeout: Duration::from_secs(overrides.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECONDS)),
+            jitter: Duration::from_secs(overrides.jitter_seconds.unwrap_or(0)),
+            max_backoff: Duration::from_secs(overrides.max_backoff_seconds.unwrap_or(DEFAULT_MAX_BACKOFF_SECONDS)),
+        }
+    }
+
+    fn backoff(&self, consecutive_failures: u32) -> Duration {
+        let exponent = consecutive_failures.saturating_sub(1).min(16);
+
+        BACKOFF_BASE.saturating_mul(1 << exponent).min(self.max_backoff)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct TaskStatus {
+    pub name: String,
+    pub enabled: bool,
+    pub running: bool,
+    pub after: Vec<String>,
+    pub interval_seconds: Option<u64>,
+    pub last_started: Option<String>,
+    pub last_finished: Option<String>,
+    pub last_success: Option<String>,
+    pub last_duration_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    pub next_run: Option<String>,
+}
+
+struct ScheduledTask {
+    task: Arc<dyn Task>,
+    settings: TaskSettings,
+    next_due: Option<Instant>,
+    // Set when a dependency completed or a refresh was requested
+    pending: bool,
+    consecutive_failures: u32,
+    // Stays set after a timeout until the blocking thread actually returns
+    running: Arc<AtomicBool>,
+}
+
+pub struct Scheduler {
+    state: AppState,
+    // Topologically sorted, dependencies always come before their dependents
+    tasks: Vec<ScheduledTask>,
+}
+
+fn random_duration(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+
+    let random = RandomState::new().build_hasher().finish();
+
+    Duration::from_millis(random % (max.as_millis() as u64 + 1))
+}
+
+fn to_rfc3339(instant: Instant) -> String {
+    let now = Instant::now();
+
+    let wall_clock = if instant >= now {
+        chrono::Utc::now() + (instant - now)
+    } else {
+        chrono::Utc::now() - (now - instant)
+    };
+
+    wall_clock.to_rfc3339()
+}
+
+// Orders tasks so that every task comes after all of its dependencies.
+fn sort_by_dependencies(tasks: Vec<(Arc<dyn Task>, TaskSettings)>) -> anyhow::Result<Vec<(Arc<dyn Task>, TaskSettings)>> {
+    let ids: HashSet<String> = tasks.iter().map(|(task, _)| task.id().to_string()).collect();
+
+    if ids.len() != tasks.len() {
+        bail!("Duplicate task ids registered");
+    }
+
+    for (task, settings) in &tasks {
+        for dependency in &settings.after {
+            if !ids.contains(dependency) {
+                bail!("Task '{}' depends on unknown task '{}'", task.id(), dependency);
+            }
+        }
+    }
+
+    let mut remaining = tasks;
+    let mut sorted: Vec<(Arc<dyn Task>, TaskSettings)> = Vec::with_capacity(remaining.len());
+    let mut placed: HashSet<String> = HashSet::new();
+
+    while !remaining.is_empty() {
+        let ready = remaining
+            .iter()
+            .position(|(_, settings)| settings.after.iter().all(|d| placed.contains(d)));
+
+        match ready {
+            Some(index) => {
+                let entry = remaining.remove(index);
+                placed.insert(entry.0.id().to_string());
+                sorted.push(entry);
+            }
+            None => {
+                let cycle = remaining.iter().map(|(task, _)| task.id()).collect::<Vec<_>>();
+                bail!("Task dependencies contain a cycle involving: {:?}", cycle);
+            }
+        }
+    }
+
+    Ok(sorted)
+}
+
+fn resolve_all(tasks: Vec<Arc<dyn Task>>, config: &AppConfig) -> anyhow::Result<Vec<(Arc<dyn Task>, TaskSettings)>> {
+    let tasks = tasks
+        .into_iter()
+        .map(|task| {
+            let settings = TaskSettings::resolve(task.as_ref(), config);
+            (task, settings)
+        })
+        .collect::<Vec<_>>();
+
+    sort_by_dependencies(tasks)
+}
+
+// Checks that a configuration yields a valid schedule without applying it
+pub fn check_task_settings(tasks: &[Arc<dyn Task>], config: &AppConfig) -> anyhow::Result<()> {
+    for id in config.tasks.keys() {
+        if !tasks.iter().any(|task| task.id() == id) {
+            bail!("Configuration refers to unknown task '{}'", id);
+        }
+    }
+
+    resolve_all(tasks.to_vec(), config).map(|_| ())
+}
+
+impl Scheduler {
+    pub fn new(state: AppState, tasks: Vec<Arc<dyn Task>>) -> anyhow::Result<Self> {
+        let config = state.config.get();
+
+        let now = Instant::now();
+
+        let tasks = resolve_all(tasks, &config)?
+            .into_iter()
+            .map(|(task, settings)| {
+                // Root tasks start right away, dependents wait to be triggered
+                let next_due = match settings.interval {
+                    Some(_) if settings.after.is_empty() => Some(now + random_duration(settings.jitter)),
+                    Some(interval) => Some(now + interval + random_duration(settings.jitter)),
+                    None => None,
+                };
+
+                ScheduledTask {
+                    task,
+                    settings,
+                    next_due,
+                    pending: false,
+                    consecutive_failures: 0,
+                    running: Arc::new(AtomicBool::new(false)),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let scheduler = Scheduler { state, tasks };
+
+        for index in 0..scheduler.tasks.len() {
+            scheduler.update_status(index, |_| {});
+        }
+
+        Ok(scheduler)
+    }
+
+    fn update_status(&self, index: usize, f: impl FnOnce(&mut TaskStatus)) {
+        let scheduled = &self.tasks[index];
+
+        let mut status_lock = self.state.task_status.write().unwrap();
+
+        let status = status_lock.entry(scheduled.task.id().to_string()).or_default();
+
+        status.name = scheduled.task.name().to_string();
+        status.enabled = scheduled.settings.enabled;
+        status.running = scheduled.running.load(Ordering::SeqCst);
+        status.after = scheduled.settings.after.clone();
+        status.interval_seconds = scheduled.settings.interval.map(|i| i.as_secs());
+        status.consecutive_failures = scheduled.consecutive_failures;
+        status.next_run = scheduled.next_due.map(to_rfc3339);
+
+        f(status);
+    }
+
+    // Re-resolves task settings after a configuration reload while keeping run state
+    fn apply_config(&mut self, config: &AppConfig) {
+        let tasks = self.tasks.iter().map(|t| t.task.clone()).collect::<Vec<_>>();
+
+        let resolved = match resolve_all(tasks, config) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                error!("Ignoring task settings from reloaded configuration: {:?}", e);
+                return;
+            }
+        };
+
+        let now = Instant::now();
+        let mut previous = std::mem::take(&mut self.tasks);
+
+        for (task, settings) in resolved {
+            let index = previous.iter().position(|t| t.task.id() == task.id()).unwrap();
+            let mut scheduled = previous.swap_remove(index);
+
+            if scheduled.settings != settings {
+                info!("Task '{}' settings changed: {:?}", task.name(), settings);
+
+                if scheduled.settings.interval != settings.interval {
+                    scheduled.next_due = settings.interval.map(|interval| now + interval);
+                }
+
+                scheduled.settings = settings;
+            }
+
+            self.tasks.push(scheduled);
+        }
+
+        for index in 0..self.tasks.len() {
+            self.update_status(index, |_| {});
+        }
+    }
+
+    fn trigger_roots(&mut self) {
+        for scheduled in &mut self.tasks {
+            if scheduled.settings.after.is_empty() {
+                scheduled.pending = true;
+            }
+        }
+    }
+
+    async fn run_task(&mut self, index: usize) {
+        let scheduled = &self.tasks[index];
+        let task = scheduled.task.clone();
+        let timeout = scheduled.settings.timeout;
+        let running = scheduled.running.clone();
+
+        info!("Running task: {}", task.name());
+
+        running.store(true, Ordering::SeqCst);
+
+        self.update_status(index, |status| {
+            status.last_started = Some(chrono::Utc::now().to_rfc3339());
+        });
+
+        let begin = Instant::now();
+
+        let blocking_task = task.clone();
+        let blocking_running = running.clone();
+
+        let handle = tokio::task::spawn_blocking(move || {
+            let result = blocking_task.run();
+            blocking_running.store(false, Ordering::SeqCst);
+            result
+        });
+
+        let result = match tokio::time::timeout(timeout, handle).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => Err(anyhow::anyhow!("Task panicked: {}", e)),
+            Err(_) => Err(anyhow::anyhow!("Task timed out after {:?}", timeout)),
+        };
+
+        let elapsed = begin.elapsed();
+
+        let success = match &result {
+            Ok(()) => {
+                info!("Successfully completed task: {}", task.name());
+                true
+            }
+            Err(e) => {
+                error!("Error running task '{}': {:?}", task.name(), e);
+                false
+            }
+        };
+
+        info!("Task '{}' completed in {:.2?}", task.name(), elapsed);
+
+        let now = Instant::now();
+        let scheduled = &mut self.tasks[index];
+
+        scheduled.pending = false;
+
+        if success {
+            scheduled.consecutive_failures = 0;
+            scheduled.next_due = scheduled.settings.interval.map(|interval| now + interval + random_duration(scheduled.settings.jitter));
+        } else {
+            scheduled.consecutive_failures += 1;
+            let backoff = scheduled.settings.backoff(scheduled.consecutive_failures);
+
+            warn!("Task '{}' failed {} time(s) in a row, retrying in {:?}", task.name(), scheduled.consecutive_failures, backoff);
+
+            scheduled.next_due = Some(now + backoff);
+        }
+
+        let finished = chrono::Utc::now().to_rfc3339();
+
+        self.update_status(index, |status| {
+            status.last_finished = Some(finished.clone());
+            status.last_duration_ms = Some(elapsed.as_millis() as u64);
+
+            match result {
+                Ok(()) => {
+                    status.last_success = Some(finished);
+                    status.last_error = None;
+                }
+                Err(e) => status.last_error = Some(format!("{:#}", e)),
+            }
+        });
+    }
+
+    // Dependents run whatever the outcome, so a failed or disabled sync still regenerates from the local checkout
+    fn trigger_dependents(&mut self, index: usize) {
+        let id = self.tasks[index].task.id().to_string();
+
+        for dependent in &mut self.tasks[index + 1..] {
+            if dependent.settings.after.contains(&id) {
+                dependent.pending = true;
+            }
+        }
+    }
+
+    async fn run_due_tasks(&mut self) {
+        for index in 0..self.tasks.len() {
+            let now = Instant::now();
+            let scheduled = &self.tasks[index];
+
+            let is_due = scheduled.pending || scheduled.next_due.is_some_and(|due| due <= now);
+
+            if !is_due {
+                continue;
+            }
+
+            if !scheduled.settings.enabled {
+                let scheduled = &mut self.tasks[index];
+                scheduled.pending = false;
+                scheduled.next_due = scheduled.settings.interval.map(|interval| now + interval);
+
+                self.trigger_dependents(index);
+                continue;
+            }
+
+            if scheduled.running.load(Ordering::SeqCst) {
+                warn!("Task '{}' is still running from a previous attempt, skipping this run.", scheduled.task.name());
+
+                let scheduled = &mut self.tasks[index];
+                scheduled.pending = false;
+                scheduled.next_due = scheduled.settings.interval.map(|interval| now + interval);
+                continue;
+            }
+
+            self.run_task(index).await;
+            self.trigger_dependents(index);
+        }
+    }
+
+    pub async fn run(mut self) {
+        let mut config_updates = self.state.config.subscribe();
+        config_updates.mark_unchanged();
+
+        loop {
+            self.run_due_tasks().await;
+
+            let next_due = self
+                .tasks
+                .iter()
+                .filter_map(|t| t.next_due)
+                .min();
+
+            match next_due {
+                Some(next_due) => info!("Waiting for {:?} before next scheduled task.", next_due.saturating_duration_since(Instant::now())),
+                None => info!("No scheduled tasks, waiting for a refresh trigger."),
+            }
+
+            let sleep = async {
+                match next_due {
+                    Some(next_due) => tokio::time::sleep_until(next_due).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = sleep => {}
+                _ = self.state.refresh_trigger.notified() => {
+                    info!("Refresh triggered, starting update immediately.");
+                    self.trigger_roots();
+                }
+                Ok(()) = config_updates.changed() => {
+                    let config = config_updates.borrow_and_update().clone();
+                    self.apply_config(&config);
+                }
+            }
+        }
+    }
+}
+
+pub async fn run_scheduler(state: AppState, tasks: Vec<Arc<dyn Task>>) -> anyhow::Result<()> {
+    let scheduler = Scheduler::new(state, tasks)?;
+
+    info!("Starting task scheduler with {} tasks.", scheduler.tasks.len());
+
+    scheduler.run().await;
+
+    Ok(())
+}
+
+pub fn task_status_snapshot(state: &AppState) -> HashMap<String, TaskStatus> {
+    state.task_status.read().map(|s| s.clone()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConfigHandle, TaskConfig};
+    use std::sync::Mutex;
+
+    struct TestTask {
+        id: String,
+        dependencies: Vec<String>,
+        log: Arc<Mutex<Vec<String>>>,
+        fail: bool,
+    }
+
+    impl Task for TestTask {
+        fn name(&self) -> &str {
+            &self.id
+        }
+
+        fn id(&self) -> &str {
+            &self.id
+        }
+
+        fn run(&self) -> anyhow::Result<()> {
+            self.log.lock().unwrap().push(self.id.clone());
+
+            if self.fail {
+                bail!("failed");
+            }
+
+            Ok(())
+        }
+
+        fn dependencies(&self) -> Vec<String> {
+            self.dependencies.clone()
+        }
+    }
+
+    fn task(id: &str, dependencies: &[&str], log: &Arc<Mutex<Vec<String>>>, fail: bool) -> Arc<dyn Task> {
+        Arc::new(TestTask {
+            id: id.to_string(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            log: log.clone(),
+            fail,
+        })
+    }
+
+    fn state_with(tasks: &[(&str, TaskConfig)]) -> AppState {
+        let mut config = AppConfig::default();
+
+        for (id, task_config) in tasks {
+            config.tasks.insert(id.to_string(), task_config.clone());
+        }
+
+        AppState {
+            config: ConfigHandle::new(config),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_defaults() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let config = AppConfig::default();
+
+        let root = TaskSettings::resolve(task("root", &[], &log, false).as_ref(), &config);
+        assert!(root.enabled);
+        assert_eq!(root.interval, Some(Duration::from_secs(config.update_interval_seconds)));
+
+        let dependent = TaskSettings::resolve(task("child", &["root"], &log, false).as_ref(), &config);
+        assert_eq!(dependent.interval, None);
+        assert_eq!(dependent.after, vec!["root".to_string()]);
+    }
+
+    #[test]
+    fn test_resolve_overrides() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let state = state_with(&[("child", TaskConfig {
+            enabled: Some(false),
+            interval_seconds: Some(60),
+            after: Some(Vec::new()),
+            timeout_seconds: Some(5),
+            ..Default::default()
+        })]);
+
+        let settings = TaskSettings::resolve(task("child", &["root"], &log, false).as_ref(), &state.config.get());
+        assert!(!settings.enabled);
+        assert_eq!(settings.interval, Some(Duration::from_secs(60)));
+        assert!(settings.after.is_empty());
+        assert_eq!(settings.timeout, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let settings = TaskSettings {
+            enabled: true,
+            interval: None,
+            after: Vec::new(),
+            timeout: Duration::from_secs(1),
+            jitter: Duration::ZERO,
+            max_backoff: Duration::from_secs(100),
+        };
+
+        assert_eq!(settings.backoff(1), Duration::from_secs(15));
+        assert_eq!(settings.backoff(2), Duration::from_secs(30));
+        assert_eq!(settings.backoff(3), Duration::from_secs(60));
+        assert_eq!(settings.backoff(4), Duration::from_secs(100));
+        assert_eq!(settings.backoff(40), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_sort_rejects_cycles_and_unknown_dependencies() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let state = state_with(&[]);
+
+        let cyclic = vec![task("a", &["b"], &log, false), task("b", &["a"], &log, false)];
+        assert!(Scheduler::new(state.clone(), cyclic).is_err());
+
+        let unknown = vec![task("a", &["missing"], &log, false)];
+        assert!(Scheduler::new(state, unknown).is_err());
+    }
+
+    #[test]
+    fn test_check_task_settings() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let tasks = vec![task("sync", &[], &log, false), task("roa", &["sync"], &log, false)];
+
+        assert!(check_task_settings(&tasks, &AppConfig::default()).is_ok());
+
+        let unknown_task = state_with(&[("typo", TaskConfig::default())]);
+        assert!(check_task_settings(&tasks, &unknown_task.config.get()).is_err());
+
+        let cycle = state_with(&[("sync", TaskConfig { after: Some(vec!["roa".to_string()]), ..Default::default() })]);
+        assert!(check_task_settings(&tasks, &cycle.config.get()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_config_keeps_order_and_updates_settings() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let state = state_with(&[]);
+
+        let tasks = vec![task("sync", &[], &log, false), task("roa", &["sync"], &log, false)];
+        let mut scheduler = Scheduler::new(state, tasks).unwrap();
+
+        let mut config = AppConfig::default();
+        config.tasks.insert("roa".to_string(), TaskConfig { interval_seconds: Some(30), ..Default::default() });
+        scheduler.apply_config(&config);
+
+        let ids = scheduler.tasks.iter().map(|t| t.task.id()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["sync", "roa"]);
+        assert_eq!(scheduler.tasks[1].settings.interval, Some(Duration::from_secs(30)));
+        assert!(scheduler.tasks[1].next_due.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_dependencies_run_in_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let state = state_with(&[]);
+
+        let tasks = vec![
+            task("dns", &["sync"], &log, false),
+            task("roa", &["sync"], &log, false),
+            task("sync", &[], &log, false),
+        ];
+
+        let mut scheduler = Scheduler::new(state.clone(), tasks).unwrap();
+        scheduler.run_due_tasks().await;
+
+        assert_eq!(*log.lock().unwrap(), vec!["sync", "dns", "roa"]);
+
+        let status = task_status_snapshot(&state);
+        assert!(status["sync"].last_success.is_some());
+        assert!(status["dns"].next_run.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_dependency_still_triggers_dependents() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let state = state_with(&[]);
+
+        let tasks = vec![task("sync", &[], &log, true), task("roa", &["sync"], &log, false)];
+
+        let mut scheduler = Scheduler::new(state.clone(), tasks).unwrap();
+        scheduler.run_due_tasks().await;
+
+        assert_eq!(*log.lock().unwrap(), vec!["sync", "roa"]);
+
+        let status = task_status_snapshot(&state);
+        assert_eq!(status["sync"].consecutive_failures, 1);
+        assert_eq!(status["sync"].last_error.as_deref(), Some("failed"));
+        assert!(status["roa"].last_success.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_dependency_still_triggers_dependents() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let state = state_with(&[("sync", TaskConfig { enabled: Some(false), ..Default::default() })]);
+
+        let tasks = vec![task("sync", &[], &log, false), task("roa", &["sync"], &log, false)];
+
+        let mut scheduler = Scheduler::new(state, tasks).unwrap();
+        scheduler.run_due_tasks().await;
+        assert_eq!(*log.lock().unwrap(), vec!["roa"]);
+
+        scheduler.trigger_roots();
+        scheduler.run_due_tasks().await;
+        assert_eq!(*log.lock().unwrap(), vec!["roa", "roa"]);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_task_is_skipped() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let state = state_with(&[("roa", TaskConfig { enabled: Some(false), ..Default::default() })]);
+
+        let tasks = vec![task("sync", &[], &log, false), task("roa", &["sync"], &log, false)];
+
+        let mut scheduler = Scheduler::new(state, tasks).unwrap();
+        scheduler.run_due_tasks().await;
+
+        assert_eq!(*log.lock().unwrap(), vec!["sync"]);
+    }
+}
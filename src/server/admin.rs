@@ -0,0 +1,158 @@
+use crate::AppState;
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+use tracing::{info, warn};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Headers carrying a hex encoded HMAC-SHA256 of the request body.
+// GitHub prefixes the digest with "sha256=", Gitea and Gogs send it bare.
+const SIGNATURE_HEADERS: [&str; 3] = ["x-hub-signature-256", "x-gitea-signature", "x-gogs-signature"];
+
+const EVENT_HEADERS: [&str; 3] = ["x-github-event", "x-gitea-event", "x-gogs-event"];
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn is_authorized(headers: &HeaderMap, token: &str) -> bool {
+    let provided = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim());
+
+    match provided {
+        Some(provided) => constant_time_eq(provided.as_bytes(), token.as_bytes()),
+        None => false,
+    }
+}
+
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let signature = signature.trim();
+    let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
+
+    let signature = match hex::decode(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+
+    let mut mac = match HmacSha256::new_from_slice(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+
+    mac.update(body);
+
+    mac.verify_slice(&signature).is_ok()
+}
+
+pub async fn post_refresh(State(state): State<AppState>, headers: HeaderMap) -> Response<Body> {
+    let config = state.config.get();
+
+    if config.admin_token.is_empty() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    if !is_authorized(&headers, &config.admin_token) {
+        warn!("Rejected unauthorized refresh request.");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    info!("Manual refresh requested.");
+
+    state.refresh_trigger.notify_one();
+
+    StatusCode::ACCEPTED.into_response()
+}
+
+pub async fn post_webhook(State(state): State<AppState>, headers: HeaderMap, body: Bytes) -> Response<Body> {
+    let config = state.config.get();
+
+    if config.webhook_secret.is_empty() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let signature = SIGNATURE_HEADERS
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .and_then(|v| v.to_str().ok());
+
+    let signature = match signature {
+        Some(signature) => signature,
+        None => {
+            warn!("Rejected webhook without signature.");
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+    };
+
+    if !verify_signature(&config.webhook_secret, &body, signature) {
+        warn!("Rejected webhook with invalid signature.");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let event = EVENT_HEADERS
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("push");
+
+    if event != "push" {
+        info!("Ignoring webhook event '{}'.", event);
+        return StatusCode::OK.into_response();
+    }
+
+    info!("Push webhook received, triggering refresh.");
+
+    state.refresh_trigger.notify_one();
+
+    StatusCode::ACCEPTED.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    // echo -n '{"ref":"refs/heads/master"}' | openssl dgst -sha256 -hmac 'secret'
+    const BODY: &[u8] = br#"{"ref":"refs/heads/master"}"#;
+    const SIGNATURE: &str = "18bd702ca7dab5713101db346ec6cd6768820c090515db9744deff53bc95ff52";
+
+    #[test]
+    fn test_verify_signature_github_and_gitea_forms() {
+        assert!(verify_signature("secret", BODY, SIGNATURE));
+        assert!(verify_signature("secret", BODY, &format!("sha256={}", SIGNATURE)));
+        assert!(verify_signature("secret", BODY, &SIGNATURE.to_uppercase()));
+    }
+
+    #[test]
+    fn test_verify_signature_rejects_mismatch() {
+        assert!(!verify_signature("other-secret", BODY, SIGNATURE));
+        assert!(!verify_signature("secret", b"tampered", SIGNATURE));
+        assert!(!verify_signature("secret", BODY, &SIGNATURE[..32]));
+        assert!(!verify_signature("secret", BODY, "not-hex"));
+        assert!(!verify_signature("secret", BODY, ""));
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_authorized(&headers, "token"));
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer token"));
+        assert!(is_authorized(&headers, "token"));
+        assert!(!is_authorized(&headers, "token2"));
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Basic token"));
+        assert!(!is_authorized(&headers, "token"));
+    }
+}
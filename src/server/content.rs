@@ -0,0 +1,381 @@
+use axum::body::{Body, Bytes};
+use axum::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, VARY};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use strum::{Display, EnumIter, IntoEnumIterator};
+use tracing::warn;
+
+const GZIP_LEVEL: u32 = 6;
+const BROTLI_QUALITY: u32 = 9;
+const BROTLI_WINDOW_SIZE: u32 = 22;
+const ZSTD_LEVEL: i32 = 9;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Display, EnumIter)]
+pub enum ContentEncoding {
+    // Ordered by preference when the client weights several encodings equally
+    #[strum(serialize = "br")]
+    Brotli,
+    #[strum(serialize = "zstd")]
+    Zstd,
+    #[strum(serialize = "gzip")]
+    Gzip,
+    #[strum(serialize = "identity")]
+    Identity,
+}
+
+impl ContentEncoding {
+    fn compress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            ContentEncoding::Brotli => {
+                let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, BROTLI_QUALITY, BROTLI_WINDOW_SIZE);
+                writer.write_all(data)?;
+                Ok(writer.into_inner())
+            }
+            ContentEncoding::Zstd => zstd::bulk::compress(data, ZSTD_LEVEL),
+            ContentEncoding::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::new(GZIP_LEVEL));
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            ContentEncoding::Identity => Ok(data.to_vec()),
+        }
+    }
+
+    fn matches_token(&self, token: &str) -> bool {
+        match self {
+            ContentEncoding::Gzip => token.eq_ignore_ascii_case("gzip") || token.eq_ignore_ascii_case("x-gzip"),
+            _ => token.eq_ignore_ascii_case(&self.to_string()),
+        }
+    }
+}
+
+// Picks the best encoding we have available according to RFC 9110 section 12.5.3.
+// Returns None if the client explicitly refuses every encoding including identity.
+pub fn negotiate_encoding(accept_encoding: Option<&str>) -> Option<ContentEncoding> {
+    let accept_encoding = match accept_encoding {
+        Some(value) if !value.trim().is_empty() => value,
+        _ => return Some(ContentEncoding::Identity),
+    };
+
+    let mut preferences: Vec<(String, f32)> = Vec::new();
+
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let token = parts.next().unwrap_or("").trim();
+
+        if token.is_empty() {
+            continue;
+        }
+
+        let mut quality = 1.0;
+
+        for param in parts {
+            if let Some((key, value)) = param.split_once('=')
+                && key.trim().eq_ignore_ascii_case("q")
+            {
+                quality = value.trim().parse::<f32>().unwrap_or(0.0);
+            }
+        }
+
+        preferences.push((token.to_string(), quality));
+    }
+
+    let quality_of = |encoding: ContentEncoding| -> Option<f32> {
+        preferences
+            .iter()
+            .find(|(token, _)| encoding.matches_token(token))
+            .or_else(|| preferences.iter().find(|(token, _)| token == "*"))
+            .map(|(_, quality)| *quality)
+    };
+
+    let mut best: Option<(ContentEncoding, f32)> = None;
+
+    for encoding in ContentEncoding::iter() {
+        let quality = match quality_of(encoding) {
+            Some(quality) => quality,
+            // identity is always acceptable unless explicitly excluded
+            None if encoding == ContentEncoding::Identity => 0.001,
+            None => continue,
+        };
+
+        if quality <= 0.0 {
+            continue;
+        }
+
+        if best.is_none_or(|(_, best_quality)| quality > best_quality) {
+            best = Some((encoding, quality));
+        }
+    }
+
+    best.map(|(encoding, _)| encoding)
+}
+
+#[derive(Clone, Debug)]
+struct EncodedBody {
+    encoding: ContentEncoding,
+    data: Bytes,
+}
+
+// A response body that has been compressed ahead of time so that request
+// handlers only have to pick a representation.
+#[derive(Clone, Debug)]
+pub struct CachedContent {
+    content_type: &'static str,
+    digest: String,
+    last_modified: SystemTime,
+    bodies: Vec<EncodedBody>,
+}
+
+impl CachedContent {
+    pub fn new(content_type: &'static str, content: impl Into<Bytes>, last_modified: SystemTime) -> Self {
+        let content = content.into();
+
+        let digest = Sha256::digest(&content)
+            .iter()
+            .take(16)
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+
+        let mut bodies = Vec::with_capacity(4);
+
+        for encoding in ContentEncoding::iter() {
+            if encoding == ContentEncoding::Identity {
+                bodies.push(EncodedBody { encoding, data: content.clone() });
+                continue;
+            }
+
+            match encoding.compress(&content) {
+                // Only keep compressed variants that actually save bytes
+                Ok(data) if data.len() < content.len() => bodies.push(EncodedBody { encoding, data: Bytes::from(data) }),
+                Ok(_) => {}
+                Err(e) => warn!("Failed to compress content with {}: {:?}", encoding, e),
+            }
+        }
+
+        CachedContent {
+            content_type,
+            digest,
+            last_modified: truncate_to_seconds(last_modified),
+            bodies,
+        }
+    }
+
+    pub fn content(&self) -> &Bytes {
+        &self.body(ContentEncoding::Identity).unwrap().data
+    }
+
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    pub fn last_modified(&self) -> SystemTime {
+        self.last_modified
+    }
+
+    pub fn etag(&self, encoding: ContentEncoding) -> String {
+        match encoding {
+            ContentEncoding::Identity => format!("\"{}\"", self.digest),
+            _ => format!("\"{}-{}\"", self.digest, encoding),
+        }
+    }
+
+    fn body(&self, encoding: ContentEncoding) -> Option<&EncodedBody> {
+        self.bodies.iter().find(|b| b.encoding == encoding)
+    }
+
+    fn is_not_modified(&self, headers: &HeaderMap) -> bool {
+        // If-None-Match takes precedence over If-Modified-Since (RFC 9110 section 13.2.2)
+        if let Some(if_none_match) = headers.get(IF_NONE_MATCH) {
+            let if_none_match = match if_none_match.to_str() {
+                Ok(value) => value,
+                Err(_) => return false,
+            };
+
+            return if_none_match.split(',').map(|tag| tag.trim()).any(|tag| {
+                if tag == "*" {
+                    return true;
+                }
+
+                // Weak comparison: the W/ prefix is ignored
+                let tag = tag.strip_prefix("W/").unwrap_or(tag);
+
+                self.bodies.iter().any(|b| self.etag(b.encoding) == tag)
+            });
+        }
+
+        if let Some(if_modified_since) = headers.get(IF_MODIFIED_SINCE)
+            && let Ok(if_modified_since) = if_modified_since.to_str()
+            && let Ok(if_modified_since) = httpdate::parse_http_date(if_modified_since)
+        {
+            return self.last_modified <= if_modified_since;
+        }
+
+        false
+    }
+
+    pub fn respond(&self, request_headers: &HeaderMap) -> Response<Body> {
+        let accept_encoding = request_headers.get(ACCEPT_ENCODING).and_then(|v| v.to_str().ok());
+
+        let body = match negotiate_encoding(accept_encoding) {
+            Some(encoding) => self.body(encoding).or_else(|| self.body(ContentEncoding::Identity)).unwrap(),
+            None => return StatusCode::NOT_ACCEPTABLE.into_response(),
+        };
+
+        let mut response_headers = HeaderMap::new();
+
+        response_headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+        response_headers.insert(LAST_MODIFIED, HeaderValue::from_str(&httpdate::fmt_http_date(self.last_modified)).unwrap());
+        response_headers.insert(ETAG, HeaderValue::from_str(&self.etag(body.encoding)).unwrap());
+
+        if self.is_not_modified(request_headers) {
+            return (StatusCode::NOT_MODIFIED, response_headers).into_response();
+        }
+
+        response_headers.insert(CONTENT_TYPE, HeaderValue::from_static(self.content_type));
+
+        if body.encoding != ContentEncoding::Identity {
+            response_headers.insert(CONTENT_ENCODING, HeaderValue::from_str(&body.encoding.to_string()).unwrap());
+        }
+
+        (response_headers, body.data.clone()).into_response()
+    }
+}
+
+impl Default for CachedContent {
+    fn default() -> Self {
+        CachedContent::new("text/plain", Bytes::new(), SystemTime::now())
+    }
+}
+
+// HTTP dates only carry whole seconds, so sub-second precision would make
+// If-Modified-Since comparisons always fail.
+fn truncate_to_seconds(time: SystemTime) -> SystemTime {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => UNIX_EPOCH + Duration::from_secs(duration.as_secs()),
+        Err(_) => UNIX_EPOCH,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (key, value) in pairs {
+            map.insert(*key, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    fn sample_content() -> CachedContent {
+        let body = "{\"roas\": []}\n".repeat(64);
+        CachedContent::new("application/json", body, UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+    }
+
+    #[test]
+    fn test_negotiate_missing_header_is_identity() {
+        assert_eq!(negotiate_encoding(None), Some(ContentEncoding::Identity));
+        assert_eq!(negotiate_encoding(Some("")), Some(ContentEncoding::Identity));
+    }
+
+    #[test]
+    fn test_negotiate_prefers_brotli_on_tie() {
+        assert_eq!(negotiate_encoding(Some("gzip, deflate, br, zstd")), Some(ContentEncoding::Brotli));
+        assert_eq!(negotiate_encoding(Some("gzip, zstd")), Some(ContentEncoding::Zstd));
+        assert_eq!(negotiate_encoding(Some("*")), Some(ContentEncoding::Brotli));
+    }
+
+    #[test]
+    fn test_negotiate_respects_quality() {
+        assert_eq!(negotiate_encoding(Some("br;q=0.5, gzip;q=0.9")), Some(ContentEncoding::Gzip));
+        assert_eq!(negotiate_encoding(Some("br;q=0, gzip;q=0")), Some(ContentEncoding::Identity));
+        assert_eq!(negotiate_encoding(Some("deflate")), Some(ContentEncoding::Identity));
+    }
+
+    #[test]
+    fn test_negotiate_identity_refused() {
+        assert_eq!(negotiate_encoding(Some("identity;q=0")), None);
+        assert_eq!(negotiate_encoding(Some("*;q=0")), None);
+        assert_eq!(negotiate_encoding(Some("gzip, identity;q=0")), Some(ContentEncoding::Gzip));
+    }
+
+    #[test]
+    fn test_etag_is_stable_and_content_derived() {
+        let a = sample_content();
+        let b = sample_content();
+        let c = CachedContent::new("application/json", "other", SystemTime::now());
+
+        assert_eq!(a.etag(ContentEncoding::Identity), b.etag(ContentEncoding::Identity));
+        assert_ne!(a.etag(ContentEncoding::Identity), c.etag(ContentEncoding::Identity));
+        assert_ne!(a.etag(ContentEncoding::Identity), a.etag(ContentEncoding::Gzip));
+    }
+
+    #[test]
+    fn test_respond_compressed() {
+        let content = sample_content();
+        let response = content.respond(&headers(&[("accept-encoding", "gzip")]));
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(response.headers().get(ETAG).unwrap().to_str().unwrap(), content.etag(ContentEncoding::Gzip));
+        assert_eq!(response.headers().get(VARY).unwrap(), "Accept-Encoding");
+    }
+
+    #[test]
+    fn test_incompressible_content_falls_back_to_identity() {
+        let content = CachedContent::new("text/plain", "x", SystemTime::now());
+        let response = content.respond(&headers(&[("accept-encoding", "br")]));
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let content = sample_content();
+        let etag = content.etag(ContentEncoding::Identity);
+
+        let response = content.respond(&headers(&[("if-none-match", &etag)]));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.headers().get(ETAG).is_some());
+
+        let weak = format!("W/{}", etag);
+        let response = content.respond(&headers(&[("if-none-match", &weak)]));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = content.respond(&headers(&[("if-none-match", "\"something-else\"")]));
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = content.respond(&headers(&[("if-none-match", "*")]));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn test_if_modified_since() {
+        let content = sample_content();
+        let last_modified = httpdate::fmt_http_date(content.last_modified());
+        let earlier = httpdate::fmt_http_date(content.last_modified() - Duration::from_secs(60));
+
+        let response = content.respond(&headers(&[("if-modified-since", &last_modified)]));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = content.respond(&headers(&[("if-modified-since", &earlier)]));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_if_none_match_takes_precedence() {
+        let content = sample_content();
+        let last_modified = httpdate::fmt_http_date(content.last_modified());
+
+        let response = content.respond(&headers(&[
+            ("if-none-match", "\"something-else\""),
+            ("if-modified-since", &last_modified),
+        ]));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
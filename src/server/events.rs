@@ -0,0 +1,181 @@
+use crate::AppState;
+use axum::body::Body;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::convert::Infallible;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+// Events not yet delivered to a slow subscriber before it starts missing some
+const UPDATE_CHANNEL_CAPACITY: usize = 64;
+
+// Sent whenever ROACache or DNSCache is replaced. Versions match the history endpoints.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum UpdateEvent {
+    Roa {
+        version: u64,
+        commit: Option<String>,
+        roas: usize,
+        aspas: usize,
+        added: usize,
+        removed: usize,
+    },
+    Dns {
+        version: u64,
+        commit: Option<String>,
+        zones: usize,
+        records: usize,
+        added: usize,
+        removed: usize,
+        // Zones whose content changed, appeared or disappeared
+        changed_zones: Vec<String>,
+    },
+}
+
+impl UpdateEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            UpdateEvent::Roa { .. } => "roa",
+            UpdateEvent::Dns { .. } => "dns",
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        match self {
+            UpdateEvent::Roa { version, .. } | UpdateEvent::Dns { version, .. } => *version,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct UpdateBroadcaster(broadcast::Sender<UpdateEvent>);
+
+impl Default for UpdateBroadcaster {
+    fn default() -> Self {
+        UpdateBroadcaster(broadcast::Sender::new(UPDATE_CHANNEL_CAPACITY))
+    }
+}
+
+impl UpdateBroadcaster {
+    pub fn send(&self, event: UpdateEvent) {
+        // Nobody listening is not an error
+        let _ = self.0.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<UpdateEvent> {
+        self.0.subscribe()
+    }
+}
+
+// Sent first on every connection, so a client can tell whether it missed versions while disconnected
+fn current_versions(state: &AppState) -> serde_json::Value {
+    serde_json::json!({
+        "type": "versions",
+        "roa": state.roa_history.read().map(|h| h.last_version()).unwrap_or_default(),
+        "dns": state.dns_history.read().map(|h| h.last_version()).unwrap_or_default(),
+    })
+}
+
+fn lagged(skipped: u64) -> serde_json::Value {
+    serde_json::json!({ "type": "lagged", "skipped": skipped })
+}
+
+fn update_stream(state: &AppState) -> impl Stream<Item = Result<Event, Infallible>> + use<> {
+    // Subscribe before reading the versions, so an update in between is delivered rather than lost
+    let updates = state.updates.subscribe();
+    let first = Event::default().event("versions").json_data(current_versions(state)).unwrap_or_default();
+
+    let updates = BroadcastStream::new(updates).map(|event| {
+        let event = match event {
+            Ok(event) => Event::default()
+                .event(event.name())
+                .id(format!("{}-{}", event.name(), event.version()))
+                .json_data(&event),
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => Event::default().event("lagged").json_data(lagged(skipped)),
+        };
+
+        Ok(event.unwrap_or_default())
+    });
+
+    tokio_stream::once(Ok(first)).chain(updates)
+}
+
+pub async fn get_events(State(state): State<AppState>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    Sse::new(update_stream(&state)).keep_alive(KeepAlive::default())
+}
+
+pub async fn get_events_websocket(State(state): State<AppState>, upgrade: WebSocketUpgrade) -> Response<Body> {
+    upgrade.on_upgrade(move |socket| forward_updates(state, socket)).into_response()
+}
+
+// Same JSON as the SSE data fields, one text message per event. Messages from the client are ignored.
+async fn forward_updates(state: AppState, mut socket: WebSocket) {
+    let mut updates = state.updates.subscribe();
+
+    if socket.send(Message::text(current_versions(&state).to_string())).await.is_err() {
+        return;
+    }
+
+    loop {
+        let message = tokio::select! {
+            update = updates.recv() => match update {
+                Ok(event) => serde_json::to_string(&event).unwrap_or_default(),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => lagged(skipped).to_string(),
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            received = socket.recv() => match received {
+                Some(Ok(_)) => continue,
+                _ => return,
+            },
+        };
+
+        if socket.send(Message::text(message)).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    async fn next_chunk(body: &mut axum::body::BodyDataStream) -> String {
+        let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), body.next()).await.unwrap().unwrap().unwrap();
+        String::from_utf8(chunk.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sse_stream() {
+        let state = AppState::default();
+        state.roa_history.write().unwrap().push(None, Default::default(), 10);
+
+        let router = Router::new().route("/events", get(get_events)).with_state(state.clone());
+        let response = router.oneshot(axum::http::Request::get("/events").body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let mut body = response.into_body().into_data_stream();
+
+        // Sent after the client connected but before anything was read, still delivered after the versions
+        state.updates.send(UpdateEvent::Dns { version: 1, commit: None, zones: 1, records: 2, added: 2, removed: 0, changed_zones: Vec::new() });
+
+        assert_eq!(next_chunk(&mut body).await, "event: versions\ndata: {\"dns\":0,\"roa\":1,\"type\":\"versions\"}\n\n");
+        assert!(next_chunk(&mut body).await.starts_with("event: dns\nid: dns-1\n"));
+
+        state.updates.send(UpdateEvent::Roa { version: 2, commit: None, roas: 3, aspas: 0, added: 1, removed: 0 });
+
+        assert_eq!(
+            next_chunk(&mut body).await,
+            "event: roa\nid: roa-2\ndata: {\"type\":\"roa\",\"version\":2,\"commit\":null,\"roas\":3,\"aspas\":0,\"added\":1,\"removed\":0}\n\n"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use crate::model::history::History;
+use crate::AppState;
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct DiffQuery {
+    from: Option<u64>,
+    // Defaults to the latest version
+    to: Option<u64>,
+    // RFC 3339 time or unix timestamp, diffs from the version that was current at that time
+    since: Option<String>,
+}
+
+fn parse_since(since: &str) -> Option<i64> {
+    since
+        .parse::<i64>()
+        .ok()
+        .or_else(|| chrono::DateTime::parse_from_rfc3339(since).ok().map(|t| t.timestamp()))
+}
+
+fn versions_response<T: Ord + Clone>(history: &RwLock<History<T>>) -> Response<Body> {
+    let history = match history.read() {
+        Ok(history) => history,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    Json(serde_json::json!({
+        "latest": history.last_version(),
+        "versions": history.versions(),
+    })).into_response()
+}
+
+fn diff_response<T: Ord + Clone + Serialize>(history: &RwLock<History<T>>, query: &DiffQuery) -> Response<Body> {
+    let history = match history.read() {
+        Ok(history) => history,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let (Some(oldest), Some(latest)) = (history.oldest(), history.latest()) else {
+        return (StatusCode::NOT_FOUND, "No versions recorded yet").into_response();
+    };
+
+    let missing = |version: u64| {
+        let message = format!("Version {} is not in the retained history ({} to {})", version, oldest.info.version, latest.info.version);
+        (StatusCode::NOT_FOUND, message).into_response()
+    };
+
+    let to = match query.to {
+        Some(version) => match history.get(version) {
+            Some(entry) => entry,
+            None => return missing(version),
+        },
+        None => latest,
+    };
+
+    // When the history does not reach back far enough the diff starts at the oldest retained version
+    let (from, truncated) = match (query.from, query.since.as_deref()) {
+        (Some(version), None) => match history.get(version) {
+            Some(entry) => (entry, false),
+            None => return missing(version),
+        },
+        (None, Some(since)) => match parse_since(since) {
+            Some(timestamp) => match history.at(timestamp) {
+                Some(entry) => (entry, false),
+                None => (oldest, true),
+            },
+            None => return (StatusCode::BAD_REQUEST, format!("Invalid since {}, expected RFC 3339 or a unix timestamp", since)).into_response(),
+        },
+        _ => return (StatusCode::BAD_REQUEST, "Exactly one of from or since is required").into_response(),
+    };
+
+    let diff = History::diff(from, to);
+
+    Json(serde_json::json!({
+        "from": diff.from,
+        "to": diff.to,
+        "truncated": truncated,
+        "added": diff.added,
+        "removed": diff.removed,
+    })).into_response()
+}
+
+pub async fn get_roa_versions(State(state): State<AppState>) -> Response<Body> {
+    versions_response(&state.roa_history)
+}
+
+pub async fn get_roa_diff(State(state): State<AppState>, Query(query): Query<DiffQuery>) -> Response<Body> {
+    diff_response(&state.roa_history, &query)
+}
+
+pub async fn get_dns_versions(State(state): State<AppState>) -> Response<Body> {
+    versions_response(&state.dns_history)
+}
+
+pub async fn get_dns_diff(State(state): State<AppState>, Query(query): Query<DiffQuery>) -> Response<Body> {
+    diff_response(&state.dns_history, &query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    async fn body_json(response: Response<Body>) -> (StatusCode, serde_json::Value) {
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_diff_response() {
+        let history = RwLock::new(History::default());
+
+        assert_eq!(diff_response(&history, &DiffQuery { from: Some(1), ..Default::default() }).status(), StatusCode::NOT_FOUND);
+
+        for items in [vec!["a", "b"], vec!["b", "c"], vec!["c", "d"]] {
+            history.write().unwrap().push(None, items.into_iter().map(String::from).collect::<BTreeSet<_>>(), 2);
+        }
+
+        let (status, body) = body_json(diff_response(&history, &DiffQuery { from: Some(2), ..Default::default() })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["added"], serde_json::json!(["d"]));
+        assert_eq!(body["removed"], serde_json::json!(["b"]));
+        assert_eq!(body["truncated"], false);
+
+        let (_, body) = body_json(diff_response(&history, &DiffQuery { since: Some("2000-01-01T00:00:00Z".to_string()), ..Default::default() })).await;
+        assert_eq!(body["from"]["version"], 2);
+        assert_eq!(body["truncated"], true);
+
+        assert_eq!(diff_response(&history, &DiffQuery { from: Some(1), ..Default::default() }).status(), StatusCode::NOT_FOUND);
+        assert_eq!(diff_response(&history, &DiffQuery::default()).status(), StatusCode::BAD_REQUEST);
+        assert_eq!(diff_response(&history, &DiffQuery { since: Some("yesterday".to_string()), ..Default::default() }).status(), StatusCode::BAD_REQUEST);
+    }
+}
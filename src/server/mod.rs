@@ -0,0 +1,348 @@
+pub mod admin;
+pub mod content;
+pub mod events;
+pub mod history;
+pub mod rdap;
+pub mod revision;
+pub mod whois;
+
+use crate::model::health::HealthProblemKind;
+use crate::model::output::ROA;
+use crate::model::registry::{parse_query_prefix, Registry};
+use crate::formatter::filter::{format_filter, FilterFormat};
+use crate::formatter::roa::RoaFormat;
+use crate::model::asn::parse_asn;
+use crate::parser::filter::expand_set;
+use crate::parser::dns_lint::DnsLintKind;
+use crate::parser::entity::{lookup_asn, lookup_mntner, EntitySummary, KnownProblems};
+use crate::parser::lookup::lookup_prefix;
+use crate::parser::route::DiagnosticAction;
+use crate::scheduler::task_status_snapshot;
+use crate::server::revision::{select_state, RevisionQuery, RevisionTarget};
+use crate::{AppConfig, AppState};
+use axum::body::Body;
+use axum::extract::{Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use axum::routing::{get, post, MethodRouter};
+use axum::Router;
+use serde::Deserialize;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use strum::IntoEnumIterator;
+use tower::ServiceExt;
+
+// Every route with the configuration field its path comes from, validate_config checks
+// these for overlaps before build_router hands them to axum, which panics on conflicts
+pub fn routes(config: &AppConfig) -> Vec<(&'static str, String, MethodRouter<AppState>)> {
+    vec![
+        ("roa_endpoint", config.roa_endpoint.clone(), get(get_roa_json)),
+        ("dns_config_endpoint", config.dns_config_endpoint.clone(), get(get_dns_conf)),
+        ("dns_content_endpoint_directory", format!("{}/{{zone}}", config.dns_content_endpoint_directory), get(get_dns_zone)),
+        ("asn_endpoint_directory", format!("{}/{{asn}}", config.asn_endpoint_directory), get(get_asn)),
+        ("mntner_endpoint_directory", format!("{}/{{handle}}", config.mntner_endpoint_directory), get(get_mntner)),
+        ("roa_diagnostics_endpoint", config.roa_diagnostics_endpoint.clone(), get(get_roa_diagnostics)),
+        ("dns_lint_endpoint", config.dns_lint_endpoint.clone(), get(get_dns_lint)),
+        ("dns_health_endpoint_directory", config.dns_health_endpoint_directory.clone(), get(get_dns_health)),
+        ("dns_health_endpoint_directory", format!("{}/{{zone}}", config.dns_health_endpoint_directory), get(get_dns_health_zone)),
+        ("filter_endpoint_directory", format!("{}/{{set}}", config.filter_endpoint_directory), get(get_filter)),
+        ("history_endpoint_directory", format!("{}/roa", config.history_endpoint_directory), get(history::get_roa_versions)),
+        ("history_endpoint_directory", format!("{}/roa/diff", config.history_endpoint_directory), get(history::get_roa_diff)),
+        ("history_endpoint_directory", format!("{}/dns", config.history_endpoint_directory), get(history::get_dns_versions)),
+        ("history_endpoint_directory", format!("{}/dns/diff", config.history_endpoint_directory), get(history::get_dns_diff)),
+        ("events_endpoint", config.events_endpoint.clone(), get(events::get_events)),
+        ("events_websocket_endpoint", config.events_websocket_endpoint.clone(), get(events::get_events_websocket)),
+        ("status_endpoint", config.status_endpoint.clone(), get(get_status)),
+        ("lookup_endpoint_directory", format!("{}/{{*query}}", config.lookup_endpoint_directory), get(get_lookup)),
+        ("rdap_endpoint_directory", format!("{}/ip/{{*query}}", config.rdap_endpoint_directory), get(rdap::get_ip)),
+        ("rdap_endpoint_directory", format!("{}/autnum/{{query}}", config.rdap_endpoint_directory), get(rdap::get_autnum)),
+        ("rdap_endpoint_directory", format!("{}/domain/{{query}}", config.rdap_endpoint_directory), get(rdap::get_domain)),
+        ("rdap_endpoint_directory", format!("{}/entity/{{query}}", config.rdap_endpoint_directory), get(rdap::get_entity)),
+        ("rdap_endpoint_directory", format!("{}/{{*query}}", config.rdap_endpoint_directory), get(rdap::not_found)),
+        ("admin_refresh_endpoint", config.admin_refresh_endpoint.clone(), post(admin::post_refresh)),
+        ("webhook_endpoint", config.webhook_endpoint.clone(), post(admin::post_webhook)),
+    ]
+}
+
+pub fn build_router(state: AppState, config: &AppConfig) -> Router {
+    routes(config)
+        .into_iter()
+        .fold(Router::new(), |router, (_, path, handler)| router.route(&path, handler))
+        .with_state(state)
+}
+
+// Dispatches every request to whatever router is currently installed, so that
+// a configuration reload can swap the routes without restarting the listener.
+pub fn reloadable_router(current: Arc<RwLock<Router>>) -> Router {
+    Router::new().fallback(move |request: Request| {
+        let router = current.read().unwrap().clone();
+
+        async move {
+            match router.oneshot(request).await {
+                Ok(response) => response,
+                Err(e) => match e {},
+            }
+        }
+    })
+}
+
+#[derive(Deserialize)]
+struct RoaQuery {
+    format: Option<String>,
+    revision: Option<String>,
+}
+
+async fn get_roa_json(State(state): State<AppState>, Query(query): Query<RoaQuery>, headers: HeaderMap) -> Response<Body> {
+    let format = match query.format.as_deref().map(RoaFormat::from_str).unwrap_or(Ok(RoaFormat::Json)) {
+        Ok(format) => format,
+        Err(_) => {
+            let formats = RoaFormat::iter().map(|f| f.to_string()).collect::<Vec<_>>().join(", ");
+            return (StatusCode::BAD_REQUEST, format!("Unknown format, expected one of: {}", formats)).into_response();
+        }
+    };
+
+    let state = match select_state(state, query.revision.as_deref(), RevisionTarget::Roa).await {
+        Ok(state) => state,
+        Err(response) => return response,
+    };
+
+    let data = match state.roa_data.read() {
+        Ok(data) => data,
+        Err(_) => {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match data.formatted_content.get(&format) {
+        Some(content) => content.respond(&headers),
+        None => data.json_content.respond(&headers),
+    }
+}
+
+async fn get_dns_conf(State(state): State<AppState>, Query(query): Query<RevisionQuery>, headers: HeaderMap) -> Response<Body> {
+    let state = match select_state(state, query.revision.as_deref(), RevisionTarget::Dns).await {
+        Ok(state) => state,
+        Err(response) => return response,
+    };
+
+    let data = match state.dns_data.read() {
+        Ok(data) => data,
+        Err(_) => {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    data.config_content.respond(&headers)
+}
+
+async fn get_dns_zone(
+    State(state): State<AppState>,
+    axum::extract::Path(zone_name): axum::extract::Path<String>,
+    Query(query): Query<RevisionQuery>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let state = match select_state(state, query.revision.as_deref(), RevisionTarget::Dns).await {
+        Ok(state) => state,
+        Err(response) => return response,
+    };
+
+    let data = match state.dns_data.read() {
+        Ok(data) => data,
+        Err(_) => {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match data.content.get(&zone_name) {
+        Some(zone_content) => zone_content.respond(&headers),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn get_lookup(
+    State(state): State<AppState>,
+    axum::extract::Path(query): axum::extract::Path<String>,
+) -> Response<Body> {
+    let prefix = match parse_query_prefix(&query) {
+        Some(prefix) => prefix,
+        None => return (StatusCode::BAD_REQUEST, format!("'{}' is not an IP address or prefix", query)).into_response(),
+    };
+
+    let (registry, roa_data) = match (state.registry.read(), state.roa_data.read()) {
+        (Ok(registry), Ok(roa_data)) => (registry, roa_data),
+        _ => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    Json(lookup_prefix(&registry, &roa_data.index, &prefix)).into_response()
+}
+
+fn entity_response(state: &AppState, lookup: impl FnOnce(&Registry, &[ROA], KnownProblems) -> EntitySummary) -> Response<Body> {
+    let (registry, roa_data, dns_data) = match (state.registry.read(), state.roa_data.read(), state.dns_data.read()) {
+        (Ok(registry), Ok(roa_data), Ok(dns_data)) => (registry, roa_data, dns_data),
+        _ => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let known = KnownProblems { routes: &roa_data.diagnostics, delegations: &dns_data.lint };
+    let summary = lookup(&registry, &roa_data.output.roas, known);
+
+    if summary.is_empty() {
+        return (StatusCode::NOT_FOUND, format!("Nothing found for {}", summary.query)).into_response();
+    }
+
+    Json(summary).into_response()
+}
+
+async fn get_asn(
+    State(state): State<AppState>,
+    axum::extract::Path(query): axum::extract::Path<String>,
+) -> Response<Body> {
+    let Some(asn) = parse_asn(&query) else {
+        return (StatusCode::BAD_REQUEST, format!("'{}' is not an AS number", query)).into_response();
+    };
+
+    entity_response(&state, |registry, roas, known| lookup_asn(registry, roas, known, asn))
+}
+
+async fn get_mntner(
+    State(state): State<AppState>,
+    axum::extract::Path(handle): axum::extract::Path<String>,
+) -> Response<Body> {
+    entity_response(&state, |registry, roas, known| lookup_mntner(registry, roas, known, &handle))
+}
+
+async fn get_roa_diagnostics(State(state): State<AppState>) -> Response<Body> {
+    let data = match state.roa_data.read() {
+        Ok(data) => data,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let count = |action| data.diagnostics.iter().filter(|d| d.action == action).count();
+
+    Json(serde_json::json!({
+        "rejected": count(DiagnosticAction::Rejected),
+        "corrected": count(DiagnosticAction::Corrected),
+        "flagged": count(DiagnosticAction::Flagged),
+        "diagnostics": data.diagnostics,
+    })).into_response()
+}
+
+async fn get_dns_lint(State(state): State<AppState>) -> Response<Body> {
+    let data = match state.dns_data.read() {
+        Ok(data) => data,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let counts = DnsLintKind::iter()
+        .map(|kind| (kind.to_string(), data.lint.iter().filter(|p| p.kind == kind).count()))
+        .collect::<std::collections::BTreeMap<_, _>>();
+
+    Json(serde_json::json!({
+        "counts": counts,
+        "problems": data.lint,
+    })).into_response()
+}
+
+async fn get_dns_health(State(state): State<AppState>) -> Response<Body> {
+    let data = match state.dns_health.read() {
+        Ok(data) => data,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let problems = data.domains.values().flat_map(|health| &health.problems).collect::<Vec<_>>();
+    let counts = HealthProblemKind::iter()
+        .map(|kind| (kind.to_string(), problems.iter().filter(|p| p.kind == kind).count()))
+        .collect::<std::collections::BTreeMap<_, _>>();
+
+    Json(serde_json::json!({
+        "last_checked": data.last_checked,
+        "checked": data.domains.len(),
+        "counts": counts,
+        "failing": data.domains.values().filter(|health| !health.healthy).collect::<Vec<_>>(),
+    })).into_response()
+}
+
+async fn get_dns_health_zone(
+    State(state): State<AppState>,
+    axum::extract::Path(zone): axum::extract::Path<String>,
+) -> Response<Body> {
+    let data = match state.dns_health.read() {
+        Ok(data) => data,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    match data.domains.get(zone.trim_end_matches('.').to_lowercase().as_str()) {
+        Some(health) => Json(health).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("No health check for {}", zone)).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct FilterQuery {
+    format: Option<String>,
+}
+
+async fn get_filter(
+    State(state): State<AppState>,
+    axum::extract::Path(set): axum::extract::Path<String>,
+    Query(query): Query<FilterQuery>,
+) -> Response<Body> {
+    let format = match query.format.as_deref().map(FilterFormat::from_str).unwrap_or(Ok(FilterFormat::Bird2)) {
+        Ok(format) => format,
+        Err(_) => {
+            let formats = FilterFormat::iter().map(|f| f.to_string()).collect::<Vec<_>>().join(", ");
+            return (StatusCode::BAD_REQUEST, format!("Unknown format, expected one of: {}", formats)).into_response();
+        }
+    };
+
+    let registry = match state.registry.read() {
+        Ok(registry) => registry,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    match expand_set(&registry, &set, state.config.get().filter_max_depth) {
+        Ok(expansion) => ([(header::CONTENT_TYPE, "text/plain")], format_filter(&expansion, format)).into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e).into_response(),
+    }
+}
+
+async fn get_status(State(state): State<AppState>) -> Response<Body> {
+    let (roa_last_updated, roa_count, roa_diagnostics, roa_sources) = match state.roa_data.read() {
+        Ok(data) => (
+            chrono::DateTime::<chrono::Utc>::from(data.last_updated).to_rfc3339(),
+            data.output.roas.len(),
+            data.diagnostics.len(),
+            data.sources.clone(),
+        ),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let (dns_last_updated, zone_count, dns_lint) = match state.dns_data.read() {
+        Ok(data) => (chrono::DateTime::<chrono::Utc>::from(data.last_updated).to_rfc3339(), data.content.len(), data.lint.len()),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let (health_last_checked, health_failing) = match state.dns_health.read() {
+        Ok(data) => (data.last_checked.clone(), data.domains.values().filter(|health| !health.healthy).count()),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    Json(serde_json::json!({
+        "roa": {
+            "last_updated": roa_last_updated,
+            "count": roa_count,
+            "diagnostics": roa_diagnostics,
+            "sources": roa_sources,
+        },
+        "dns": {
+            "last_updated": dns_last_updated,
+            "zones": zone_count,
+            "lint": dns_lint,
+            "health": {
+                "last_checked": health_last_checked,
+                "failing": health_failing,
+            },
+        },
+        "tasks": task_status_snapshot(&state),
+        "config": state.reload_status.read().map(|s| s.clone()).unwrap_or_default(),
+    })).into_response()
+}
@@ -0,0 +1,424 @@
+use crate::model::asn::parse_asn;
+use crate::model::record::RecordFile;
+use crate::model::registry::{parse_query_prefix, Registry};
+use crate::AppState;
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::header::{ACCESS_CONTROL_ALLOW_ORIGIN, CONTENT_TYPE, HOST};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde_json::{json, Value};
+use std::net::IpAddr;
+
+const RDAP_CONTENT_TYPE: &str = "application/rdap+json";
+
+type LookupResult = Result<Value, (StatusCode, String)>;
+
+// Contact attribute -> RDAP entity role
+const ENTITY_ROLES: [(&str, &str); 5] = [
+    ("mnt-by", "registrant"),
+    ("admin-c", "administrative"),
+    ("tech-c", "technical"),
+    ("zone-c", "technical"),
+    ("abuse-c", "abuse"),
+];
+
+fn rdap_response(status: StatusCode, body: Value) -> Response<Body> {
+    (
+        status,
+        [(CONTENT_TYPE, RDAP_CONTENT_TYPE), (ACCESS_CONTROL_ALLOW_ORIGIN, "*")],
+        body.to_string(),
+    ).into_response()
+}
+
+fn notices() -> Value {
+    json!([{
+        "title": "Source",
+        "description": ["Objects are generated from the dn42 registry."],
+    }])
+}
+
+fn error_response(status: StatusCode, description: &str) -> Response<Body> {
+    rdap_response(status, json!({
+        "rdapConformance": ["rdap_level_0"],
+        "errorCode": status.as_u16(),
+        "title": status.canonical_reason().unwrap_or(""),
+        "description": [description],
+        "notices": notices(),
+    }))
+}
+
+fn self_link(base: &str, path: &str) -> Value {
+    let href = format!("{}/{}", base, path);
+
+    json!([{
+        "value": href,
+        "rel": "self",
+        "href": href,
+        "type": RDAP_CONTENT_TYPE,
+    }])
+}
+
+fn events(registry: &Registry) -> Value {
+    match registry.last_updated() {
+        Some(last_updated) => json!([{
+            "eventAction": "last update of RDAP database",
+            "eventDate": chrono::DateTime::<chrono::Utc>::from(last_updated).to_rfc3339(),
+        }]),
+        None => json!([]),
+    }
+}
+
+fn remarks(record: &RecordFile) -> Value {
+    let description = record.get_attributes("descr")
+        .chain(record.get_attributes("remarks"))
+        .collect::<Vec<_>>();
+
+    if description.is_empty() {
+        json!([])
+    } else {
+        json!([{ "description": description }])
+    }
+}
+
+fn vcard(record: &RecordFile) -> Value {
+    let kind = match record.object_type() {
+        "person" => "individual",
+        "organisation" => "org",
+        _ => "group",
+    };
+
+    let name = record.get_attribute(record.object_type()).unwrap_or(record.primary_key());
+
+    let mut properties = vec![
+        json!(["version", {}, "text", "4.0"]),
+        json!(["fn", {}, "text", name]),
+        json!(["kind", {}, "text", kind]),
+    ];
+
+    for email in record.get_attributes("e-mail") {
+        properties.push(json!(["email", {}, "text", email]));
+    }
+
+    for contact in record.get_attributes("contact") {
+        properties.push(json!(["contact-uri", {}, "uri", contact]));
+    }
+
+    json!(["vcard", properties])
+}
+
+fn entity(registry: &Registry, base: &str, handle: &str, roles: Vec<&str>) -> Value {
+    let mut entity = json!({
+        "objectClassName": "entity",
+        "handle": handle,
+        "links": self_link(base, &format!("entity/{}", handle)),
+    });
+
+    if !roles.is_empty() {
+        entity["roles"] = json!(roles);
+    }
+
+    if let Some(record) = find_entity(registry, handle) {
+        entity["vcardArray"] = vcard(record);
+    }
+
+    entity
+}
+
+fn entities(registry: &Registry, base: &str, record: &RecordFile) -> Value {
+    // handle -> roles, in order of first appearance
+    let mut handles: Vec<(&str, Vec<&str>)> = Vec::new();
+
+    for (attribute, role) in ENTITY_ROLES {
+        for handle in record.get_attributes(attribute) {
+            match handles.iter_mut().find(|(h, _)| h.eq_ignore_ascii_case(handle)) {
+                Some((_, roles)) if !roles.contains(&role) => roles.push(role),
+                Some(_) => {}
+                None => handles.push((handle, vec![role])),
+            }
+        }
+    }
+
+    Value::Array(handles.into_iter().map(|(handle, roles)| entity(registry, base, handle, roles)).collect())
+}
+
+fn common(registry: &Registry, base: &str, record: &RecordFile, object_class: &str, path: &str) -> Value {
+    json!({
+        "rdapConformance": ["rdap_level_0", "cidr0"],
+        "objectClassName": object_class,
+        "handle": record.primary_key(),
+        "links": self_link(base, path),
+        "events": events(registry),
+        "entities": entities(registry, base, record),
+        "remarks": remarks(record),
+        "notices": notices(),
+    })
+}
+
+fn find_entity<'a>(registry: &'a Registry, handle: &str) -> Option<&'a RecordFile> {
+    ["person", "role", "mntner", "organisation"]
+        .iter()
+        .find_map(|object_type| registry.get(object_type, handle))
+}
+
+pub fn ip_network(registry: &Registry, base: &str, query: &str) -> LookupResult {
+    let prefix = parse_query_prefix(query)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("'{}' is not an IP address or prefix", query)))?;
+
+    let covering = registry.covering(&["inetnum", "inet6num"], &prefix);
+
+    let (network, record) = covering
+        .first()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No inetnum covers {}", query)))?;
+
+    let mut body = common(registry, base, record, "ip network", &format!("ip/{}", network));
+
+    let version = if network.network().is_ipv4() { "v4" } else { "v6" };
+
+    body["startAddress"] = json!(network.network().to_string());
+    body["endAddress"] = json!(network.last_address().to_string());
+    body["ipVersion"] = json!(version);
+
+    if let Some(name) = record.get_attribute("netname") {
+        body["name"] = json!(name);
+    }
+
+    if let Some(country) = record.get_attribute("country") {
+        body["country"] = json!(country);
+    }
+
+    body["cidr0_cidrs"] = json!([{
+        format!("{}prefix", version): network.network().to_string(),
+        "length": network.prefix_len(),
+    }]);
+
+    if let Some((parent, _)) = covering.get(1) {
+        body["parentHandle"] = json!(parent.to_string());
+    }
+
+    Ok(body)
+}
+
+pub fn autnum(registry: &Registry, base: &str, query: &str) -> LookupResult {
+    let asn = parse_asn(query)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("'{}' is not an AS number", query)))?;
+
+    let record = registry
+        .get("aut-num", &format!("AS{}", asn))
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("AS{} is not registered", asn)))?;
+
+    let mut body = common(registry, base, record, "autnum", &format!("autnum/{}", asn));
+
+    body["startAutnum"] = json!(asn);
+    body["endAutnum"] = json!(asn);
+
+    if let Some(name) = record.get_attribute("as-name") {
+        body["name"] = json!(name);
+    }
+
+    Ok(body)
+}
+
+fn nameserver(nserver: &str) -> Value {
+    // "ns1.example.dn42 172.20.0.53", the address is optional glue
+    let mut parts = nserver.split_whitespace();
+    let name = parts.next().unwrap_or("");
+
+    let (v4, v6): (Vec<IpAddr>, Vec<IpAddr>) = parts
+        .filter_map(|address| address.parse::<IpAddr>().ok())
+        .partition(|address| address.is_ipv4());
+
+    // No self link, nameserver lookups are not served
+    let mut nameserver = json!({
+        "objectClassName": "nameserver",
+        "ldhName": name,
+    });
+
+    if !v4.is_empty() || !v6.is_empty() {
+        nameserver["ipAddresses"] = json!({ "v4": v4, "v6": v6 });
+    }
+
+    nameserver
+}
+
+fn ds_data(ds_rdata: &str) -> Option<Value> {
+    // "<key tag> <algorithm> <digest type> <digest>"
+    let parts = ds_rdata.split_whitespace().collect::<Vec<_>>();
+
+    if parts.len() < 4 {
+        return None;
+    }
+
+    Some(json!({
+        "keyTag": parts[0].parse::<u16>().ok()?,
+        "algorithm": parts[1].parse::<u8>().ok()?,
+        "digestType": parts[2].parse::<u8>().ok()?,
+        "digest": parts[3..].concat(),
+    }))
+}
+
+pub fn domain(registry: &Registry, base: &str, query: &str) -> LookupResult {
+    let name = query.trim_end_matches('.').to_lowercase();
+
+    let record = registry
+        .get("domain", &name)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Domain {} is not registered", name)))?;
+
+    let mut body = common(registry, base, record, "domain", &format!("domain/{}", name));
+
+    let ds = record.get_attributes("ds-rdata").filter_map(ds_data).collect::<Vec<_>>();
+
+    body["ldhName"] = json!(name);
+    body["nameservers"] = Value::Array(record.get_attributes("nserver").map(nameserver).collect());
+    body["secureDNS"] = json!({
+        "delegationSigned": !ds.is_empty(),
+        "dsData": ds,
+    });
+
+    Ok(body)
+}
+
+pub fn entity_object(registry: &Registry, base: &str, handle: &str) -> LookupResult {
+    let record = find_entity(registry, handle)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Entity {} is not registered", handle)))?;
+
+    let mut body = common(registry, base, record, "entity", &format!("entity/{}", record.primary_key()));
+
+    body["vcardArray"] = vcard(record);
+
+    Ok(body)
+}
+
+// Links must be absolute URIs (RFC 9083 section 4.2)
+fn base_url(state: &AppState, headers: &HeaderMap) -> String {
+    let config = state.config.get();
+
+    if !config.rdap_base_url.is_empty() {
+        return config.rdap_base_url.trim_end_matches('/').to_string();
+    }
+
+    let host = headers.get(HOST).and_then(|host| host.to_str().ok()).unwrap_or("localhost");
+
+    format!("http://{}{}", host, config.rdap_endpoint_directory)
+}
+
+fn respond(
+    state: &AppState,
+    headers: &HeaderMap,
+    query: &str,
+    lookup: fn(&Registry, &str, &str) -> LookupResult,
+) -> Response<Body> {
+    let base = base_url(state, headers);
+
+    let result = match state.registry.read() {
+        Ok(registry) => lookup(&registry, &base, query),
+        Err(_) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Registry unavailable"),
+    };
+
+    match result {
+        Ok(body) => rdap_response(StatusCode::OK, body),
+        Err((status, description)) => error_response(status, &description),
+    }
+}
+
+pub async fn get_ip(State(state): State<AppState>, headers: HeaderMap, Path(query): Path<String>) -> Response<Body> {
+    respond(&state, &headers, &query, ip_network)
+}
+
+pub async fn get_autnum(State(state): State<AppState>, headers: HeaderMap, Path(query): Path<String>) -> Response<Body> {
+    respond(&state, &headers, &query, autnum)
+}
+
+pub async fn get_domain(State(state): State<AppState>, headers: HeaderMap, Path(query): Path<String>) -> Response<Body> {
+    respond(&state, &headers, &query, domain)
+}
+
+pub async fn get_entity(State(state): State<AppState>, headers: HeaderMap, Path(query): Path<String>) -> Response<Body> {
+    respond(&state, &headers, &query, entity_object)
+}
+
+pub async fn not_found() -> Response<Body> {
+    error_response(StatusCode::NOT_FOUND, "Unsupported RDAP query")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::registry::tests::{record, test_registry};
+
+    #[test]
+    fn test_ip_network() {
+        let registry = test_registry();
+
+        let body = ip_network(&registry, "https://rdap.example.dn42/rdap", "172.20.0.1").unwrap();
+
+        assert_eq!(body["objectClassName"], "ip network");
+        assert_eq!(body["handle"], "172.20.0.0/24");
+        assert_eq!(body["startAddress"], "172.20.0.0");
+        assert_eq!(body["endAddress"], "172.20.0.255");
+        assert_eq!(body["ipVersion"], "v4");
+        assert_eq!(body["parentHandle"], "172.20.0.0/14");
+        assert_eq!(body["cidr0_cidrs"][0]["v4prefix"], "172.20.0.0");
+        assert_eq!(body["links"][0]["href"], "https://rdap.example.dn42/rdap/ip/172.20.0.0/24");
+        assert_eq!(body["entities"][0]["handle"], "EXAMPLE-MNT");
+        assert_eq!(body["entities"][0]["roles"], json!(["registrant"]));
+
+        assert_eq!(ip_network(&registry, "https://rdap.example.dn42/rdap", "fd00::1").unwrap_err().0, StatusCode::NOT_FOUND);
+        assert_eq!(ip_network(&registry, "https://rdap.example.dn42/rdap", "example").unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_autnum() {
+        let registry = test_registry();
+
+        for query in ["AS4242420000", "4242420000"] {
+            let body = autnum(&registry, "https://rdap.example.dn42/rdap", query).unwrap();
+
+            assert_eq!(body["startAutnum"], 4242420000u32);
+            assert_eq!(body["name"], "EXAMPLE-AS");
+
+            let roles = body["entities"].as_array().unwrap().iter()
+                .map(|e| (e["handle"].as_str().unwrap(), e["roles"].clone()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(roles, vec![("EXAMPLE-MNT", json!(["registrant"])), ("EXAMPLE-DN42", json!(["administrative"]))]);
+            assert_eq!(body["entities"][1]["vcardArray"][1][1][3], "Example Person");
+        }
+
+        assert_eq!(autnum(&registry, "https://rdap.example.dn42/rdap", "AS1").unwrap_err().0, StatusCode::NOT_FOUND);
+        assert_eq!(autnum(&registry, "https://rdap.example.dn42/rdap", "ASX").unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_domain() {
+        let registry = Registry::new(vec![
+            record("data/domain/example.dn42", "domain: example.dn42\nnserver: ns1.example.dn42 172.20.0.53\nnserver: ns2.example.dn42 fd00::53\nds-rdata: 12345 13 2 ABCDEF\nsource: DN42\n"),
+        ]);
+
+        let body = domain(&registry, "https://rdap.example.dn42/rdap", "Example.dn42.").unwrap();
+
+        assert_eq!(body["ldhName"], "example.dn42");
+        assert_eq!(body["nameservers"][0]["ldhName"], "ns1.example.dn42");
+        assert_eq!(body["nameservers"][0]["ipAddresses"]["v4"], json!(["172.20.0.53"]));
+        assert!(body["nameservers"][0].get("links").is_none());
+        assert_eq!(body["nameservers"][1]["ipAddresses"]["v6"], json!(["fd00::53"]));
+        assert_eq!(body["secureDNS"]["delegationSigned"], true);
+        assert_eq!(body["secureDNS"]["dsData"][0]["keyTag"], 12345);
+
+        assert_eq!(domain(&registry, "https://rdap.example.dn42/rdap", "other.dn42").unwrap_err().0, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_entity() {
+        let registry = test_registry();
+
+        let body = entity_object(&registry, "https://rdap.example.dn42/rdap", "example-dn42").unwrap();
+
+        assert_eq!(body["objectClassName"], "entity");
+        assert_eq!(body["handle"], "EXAMPLE-DN42");
+        assert_eq!(body["vcardArray"][0], "vcard");
+
+        assert!(entity_object(&registry, "https://rdap.example.dn42/rdap", "EXAMPLE-MNT").is_ok());
+        assert_eq!(entity_object(&registry, "https://rdap.example.dn42/rdap", "NOBODY").unwrap_err().0, StatusCode::NOT_FOUND);
+    }
+}
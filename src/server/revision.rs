@@ -0,0 +1,154 @@
+use crate::io::RegistrySource;
+use crate::task::dns::GenerateDNSAuthoritativeZonesTask;
+use crate::task::roa::GenerateRoaTask;
+use crate::task::Task;
+use crate::{AppConfig, AppState, ConfigHandle};
+use axum::body::Body;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+// Past revisions are generated one at a time, each one reads the whole registry
+static REVISION_GENERATION: Semaphore = Semaphore::const_new(1);
+
+// Generated states kept per resolved commit, so repeated requests at one revision generate once
+const REVISION_CACHE_SIZE: usize = 8;
+
+#[derive(Deserialize)]
+pub struct RevisionQuery {
+    pub revision: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionTarget {
+    Roa,
+    Dns,
+}
+
+impl RevisionTarget {
+    fn generate(self, state: AppState) -> anyhow::Result<()> {
+        match self {
+            RevisionTarget::Roa => GenerateRoaTask::new(state).run(),
+            RevisionTarget::Dns => GenerateDNSAuthoritativeZonesTask::new(state).run(),
+        }
+    }
+}
+
+struct CachedRevision {
+    target: RevisionTarget,
+    commit: String,
+    // The live configuration the state was generated with, a reload invalidates the entry
+    config: Arc<AppConfig>,
+    state: AppState,
+}
+
+// Least recently used first
+#[derive(Default)]
+pub struct RevisionCache(VecDeque<CachedRevision>);
+
+impl RevisionCache {
+    fn get(&mut self, target: RevisionTarget, commit: &str, config: &Arc<AppConfig>) -> Option<AppState> {
+        let index = self
+            .0
+            .iter()
+            .position(|entry| entry.target == target && entry.commit == commit && Arc::ptr_eq(&entry.config, config))?;
+
+        let entry = self.0.remove(index)?;
+        let state = entry.state.clone();
+        self.0.push_back(entry);
+
+        Some(state)
+    }
+
+    fn insert(&mut self, target: RevisionTarget, commit: String, config: Arc<AppConfig>, state: AppState) {
+        self.0.retain(|entry| entry.target != target || entry.commit != commit);
+        self.0.push_back(CachedRevision { target, commit, config, state });
+
+        while self.0.len() > REVISION_CACHE_SIZE {
+            self.0.pop_front();
+        }
+    }
+}
+
+// The live state, or one generated from the registry at ?revision=. Generating a
+// revision uses a separate state, so the live caches are not touched.
+pub async fn select_state(state: AppState, revision: Option<&str>, target: RevisionTarget) -> Result<AppState, Response<Body>> {
+    let Some(revision) = revision else {
+        return Ok(state);
+    };
+
+    let config = state.config.get();
+    let revision_config = config.at_revision(revision);
+
+    // Branch and tag names move, so the cache is keyed by the commit they point at now
+    let resolved = tokio::task::spawn_blocking(move || RegistrySource::from_config(&revision_config).map(|source| source.commit())).await;
+
+    let commit = match resolved {
+        Ok(Ok(Some(commit))) => commit,
+        Ok(Err(e)) => return Err((StatusCode::BAD_REQUEST, format!("{:#}", e)).into_response()),
+        _ => return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    if let Some(cached) = state.revision_cache.lock().unwrap().get(target, &commit, &config) {
+        return Ok(cached);
+    }
+
+    let Ok(_permit) = REVISION_GENERATION.acquire().await else {
+        return Err(StatusCode::SERVICE_UNAVAILABLE.into_response());
+    };
+
+    // Another request may have generated it while this one waited
+    if let Some(cached) = state.revision_cache.lock().unwrap().get(target, &commit, &config) {
+        return Ok(cached);
+    }
+
+    let generation_config = config.at_revision(&commit);
+
+    let generated = tokio::task::spawn_blocking(move || {
+        let state = AppState {
+            config: ConfigHandle::new(generation_config),
+            ..Default::default()
+        };
+
+        target.generate(state.clone()).map(|_| state)
+    }).await;
+
+    match generated {
+        Ok(Ok(generated)) => {
+            state.revision_cache.lock().unwrap().insert(target, commit, config, generated.clone());
+            Ok(generated)
+        }
+        Ok(Err(e)) => Err((StatusCode::BAD_REQUEST, format!("{:#}", e)).into_response()),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revision_cache() {
+        let config = Arc::new(AppConfig::default());
+        let mut cache = RevisionCache::default();
+
+        for index in 0..REVISION_CACHE_SIZE {
+            cache.insert(RevisionTarget::Roa, index.to_string(), config.clone(), AppState::default());
+        }
+
+        // Using the oldest entry keeps it, the next insert evicts the second oldest instead
+        assert!(cache.get(RevisionTarget::Roa, "0", &config).is_some());
+        cache.insert(RevisionTarget::Dns, "0".to_string(), config.clone(), AppState::default());
+
+        assert!(cache.get(RevisionTarget::Roa, "0", &config).is_some());
+        assert!(cache.get(RevisionTarget::Dns, "0", &config).is_some());
+        assert!(cache.get(RevisionTarget::Roa, "1", &config).is_none());
+
+        // A reloaded configuration does not reuse states generated with the previous one
+        let reloaded = Arc::new(AppConfig::default());
+        assert!(cache.get(RevisionTarget::Roa, "0", &reloaded).is_none());
+    }
+}
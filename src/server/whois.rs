@@ -0,0 +1,277 @@
+use crate::model::asn::parse_asn;
+use crate::model::record::RecordFile;
+use crate::model::registry::{parse_query_prefix, Registry, INVERSE_ATTRIBUTES};
+use crate::AppState;
+use anyhow::Context;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+const MAX_QUERY_LENGTH: u64 = 1024;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const RESPONSE_HEADER: &str = "% This is the dn42 whois query service.\n% Data is generated from the dn42 registry.\n\n";
+
+#[derive(Debug, PartialEq, Eq, Default)]
+struct WhoisQuery {
+    // -i attribute[,attribute]
+    inverse: Vec<String>,
+    // -T type[,type]
+    types: Vec<String>,
+    term: String,
+}
+
+fn split_list(value: Option<&str>, flag: &str) -> Result<Vec<String>, String> {
+    match value {
+        Some(value) => Ok(value.split(',').map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty()).collect()),
+        None => Err(format!("%ERROR:106: flag {} requires an argument", flag)),
+    }
+}
+
+fn parse_query(line: &str) -> Result<WhoisQuery, String> {
+    let mut query = WhoisQuery::default();
+    let mut terms = Vec::new();
+    let mut tokens = line.split_whitespace();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "-i" => query.inverse.extend(split_list(tokens.next(), token)?),
+            "-T" => query.types.extend(split_list(tokens.next(), token)?),
+            // Accepted for compatibility with common clients, filtering and grouping do not apply here
+            "-r" | "-B" | "-G" => {}
+            flag if flag.starts_with('-') && terms.is_empty() => {
+                return Err(format!("%ERROR:111: invalid option {}", flag));
+            }
+            term => terms.push(term),
+        }
+    }
+
+    if terms.is_empty() {
+        return Err("%ERROR:106: no search key specified".to_string());
+    }
+
+    query.term = terms.join(" ");
+
+    Ok(query)
+}
+
+fn lookup<'a>(registry: &'a Registry, term: &str) -> Vec<&'a RecordFile> {
+    if let Some(prefix) = parse_query_prefix(term) {
+        let mut objects = Vec::new();
+
+        objects.extend(registry.most_specific_inetnum(&prefix));
+        objects.extend(registry.most_specific_routes(&prefix));
+
+        return objects;
+    }
+
+    // Bare and asdot numbers are taken as AS numbers
+    if term.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && let Some(asn) = parse_asn(term)
+    {
+        return registry.find(&format!("AS{}", asn));
+    }
+
+    let objects = registry.find(term);
+
+    if !objects.is_empty() {
+        return objects;
+    }
+
+    // Names below a delegated domain resolve to the domain object
+    let mut name = term.trim_end_matches('.');
+
+    while let Some((_, parent)) = name.split_once('.') {
+        if let Some(domain) = registry.get("domain", parent) {
+            return vec![domain];
+        }
+
+        name = parent;
+    }
+
+    Vec::new()
+}
+
+fn answer_query(registry: &Registry, line: &str) -> String {
+    let mut response = RESPONSE_HEADER.to_string();
+
+    let query = match parse_query(line) {
+        Ok(query) => query,
+        Err(e) => {
+            response.push_str(&e);
+            response.push_str("\n\n");
+            return response;
+        }
+    };
+
+    let mut objects = Vec::new();
+
+    if query.inverse.is_empty() {
+        objects = lookup(registry, &query.term);
+    } else {
+        for attribute in &query.inverse {
+            if !INVERSE_ATTRIBUTES.contains(&attribute.as_str()) {
+                response.push_str(&format!("%ERROR:104: attribute {} is not inverse searchable\n\n", attribute));
+                return response;
+            }
+
+            objects.extend(registry.inverse(attribute, &query.term));
+        }
+    }
+
+    if !query.types.is_empty() {
+        objects.retain(|o| query.types.iter().any(|t| t == o.object_type()));
+    }
+
+    let mut seen = Vec::with_capacity(objects.len());
+    objects.retain(|o| {
+        let path = o.get_file_path();
+        if seen.contains(&path) {
+            return false;
+        }
+        seen.push(path);
+        true
+    });
+
+    if objects.is_empty() {
+        response.push_str("%ERROR:101: no entries found\n\n");
+        return response;
+    }
+
+    for object in objects {
+        response.push_str(object.content().trim_end());
+        response.push_str("\n\n");
+    }
+
+    response
+}
+
+async fn handle_connection(state: AppState, mut stream: TcpStream) -> anyhow::Result<()> {
+    let (reader, mut writer) = stream.split();
+    let mut reader = BufReader::new(reader.take(MAX_QUERY_LENGTH));
+
+    let mut line = String::new();
+    tokio::time::timeout(QUERY_TIMEOUT, reader.read_line(&mut line))
+        .await
+        .context("Timed out waiting for the query")??;
+
+    let response = {
+        let registry = state.registry.read().unwrap();
+        answer_query(&registry, line.trim())
+    };
+
+    // RFC 3912: the server closes the connection once the response is sent
+    writer.write_all(response.as_bytes()).await?;
+    writer.shutdown().await?;
+
+    Ok(())
+}
+
+pub async fn run_whois_server(state: AppState) -> anyhow::Result<()> {
+    let listen_address = state.config.get().whois_listen_address.clone();
+
+    if listen_address.is_empty() {
+        return Ok(());
+    }
+
+    let listener = TcpListener::bind(&listen_address)
+        .await
+        .with_context(|| format!("Failed to bind whois server to {}", listen_address))?;
+
+    info!("Whois server listening on: {}", listen_address);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Failed to accept whois connection: {:?}", e);
+                continue;
+            }
+        };
+
+        let state = state.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(state, stream).await {
+                warn!("Whois query from {} failed: {:?}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::registry::tests::test_registry;
+
+    fn object_keys(response: &str) -> Vec<&str> {
+        response
+            .lines()
+            .filter(|l| ["aut-num:", "mntner:", "person:", "inetnum:", "route:", "domain:"].iter().any(|t| l.starts_with(t)))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(parse_query("AS4242420000").unwrap().term, "AS4242420000");
+
+        let query = parse_query("-i mnt-by,admin-c -T route FOO-MNT").unwrap();
+        assert_eq!(query.inverse, vec!["mnt-by", "admin-c"]);
+        assert_eq!(query.types, vec!["route"]);
+        assert_eq!(query.term, "FOO-MNT");
+
+        assert!(parse_query("").is_err());
+        assert!(parse_query("-i").is_err());
+        assert!(parse_query("-x FOO").is_err());
+    }
+
+    #[test]
+    fn test_answer_asn_preserves_formatting() {
+        let registry = test_registry();
+
+        for query in ["AS4242420000", "as4242420000", "4242420000", "64734.12576"] {
+            let response = answer_query(&registry, query);
+            assert!(response.contains("aut-num: AS4242420000\nas-name: EXAMPLE-AS\n"), "{}", response);
+        }
+    }
+
+    #[test]
+    fn test_answer_address() {
+        let registry = test_registry();
+
+        let response = answer_query(&registry, "172.20.0.1");
+
+        assert_eq!(object_keys(&response), vec![
+            "inetnum: 172.20.0.0 - 172.20.0.255",
+            "route: 172.20.0.0/24",
+            "route: 172.20.0.0/24",
+        ]);
+    }
+
+    #[test]
+    fn test_answer_domain_and_subdomain() {
+        let registry = test_registry();
+
+        assert_eq!(object_keys(&answer_query(&registry, "example.dn42")), vec!["domain: example.dn42"]);
+        assert_eq!(object_keys(&answer_query(&registry, "www.example.dn42")), vec!["domain: example.dn42"]);
+    }
+
+    #[test]
+    fn test_answer_inverse() {
+        let registry = test_registry();
+
+        let response = answer_query(&registry, "-i mnt-by -T route EXAMPLE-MNT");
+        assert_eq!(object_keys(&response), vec!["route: 172.20.0.0/24"]);
+
+        let response = answer_query(&registry, "-i descr EXAMPLE-MNT");
+        assert!(response.contains("%ERROR:104"));
+    }
+
+    #[test]
+    fn test_answer_not_found() {
+        let registry = test_registry();
+
+        assert!(answer_query(&registry, "NOBODY-MNT").contains("%ERROR:101"));
+    }
+}
@@ -1,10 +1,27 @@
-use crate::formatter::dns_zone::format_dns_zone;
-use crate::io::get_records_from_dirs;
+use crate::formatter::dns_zone::{format_dns_record, format_dns_zone};
+use crate::io::RegistrySource;
+use crate::model::dns::DNSZone;
+use crate::model::history::ZoneRecord;
 use crate::parser::dns::{generate_reverse_zones, get_parsed_ns_records};
+use crate::parser::dns_lint::lint_delegations;
+use crate::server::content::CachedContent;
+use crate::server::events::UpdateEvent;
+use crate::task::git::SYNC_GIT_REPOSITORY_TASK_ID;
 use crate::task::Task;
 use crate::AppState;
-use std::path::Path;
-use tracing::warn;
+use std::collections::{BTreeSet, HashMap};
+use tracing::{info, warn};
+
+pub const GENERATE_DNS_TASK_ID: &str = "generate_dns";
+
+fn zone_records(zones: &HashMap<String, DNSZone>) -> BTreeSet<ZoneRecord> {
+    zones
+        .iter()
+        .flat_map(|(zone_name, zone)| {
+            zone.records().iter().map(|record| ZoneRecord { zone: zone_name.clone(), record: format_dns_record(record) })
+        })
+        .collect()
+}
 
 pub struct GenerateDNSAuthoritativeZonesTask {
     app_state: AppState,
@@ -21,43 +38,110 @@ impl Task for GenerateDNSAuthoritativeZonesTask {
         "Generate DNS Authoritative Zones"
     }
 
+    fn id(&self) -> &str {
+        GENERATE_DNS_TASK_ID
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        vec![SYNC_GIT_REPOSITORY_TASK_ID.to_string()]
+    }
+
     fn run(&self) -> anyhow::Result<()> {
         let state = &self.app_state;
+        let config = state.config.get();
 
-        let git_repo_local_path = Path::new(&state.config.git_repo_local_path);
+        let source = RegistrySource::from_config(&config)?;
 
-        let dns_zones = if git_repo_local_path.exists() {
-            let dns_directories = [
-                git_repo_local_path.join(&state.config.git_repo_dns_relative_path),
-            ];
+        let (dns_zones, lint) = if source.exists() {
+            let dns_records = source.records("DNS", &[&config.git_repo_dns_relative_path])?;
+            let inetnum_records = source.records("INETNUM", &[
+                &config.git_repo_inetnum_relative_path,
+                &config.git_repo_inet6num_relative_path,
+            ])?;
 
-            let inetnum_directories = [
-                git_repo_local_path.join(&state.config.git_repo_inetnum_relative_path),
-                git_repo_local_path.join(&state.config.git_repo_inet6num_relative_path),
-            ];
+            let mut dns_zones = get_parsed_ns_records(&dns_records, &config.dns_primary_master, &config.dns_responsible_party);
+            dns_zones.extend(generate_reverse_zones(&inetnum_records, &config.dns_primary_master, &config.dns_responsible_party));
 
-            let dns_records = get_records_from_dirs("DNS", dns_directories.iter())?;
-            let inetnum_records = get_records_from_dirs("INETNUM", inetnum_directories.iter())?;
+            let lint = lint_delegations(&dns_records, &inetnum_records);
 
-            let mut dns_zones = get_parsed_ns_records(&dns_records, &self.app_state.config.dns_primary_master, &self.app_state.config.dns_responsible_party);
-            dns_zones.extend(generate_reverse_zones(&inetnum_records, &self.app_state.config.dns_primary_master, &self.app_state.config.dns_responsible_party));
+            if !lint.is_empty() {
+                info!("Found {} delegation problems.", lint.len());
+            }
 
-            dns_zones
+            (dns_zones, lint)
         } else {
-            warn!("Git repository path {:?} does not exist. Skipping DNS forward zone generation.", git_repo_local_path);
+            warn!("Git repository path {:?} does not exist. Skipping DNS forward zone generation.", source.repo());
+
+            (Vec::default(), Vec::default())
+        };
+
+        // Kept up to date even when no zone changes, like the ROA diagnostics
+        state.dns_data.write().unwrap().lint = lint;
+
+        let now = std::time::SystemTime::now();
+
+        let mut zones = HashMap::with_capacity(dns_zones.len());
+        let mut zone_name_to_content = HashMap::with_capacity(dns_zones.len());
+        let mut changed_zones = Vec::new();
+
+        {
+            let previous = state.dns_data.read().unwrap();
+
+            for zone in dns_zones {
+                let zone_name = zone.origin().to_string();
+
+                // Unchanged zones keep their previous serial, content and ETag
+                if let (Some(previous_zone), Some(previous_content)) = (previous.zones.get(&zone_name), previous.content.get(&zone_name))
+                    && previous_zone.has_same_content(&zone)
+                {
+                    zones.insert(zone_name.clone(), previous_zone.clone());
+                    zone_name_to_content.insert(zone_name, previous_content.clone());
+                    continue;
+                }
+
+                changed_zones.push(zone_name.clone());
+                zone_name_to_content.insert(zone_name.clone(), CachedContent::new("text/plain", format_dns_zone(&zone), now));
+                zones.insert(zone_name, zone);
+            }
+
+            // Zones no longer generated count as changed too
+            changed_zones.extend(previous.zones.keys().filter(|zone_name| !zones.contains_key(*zone_name)).cloned());
+
+            if changed_zones.is_empty() {
+                info!("DNS zones unchanged, keeping cached output.");
+                return Ok(());
+            }
+        }
+
+        info!("{} of {} DNS zones changed.", changed_zones.len(), zones.len());
+        changed_zones.sort();
+
+        let mut zone_names = zones.keys().collect::<Vec<_>>();
+        zone_names.sort();
+
+        let config_content = CachedContent::new("application/json", serde_json::to_string(&zone_names)?, now);
+
+        let history_items = zone_records(&zones);
+        let (zone_count, records) = (zones.len(), history_items.len());
+
+        let (added, removed) = {
+            let mut data_lock = state.dns_data.write().unwrap();
+
+            let previous = zone_records(&data_lock.zones);
+            let changes = (history_items.difference(&previous).count(), previous.difference(&history_items).count());
+
+            data_lock.last_updated = now;
+            data_lock.zones = zones;
+            data_lock.content = zone_name_to_content;
+            data_lock.config_content = config_content;
 
-            Vec::default()
+            changes
         };
 
-        let zone_name_to_content = dns_zones
-            .into_iter()
-            .map(|zone| (zone.origin().to_string(), format_dns_zone(&zone)))
-            .collect::<std::collections::HashMap<String, _>>();
+        let commit = source.commit();
+        let version = state.dns_history.write().unwrap().push(commit.clone(), history_items, config.history_size);
 
-        let mut data_lock = state.dns_data.write().unwrap();
-        
-        data_lock.last_updated = std::time::SystemTime::now();
-        data_lock.content = zone_name_to_content;
+        state.updates.send(UpdateEvent::Dns { version, commit, zones: zone_count, records, added, removed, changed_zones });
 
         Ok(())
     }
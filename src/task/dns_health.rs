@@ -0,0 +1,444 @@
+use crate::io::{dns_query, RegistrySource};
+use crate::model::health::{DomainHealth, HealthProblem, HealthProblemKind, NameserverCheck};
+use crate::parser::dns_lint::{forward_delegations, reverse_delegations, Delegation};
+use crate::parser::dns_wire::{
+    key_tag, parse_ds_rdata, rcode_name, DnsResponse, RecordData, RCODE_NOERROR, TYPE_A, TYPE_AAAA, TYPE_DNSKEY, TYPE_NS, TYPE_SOA,
+};
+use crate::task::dns::GENERATE_DNS_TASK_ID;
+use crate::task::Task;
+use crate::{AppConfig, AppState};
+use anyhow::Context;
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub const CHECK_DELEGATIONS_TASK_ID: &str = "check_delegations";
+
+// Queries the nameservers of every delegation directly, without recursion
+pub struct DelegationChecker {
+    resolver: SocketAddr,
+    port: u16,
+    timeout: Duration,
+}
+
+fn name_set(names: impl Iterator<Item = String>) -> BTreeSet<String> {
+    names.map(|name| name.trim_end_matches('.').to_lowercase()).collect()
+}
+
+fn join(names: &BTreeSet<String>) -> String {
+    names.iter().cloned().collect::<Vec<_>>().join(", ")
+}
+
+impl DelegationChecker {
+    pub fn new(resolver: SocketAddr, port: u16, timeout: Duration) -> Self {
+        DelegationChecker { resolver, port, timeout }
+    }
+
+    pub fn from_config(config: &AppConfig) -> anyhow::Result<Self> {
+        let resolver = config
+            .dns_health_resolver
+            .parse()
+            .with_context(|| format!("Invalid dns_health_resolver '{}'", config.dns_health_resolver))?;
+
+        Ok(DelegationChecker::new(resolver, config.dns_health_nameserver_port, Duration::from_secs(config.dns_health_timeout_seconds)))
+    }
+
+    fn query(&self, server: SocketAddr, name: &str, record_type: u16, recursion_desired: bool) -> Result<DnsResponse, String> {
+        dns_query(server, name, record_type, recursion_desired, self.timeout).map_err(|e| format!("{:#}", e))
+    }
+
+    // Authoritative answers only, anything else means the server is lame for the zone
+    fn authoritative_query(&self, server: SocketAddr, zone: &str, record_type: u16) -> Result<DnsResponse, String> {
+        let response = self.query(server, zone, record_type, false)?;
+
+        if response.rcode != RCODE_NOERROR {
+            return Err(format!("answered {}", rcode_name(response.rcode)));
+        }
+
+        if !response.authoritative {
+            return Err("answer is not authoritative".to_string());
+        }
+
+        Ok(response)
+    }
+
+    // A failing lookup of one address family does not hide the addresses of the other
+    fn resolve(&self, name: &str) -> Result<Vec<IpAddr>, String> {
+        let mut addresses = Vec::new();
+        let mut errors = Vec::new();
+
+        for record_type in [TYPE_A, TYPE_AAAA] {
+            let response = match self.query(self.resolver, name, record_type, true) {
+                Ok(response) if response.rcode == RCODE_NOERROR => response,
+                Ok(response) => {
+                    errors.push(format!("resolver answered {}", rcode_name(response.rcode)));
+                    continue;
+                }
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                }
+            };
+
+            addresses.extend(response.answers.iter().filter_map(|answer| match answer.data {
+                RecordData::A(address) => Some(IpAddr::V4(address)),
+                RecordData::Aaaa(address) => Some(IpAddr::V6(address)),
+                _ => None,
+            }));
+        }
+
+        if !addresses.is_empty() {
+            return Ok(addresses);
+        }
+
+        if errors.is_empty() {
+            return Err("resolver returned no addresses".to_string());
+        }
+
+        errors.dedup();
+
+        Err(errors.join(", "))
+    }
+
+    fn soa_serial(&self, server: SocketAddr, zone: &str) -> Result<u32, String> {
+        self.authoritative_query(server, zone, TYPE_SOA)?
+            .answers
+            .iter()
+            .find_map(|answer| match answer.data {
+                RecordData::Soa { serial, .. } if answer.name == zone => Some(serial),
+                _ => None,
+            })
+            .ok_or_else(|| "no SOA record in the answer".to_string())
+    }
+
+    fn name_servers(&self, server: SocketAddr, zone: &str) -> Result<BTreeSet<String>, String> {
+        let response = self.authoritative_query(server, zone, TYPE_NS)?;
+
+        Ok(name_set(response.answers.into_iter().filter(|answer| answer.name == zone).filter_map(|answer| match answer.data {
+            RecordData::Ns(name) => Some(name),
+            _ => None,
+        })))
+    }
+
+    fn dnskeys(&self, server: SocketAddr, zone: &str) -> Result<Vec<(u8, Vec<u8>)>, String> {
+        let response = self.authoritative_query(server, zone, TYPE_DNSKEY)?;
+
+        Ok(response.answers.into_iter().filter(|answer| answer.name == zone).filter_map(|answer| match answer.data {
+            RecordData::Dnskey { algorithm, rdata } => Some((algorithm, rdata)),
+            _ => None,
+        }).collect())
+    }
+
+    fn check_ds(&self, delegation: &Delegation, zone: &str, nameserver: &str, server: SocketAddr) -> Option<String> {
+        let mut ds_records = Vec::new();
+
+        for ds_rdata in &delegation.ds_rdata {
+            match parse_ds_rdata(ds_rdata) {
+                Ok(ds) => ds_records.push(ds),
+                Err(e) => return Some(e),
+            }
+        }
+
+        let keys = match self.dnskeys(server, zone) {
+            Ok(keys) => keys,
+            Err(e) => return Some(format!("DNSKEY query to {} failed: {}", nameserver, e)),
+        };
+
+        if keys.is_empty() {
+            return Some(format!("The registry has DS records for {} but {} serves no DNSKEY", zone, nameserver));
+        }
+
+        if ds_records.iter().any(|ds| keys.iter().any(|(algorithm, rdata)| ds.matches(zone, *algorithm, rdata))) {
+            return None;
+        }
+
+        let ds_tags = ds_records.iter().map(|ds| ds.key_tag.to_string()).collect::<Vec<_>>();
+        let key_tags = keys.iter().map(|(_, rdata)| key_tag(rdata).to_string()).collect::<Vec<_>>();
+
+        Some(format!("No DS record (key tags {}) matches a DNSKEY served by {} (key tags {})", ds_tags.join(", "), nameserver, key_tags.join(", ")))
+    }
+
+    pub(crate) fn check(&self, delegation: &Delegation, checked: String) -> DomainHealth {
+        let zone = delegation.zone.as_str().trim_end_matches('.').to_string();
+        let registered = name_set(delegation.name_servers.iter().map(|ns| ns.name_server.to_string()));
+
+        let mut nameservers = Vec::new();
+        let mut problems = Vec::new();
+        let mut authoritative = None;
+
+        let mut problem = |kind, nameserver: &str, message| {
+            problems.push(HealthProblem { kind, nameserver: Some(nameserver.to_string()), message });
+        };
+
+        for (name_server, glue) in delegation.glue() {
+            let name = name_server.as_str().trim_end_matches('.');
+
+            let addresses = if glue.is_empty() {
+                match self.resolve(name) {
+                    Ok(addresses) => addresses,
+                    Err(e) => {
+                        problem(HealthProblemKind::Unresolvable, name, format!("{} has no glue and could not be resolved: {}", name, e));
+                        continue;
+                    }
+                }
+            } else {
+                glue.into_iter().collect()
+            };
+
+            for address in addresses {
+                let server = SocketAddr::new(address, self.port);
+                let serial = self.soa_serial(server, &zone);
+
+                nameservers.push(NameserverCheck {
+                    nameserver: name.to_string(),
+                    address: address.to_string(),
+                    authoritative: serial.is_ok(),
+                    serial: serial.as_ref().ok().copied(),
+                });
+
+                if let Err(e) = serial {
+                    problem(HealthProblemKind::Lame, name, format!("{} ({}) is lame for {}: {}", name, address, zone, e));
+                    continue;
+                }
+
+                authoritative.get_or_insert((name.to_string(), server));
+
+                match self.name_servers(server, &zone) {
+                    Ok(served) if served != registered => {
+                        let message = format!("{} ({}) serves NS {}, the registry lists {}", name, address, join(&served), join(&registered));
+                        problem(HealthProblemKind::NsMismatch, name, message);
+                    }
+                    Ok(_) => {}
+                    Err(e) => problem(HealthProblemKind::NsMismatch, name, format!("NS query to {} ({}) failed: {}", name, address, e)),
+                }
+            }
+        }
+
+        // DS records are compared against one authoritative server, the NS check covers the others
+        if !delegation.ds_rdata.is_empty()
+            && let Some((name, server)) = &authoritative
+            && let Some(message) = self.check_ds(delegation, &zone, name, *server)
+        {
+            problem(HealthProblemKind::DsMismatch, name, message);
+        }
+
+        DomainHealth {
+            zone,
+            file: delegation.file.clone(),
+            checked,
+            healthy: problems.is_empty(),
+            nameservers,
+            problems,
+            failures: Default::default(),
+        }
+    }
+}
+
+// Spreads the delegations over a fixed number of threads, results come back in input order
+fn check_all(checker: &DelegationChecker, delegations: &[Delegation], concurrency: usize, checked: &str) -> Vec<DomainHealth> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(delegations.len()));
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, delegations.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(delegation) = delegations.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let health = checker.check(delegation, checked.to_string());
+                    results.lock().unwrap().push(health);
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by(|a, b| a.zone.cmp(&b.zone));
+
+    results
+}
+
+pub struct CheckDelegationsTask {
+    app_state: AppState,
+}
+
+impl CheckDelegationsTask {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+}
+
+impl Task for CheckDelegationsTask {
+    fn name(&self) -> &str {
+        "Check Delegations"
+    }
+
+    fn id(&self) -> &str {
+        CHECK_DELEGATIONS_TASK_ID
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        vec![GENERATE_DNS_TASK_ID.to_string()]
+    }
+
+    fn run(&self) -> anyhow::Result<()> {
+        let state = &self.app_state;
+        let config = state.config.get();
+
+        if config.dns_health_resolver.is_empty() {
+            return Ok(());
+        }
+
+        let checker = DelegationChecker::from_config(&config)?;
+        let source = RegistrySource::from_config(&config)?;
+
+        if !source.exists() {
+            warn!("Git repository path {:?} does not exist. Skipping delegation health check.", source.repo());
+            return Ok(());
+        }
+
+        let mut delegations = forward_delegations(&source.records("DNS", &[&config.git_repo_dns_relative_path])?);
+        delegations.extend(reverse_delegations(&source.records("INETNUM", &[
+            &config.git_repo_inetnum_relative_path,
+            &config.git_repo_inet6num_relative_path,
+        ])?));
+
+        let checked = chrono::Utc::now().to_rfc3339();
+        let results = check_all(&checker, &delegations, config.dns_health_concurrency, &checked);
+
+        let mut data = state.dns_health.write().unwrap();
+
+        // Domains that are no longer delegated lose their history
+        let domains = results
+            .into_iter()
+            .map(|health| {
+                let previous = data.domains.get(&health.zone);
+                let health = health.with_history(previous, config.dns_health_history_size);
+                (health.zone.clone(), health)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        info!("Checked {} delegations, {} failing.", domains.len(), domains.values().filter(|health| !health.healthy).count());
+
+        data.domains = domains;
+        data.last_checked = Some(checked);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::registry::tests::record;
+    use crate::parser::dns_wire::tests::{encode_response, EXAMPLE_DNSKEY, EXAMPLE_DS};
+    use crate::parser::dns_wire::encode_name;
+    use std::net::UdpSocket;
+
+    // The question name and type, read from the raw query
+    fn question(query: &[u8]) -> (String, u16) {
+        let mut position = 12;
+        let mut labels = Vec::new();
+
+        while query[position] != 0 {
+            let length = query[position] as usize;
+            labels.push(String::from_utf8_lossy(&query[position + 1..position + 1 + length]).to_string());
+            position += 1 + length;
+        }
+
+        (labels.join("."), u16::from_be_bytes([query[position + 1], query[position + 2]]))
+    }
+
+    fn soa() -> Vec<u8> {
+        let mut soa = encode_name("ns1.good.dn42").unwrap();
+        soa.extend(encode_name("hostmaster.good.dn42").unwrap());
+        soa.extend(7u32.to_be_bytes());
+        soa.extend([0; 16]);
+        soa
+    }
+
+    // Stand-in for both the resolver and every delegated nameserver, answering on 127.0.0.1
+    fn stand_in_server() -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+
+        std::thread::spawn(move || {
+            let mut buffer = [0; 512];
+
+            while let Ok((length, from)) = socket.recv_from(&mut buffer) {
+                let query = &buffer[..length];
+                let (name, record_type) = question(query);
+
+                let response = match (name.as_str(), record_type) {
+                    ("lame.dn42", _) => encode_response(query, false, 5, &[]),
+                    (zone, TYPE_SOA) => encode_response(query, true, 0, &[(zone, TYPE_SOA, soa())]),
+                    ("good.dn42" | "dskey.example.com", TYPE_NS) => encode_response(query, true, 0, &[
+                        (&name, TYPE_NS, encode_name("ns1.good.dn42").unwrap()),
+                    ]),
+                    ("mismatch.dn42", TYPE_NS) => encode_response(query, true, 0, &[
+                        (&name, TYPE_NS, encode_name("ns1.good.dn42").unwrap()),
+                        (&name, TYPE_NS, encode_name("ns2.good.dn42").unwrap()),
+                    ]),
+                    ("ns1.good.dn42", TYPE_A) => encode_response(query, false, 0, &[(&name, TYPE_A, vec![127, 0, 0, 1])]),
+                    ("ns1.good.dn42", TYPE_AAAA) => encode_response(query, false, 2, &[]),
+                    ("dskey.example.com", TYPE_DNSKEY) => encode_response(query, true, 0, &[
+                        (&name, TYPE_DNSKEY, hex::decode(EXAMPLE_DNSKEY).unwrap()),
+                    ]),
+                    (_, TYPE_DNSKEY) => encode_response(query, true, 0, &[]),
+                    _ => encode_response(query, false, 3, &[]),
+                };
+
+                socket.send_to(&response, from).unwrap();
+            }
+        });
+
+        port
+    }
+
+    fn check(checker: &DelegationChecker, content: &str) -> DomainHealth {
+        let delegations = forward_delegations(&[record("data/dns/zone", content)]);
+        checker.check(&delegations[0], "now".to_string())
+    }
+
+    fn kinds(health: &DomainHealth) -> Vec<HealthProblemKind> {
+        health.problems.iter().map(|p| p.kind).collect()
+    }
+
+    #[test]
+    fn test_check_delegations() {
+        let port = stand_in_server();
+        let checker = DelegationChecker::new(SocketAddr::from(([127, 0, 0, 1], port)), port, Duration::from_secs(2));
+
+        let good = check(&checker, "domain: good.dn42\nnserver: ns1.good.dn42 127.0.0.1\n");
+        assert!(good.healthy, "{:?}", good.problems);
+        assert_eq!(good.nameservers, vec![NameserverCheck {
+            nameserver: "ns1.good.dn42".to_string(),
+            address: "127.0.0.1".to_string(),
+            authoritative: true,
+            serial: Some(7),
+        }]);
+
+        // Resolved through the resolver instead of glue, the SERVFAIL for AAAA does not hide the A record
+        let resolved = check(&checker, "domain: other.dn42\nnserver: ns1.good.dn42\n");
+        assert_eq!(resolved.nameservers.len(), 1);
+        assert_eq!(kinds(&resolved), vec![HealthProblemKind::NsMismatch]);
+
+        let lame = check(&checker, "domain: lame.dn42\nnserver: ns1.good.dn42 127.0.0.1\n");
+        assert_eq!(kinds(&lame), vec![HealthProblemKind::Lame]);
+        assert_eq!(lame.problems[0].message, "ns1.good.dn42 (127.0.0.1) is lame for lame.dn42: answered REFUSED");
+
+        let unresolvable = check(&checker, "domain: nowhere.dn42\nnserver: ns.nowhere.dn42\n");
+        assert_eq!(kinds(&unresolvable), vec![HealthProblemKind::Unresolvable]);
+        assert_eq!(unresolvable.problems[0].message, "ns.nowhere.dn42 has no glue and could not be resolved: resolver answered NXDOMAIN");
+
+        let mismatch = check(&checker, "domain: mismatch.dn42\nnserver: ns1.good.dn42 127.0.0.1\n");
+        assert_eq!(mismatch.problems[0].message, "ns1.good.dn42 (127.0.0.1) serves NS ns1.good.dn42, ns2.good.dn42, the registry lists ns1.good.dn42");
+
+        let signed = check(&checker, &format!("domain: dskey.example.com\nnserver: ns1.good.dn42 127.0.0.1\nds-rdata: {}\n", EXAMPLE_DS));
+        assert!(signed.healthy, "{:?}", signed.problems);
+
+        let unsigned = check(&checker, &format!("domain: good.dn42\nnserver: ns1.good.dn42 127.0.0.1\nds-rdata: {}\n", EXAMPLE_DS));
+        assert_eq!(kinds(&unsigned), vec![HealthProblemKind::DsMismatch]);
+    }
+}
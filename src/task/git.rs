@@ -0,0 +1,37 @@
+use crate::io::sync_git_repository;
+use crate::task::Task;
+use crate::AppState;
+use std::path::Path;
+
+pub const SYNC_GIT_REPOSITORY_TASK_ID: &str = "sync_registry";
+
+pub struct SyncGitRepositoryTask {
+    app_state: AppState,
+}
+
+impl SyncGitRepositoryTask {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+}
+
+impl Task for SyncGitRepositoryTask {
+    fn name(&self) -> &str {
+        "Sync Git Repository"
+    }
+
+    fn id(&self) -> &str {
+        SYNC_GIT_REPOSITORY_TASK_ID
+    }
+
+    fn run(&self) -> anyhow::Result<()> {
+        let config = self.app_state.config.get();
+
+        // Tasks run on the blocking thread pool, so we may block on the runtime here
+        tokio::runtime::Handle::current().block_on(sync_git_repository(
+            &config.git_repo_url,
+            Path::new(&config.git_repo_local_path),
+            config.do_git_pull,
+        ))
+    }
+}
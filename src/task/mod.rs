@@ -1,7 +1,35 @@
 pub mod roa;
 pub mod dns;
+pub mod dns_health;
+pub mod git;
+pub mod registry;
+
+use crate::task::dns::GenerateDNSAuthoritativeZonesTask;
+use crate::task::dns_health::CheckDelegationsTask;
+use crate::task::git::SyncGitRepositoryTask;
+use crate::task::registry::IndexRegistryTask;
+use crate::task::roa::GenerateRoaTask;
+use crate::AppState;
+use std::sync::Arc;
 
 pub trait Task: Send + Sync {
     fn name(&self) -> &str;
+    // Stable identifier used in configuration, dependencies and the status endpoint
+    fn id(&self) -> &str;
     fn run(&self) -> anyhow::Result<()>;
-}
\ No newline at end of file
+
+    // Tasks that must complete successfully before this one runs, can be overridden in config
+    fn dependencies(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+pub fn default_tasks(state: &AppState) -> Vec<Arc<dyn Task>> {
+    vec![
+        Arc::new(SyncGitRepositoryTask::new(state.clone())),
+        Arc::new(GenerateRoaTask::new(state.clone())),
+        Arc::new(GenerateDNSAuthoritativeZonesTask::new(state.clone())),
+        Arc::new(IndexRegistryTask::new(state.clone())),
+        Arc::new(CheckDelegationsTask::new(state.clone())),
+    ]
+}
@@ -0,0 +1,55 @@
+use crate::io::RegistrySource;
+use crate::model::registry::Registry;
+use crate::task::git::SYNC_GIT_REPOSITORY_TASK_ID;
+use crate::task::Task;
+use crate::AppState;
+use tracing::{info, warn};
+
+pub const INDEX_REGISTRY_TASK_ID: &str = "index_registry";
+
+pub struct IndexRegistryTask {
+    app_state: AppState,
+}
+
+impl IndexRegistryTask {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+}
+
+impl Task for IndexRegistryTask {
+    fn name(&self) -> &str {
+        "Index Registry"
+    }
+
+    fn id(&self) -> &str {
+        INDEX_REGISTRY_TASK_ID
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        vec![SYNC_GIT_REPOSITORY_TASK_ID.to_string()]
+    }
+
+    fn run(&self) -> anyhow::Result<()> {
+        let state = &self.app_state;
+        let config = state.config.get();
+
+        let source = RegistrySource::from_config(&config)?;
+
+        if !source.has_directory(&config.git_repo_data_relative_path) {
+            warn!("Registry data path {:?} does not exist in {:?}. Skipping registry indexing.", config.git_repo_data_relative_path, source.repo());
+            return Ok(());
+        }
+
+        // Read from the same source as ROA and DNS generation, so lookups agree with a pinned revision
+        let object_directories = source.directories(&config.git_repo_data_relative_path)?;
+        let records = source.records("REGISTRY", &object_directories.iter().map(String::as_str).collect::<Vec<_>>())?;
+        let registry = Registry::new(records);
+
+        info!("Indexed {} registry objects.", registry.len());
+
+        *state.registry.write().unwrap() = registry;
+
+        Ok(())
+    }
+}
@@ -1,10 +1,19 @@
-use crate::io::get_records_from_dirs;
-use crate::model::output::RpkiClientOutput;
-use crate::parser::route::get_parsed_roa_routes;
+use crate::formatter::roa::{format_roa, RoaFormat};
+use crate::io::{read_location, RegistrySource};
+use crate::model::asn::parse_asn;
+use crate::model::output::{CompressionMetadata, RpkiClientOutput, ROA};
+use crate::parser::aggregate::compress_roas;
+use crate::parser::aspa::get_parsed_aspas;
+use crate::parser::route::{build_roa_index, get_parsed_roa_routes, RouteOptions};
+use crate::parser::source::{merge_roa_sources, parse_roa_source};
+use crate::server::content::CachedContent;
+use crate::server::events::UpdateEvent;
+use crate::task::git::SYNC_GIT_REPOSITORY_TASK_ID;
 use crate::task::Task;
-use crate::AppState;
-use std::path::Path;
-use tracing::warn;
+use crate::{AppState, RoaSourceConfig, RoaSourceStatus};
+use std::collections::{BTreeSet, HashMap};
+use strum::IntoEnumIterator;
+use tracing::{info, warn};
 
 pub struct GenerateRoaTask {
     app_state: AppState,
@@ -16,37 +25,249 @@ impl GenerateRoaTask {
     }
 }
 
+// Reads every source independently, a failing source keeps the content of its last successful read
+fn load_roa_sources(sources: &[RoaSourceConfig], previous: &[RoaSourceStatus]) -> Vec<RoaSourceStatus> {
+    sources
+        .iter()
+        .map(|source| {
+            let mut status = previous
+                .iter()
+                .find(|status| status.name == source.name && status.location == source.location)
+                .cloned()
+                .unwrap_or_else(|| RoaSourceStatus {
+                    name: source.name.clone(),
+                    location: source.location.clone(),
+                    ..Default::default()
+                });
+
+            let ta = if source.trust_anchor.is_empty() { &source.name } else { &source.trust_anchor };
+
+            match read_location(&source.location).and_then(|content| parse_roa_source(&content, source.format, ta)) {
+                Ok(parsed) => {
+                    for warning in &parsed.warnings {
+                        warn!("ROA source {}: {}", source.name, warning);
+                    }
+
+                    status.roas = parsed.roas.len();
+                    status.warnings = parsed.warnings.clone();
+                    status.last_success = Some(chrono::Utc::now().to_rfc3339());
+                    status.last_error = None;
+                    status.parsed = Some(parsed);
+                }
+                Err(e) => {
+                    warn!("ROA source {} failed, keeping its previous ROAs: {:#}", source.name, e);
+                    status.last_error = Some(format!("{:#}", e));
+                }
+            }
+
+            status
+        })
+        .collect()
+}
+
+// Filters apply to the ROAs as published by their authors, so sources are merged and
+// filtered before compression can fold a filtered prefix into a surviving supernet
+fn finish_roas(output: &mut RpkiClientOutput, sources: &[RoaSourceStatus], compression: bool) {
+    if !sources.is_empty() {
+        let parsed = sources.iter().filter_map(|source| source.parsed.as_ref()).collect::<Vec<_>>();
+
+        let before = output.roas.len();
+        let filtered = merge_roa_sources(&mut output.roas, &parsed);
+
+        info!("Merged {} ROA sources: {} ROAs added, {} removed by prefix filters.",
+            parsed.len(), (output.roas.len() + filtered).saturating_sub(before), filtered);
+
+        output.update_counts();
+    }
+
+    if compression {
+        let before = output.roas.len() as u64;
+        output.roas = compress_roas(&output.roas);
+        let after = output.roas.len() as u64;
+
+        info!("Compressed {} ROA entries to {}.", before, after);
+
+        output.update_counts();
+        output.metadata.compression = Some(CompressionMetadata { before, after });
+    }
+}
+
 impl Task for GenerateRoaTask {
     fn name(&self) -> &str {
         "Generate ROA"
     }
 
+    fn id(&self) -> &str {
+        "generate_roa"
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        vec![SYNC_GIT_REPOSITORY_TASK_ID.to_string()]
+    }
+
     fn run(&self) -> anyhow::Result<()> {
         let state = &self.app_state;
+        let config = state.config.get();
+
+        let source = RegistrySource::from_config(&config)?;
 
-        let git_repo_local_path = Path::new(&state.config.git_repo_local_path);
+        let (mut output, diagnostics) = if source.exists() {
+            let route_records = source.records("ROA", &[
+                &config.git_repo_ipv4_route_relative_path,
+                &config.git_repo_ipv6_route_relative_path,
+            ])?;
 
-        let output = if git_repo_local_path.exists() {
-            let route_directories = [
-                git_repo_local_path.join(&state.config.git_repo_ipv4_route_relative_path),
-                git_repo_local_path.join(&state.config.git_repo_ipv6_route_relative_path)
-            ];
+            let aut_num_records = if source.has_directory(&config.git_repo_aut_num_relative_path) {
+                Some(source.records("AUT-NUM", &[&config.git_repo_aut_num_relative_path])?)
+            } else {
+                warn!("aut-num directory {:?} does not exist. Skipping ROA origin check and ASPA generation.", config.git_repo_aut_num_relative_path);
+                None
+            };
 
-            let route_records = get_records_from_dirs("ROA", route_directories.iter())?;
+            let registered_origins = aut_num_records
+                .as_ref()
+                .map(|records| records.iter().filter_map(|record| parse_asn(record.primary_key())).collect());
 
-            get_parsed_roa_routes(&route_records)
+            let options = RouteOptions {
+                normalise_prefixes: config.roa_normalise_prefixes,
+                registered_origins,
+                exclude_unregistered_origins: config.roa_exclude_unregistered_origins,
+            };
+
+            let (mut output, diagnostics) = get_parsed_roa_routes(&route_records, &options);
+
+            if !config.aspa_provider_attribute.is_empty() {
+                let aspas = get_parsed_aspas(aut_num_records.as_deref().unwrap_or_default(), &config.aspa_provider_attribute.to_lowercase());
+
+                output.aspas = Some(aspas);
+                output.update_counts();
+            }
+
+            output.metadata.commit = source.commit();
+
+            (output, diagnostics)
         } else {
-            warn!("Git repository path {:?} does not exist. Skipping JSON ROA generation.", git_repo_local_path);
+            warn!("Git repository path {:?} does not exist. Skipping JSON ROA generation.", source.repo());
+
+            (RpkiClientOutput::default(), Vec::new())
+        };
 
-            RpkiClientOutput::default()
+        let previous_sources = state.roa_data.read().unwrap().sources.clone();
+        let sources = load_roa_sources(&config.roa_sources, &previous_sources);
+
+        finish_roas(&mut output, &sources, config.roa_compression);
+
+        let (roas_unchanged, rendering_unchanged) = {
+            let mut data = state.roa_data.write().unwrap();
+            // Diagnostics can change without the ROA set changing, e.g. a newly rejected duplicate
+            data.diagnostics = diagnostics;
+            data.sources = sources;
+
+            // Keep the previous expiry until half of the validity has passed, so that an
+            // unchanged registry does not produce a new ROA set on every run
+            let generated = output.metadata.generated;
+            let validity = config.roa_expiry_seconds as i64;
+
+            let expires = match data.output.expires() {
+                _ if validity == 0 => 0,
+                Some(expires) if expires - generated > validity / 2 => expires,
+                _ => generated + validity,
+            };
+
+            output.stamp(&config.roa_trust_anchor, expires);
+
+            let cached = !data.json_content.content().is_empty();
+
+            (
+                cached && data.output.roas == output.roas && data.output.aspas == output.aspas,
+                // A reloaded json style or a new commit changes the rendered output, not the ROA set
+                cached && data.json_style == config.roa_json_style && data.output.metadata.commit == output.metadata.commit,
+            )
+        };
+
+        if roas_unchanged && rendering_unchanged {
+            info!("ROA set unchanged, keeping cached output.");
+            return Ok(());
+        }
+
+        let now = std::time::SystemTime::now();
+        let json_content = CachedContent::new("application/json", format_roa(&output, RoaFormat::Json, config.roa_json_style)?, now);
+        let index = build_roa_index(&output.roas);
+
+        let mut formatted_content = HashMap::new();
+
+        for format in RoaFormat::iter().filter(|f| *f != RoaFormat::Json) {
+            formatted_content.insert(format, CachedContent::new(format.content_type(), format_roa(&output, format, config.roa_json_style)?, now));
+        }
+
+        let commit = output.metadata.commit.clone();
+        let without_expiry = |roas: &[ROA]| roas.iter().map(|roa| ROA { expires: 0, ..roa.clone() }).collect::<BTreeSet<_>>();
+        let history_items = without_expiry(&output.roas);
+        let (roas, aspas) = (output.roas.len(), output.aspas.as_ref().map_or(0, |aspas| aspas.len()));
+
+        let (added, removed) = {
+            let mut data_lock = state.roa_data.write().unwrap();
+
+            let previous = without_expiry(&data_lock.output.roas);
+            let changes = (history_items.difference(&previous).count(), previous.difference(&history_items).count());
+
+            if !roas_unchanged {
+                data_lock.last_updated = now;
+            }
+
+            data_lock.output = output;
+            data_lock.json_content = json_content;
+            data_lock.formatted_content = formatted_content;
+            data_lock.json_style = config.roa_json_style;
+            data_lock.index = index;
+
+            changes
         };
 
-        let mut data_lock = state.roa_data.write().unwrap();
+        if roas_unchanged {
+            info!("ROA set unchanged, re-rendered cached output.");
+            return Ok(());
+        }
 
-        data_lock.last_updated = std::time::SystemTime::now();
-        data_lock.json_content = serde_json::to_string_pretty(&output)?;
+        let version = state.roa_history.write().unwrap().push(commit.clone(), history_items, config.history_size);
+
+        state.updates.send(UpdateEvent::Roa { version, commit, roas, aspas, added, removed });
 
         Ok(())
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::record::Prefix;
+    use crate::parser::source::{ParsedRoaSource, PrefixFilter};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_filters_apply_before_compression() {
+        let roa = |prefix: &str, max_length| ROA { asn: 4242420000, prefix: prefix.to_string(), max_length, ..Default::default() };
+
+        let mut output = RpkiClientOutput {
+            roas: vec![roa("10.0.0.0/16", 23), roa("10.0.0.0/24", 24)],
+            ..Default::default()
+        };
+
+        let sources = vec![RoaSourceStatus {
+            parsed: Some(ParsedRoaSource {
+                roas: vec![roa("10.0.1.0/24", 24)],
+                filters: vec![PrefixFilter { prefix: Some(Prefix::from_str("10.0.0.0/24").unwrap()), asn: None }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }];
+
+        finish_roas(&mut output, &sources, true);
+
+        // Compressed first, 10.0.0.0/23 max 24 would survive the filter and keep 10.0.0.0/24 valid
+        let roas = output.roas.iter().map(|roa| format!("{} {}", roa.prefix, roa.max_length)).collect::<Vec<_>>();
+        assert_eq!(roas, vec!["10.0.0.0/16 23", "10.0.1.0/24 24"]);
+        assert_eq!(output.metadata.compression, Some(CompressionMetadata { before: 2, after: 2 }));
+    }
+}